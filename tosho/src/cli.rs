@@ -115,6 +115,57 @@ pub(crate) enum ToshoCommands {
         #[command(subcommand)]
         subcommand: crate::r#impl::nids::NIDSCommands,
     },
+    /// Show the upcoming releases of your followed titles across sources
+    ///
+    /// Currently supports MU!, KM, AM, and M+, using every saved account of each source.
+    Calendar {
+        /// The amount of days to show starting from today
+        #[arg(short = 'd', long, default_value_t = 7)]
+        days: u32,
+        /// Only check the specified sources
+        #[arg(short = 's', long = "source", value_enum)]
+        sources: Vec<crate::r#impl::calendar::CalendarSource>,
+        /// Export the calendar into an iCalendar (.ics) file
+        #[arg(long)]
+        ics: Option<std::path::PathBuf>,
+    },
+    /// Manage every saved account across sources
+    Accounts {
+        #[command(subcommand)]
+        subcommand: crate::r#impl::accounts::AccountsCommands,
+    },
+    /// Keep running and sync titles on a schedule
+    ///
+    /// Each job in the schedule runs the `autodownload` command of its source,
+    /// stop it with Ctrl+C (or SIGTERM) to finish the current page and save the queue.
+    Daemon {
+        /// The schedule file to use, defaults to `daemon.json` in the user config directory
+        #[arg(short = 'c', long = "config", default_value = None)]
+        config: Option<std::path::PathBuf>,
+        /// Write the structured logs (JSON lines) into this file, defaults to `daemon.log`
+        #[arg(long = "log-file", default_value = None)]
+        log_file: Option<std::path::PathBuf>,
+    },
+    /// Serve a local HTTP API to search and queue downloads remotely
+    ///
+    /// Every request needs the `Authorization: Bearer <token>` header.
+    #[cfg(feature = "with-api")]
+    Serve {
+        /// The address to listen on
+        #[arg(short = 'b', long = "bind", default_value = "127.0.0.1:8642")]
+        bind: std::net::SocketAddr,
+        /// The API token, uses `TOSHO_API_TOKEN` or the saved `api_token` file when omitted
+        ///
+        /// A new token is generated and saved into `api_token` in the user config directory
+        /// when none is available.
+        #[arg(long, default_value = None)]
+        token: Option<String>,
+        /// The directory every download is written under, defaults to `DOWNLOADS`
+        ///
+        /// The `output` of a download request is resolved relative to it.
+        #[arg(short = 'o', long = "output-root", default_value = None)]
+        output_root: Option<std::path::PathBuf>,
+    },
     /// Additional tools to manage your downloaded manga
     Tools {
         #[command(subcommand)]
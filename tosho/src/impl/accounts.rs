@@ -0,0 +1,422 @@
+//! Check the health of every saved account across sources.
+//!
+//! Each account does a single cheap authenticated request, the failure is then
+//! classified from the returned error so it can be used in a scheduled job.
+
+use clap::Subcommand;
+use color_print::cformat;
+use num_format::{Locale, ToFormattedString};
+use tosho_common::{ToshoAuthError, ToshoError, ToshoParseError, ToshoResult, make_error};
+
+use crate::{
+    cli::ExitCode,
+    config::{ConfigImpl, get_all_config},
+    r#impl::{Implementations, client, common::unix_timestamp_to_string},
+};
+
+#[derive(Subcommand)]
+pub(crate) enum AccountsCommands {
+    /// Check every saved account across all sources
+    ///
+    /// Exits with a non-zero code if any account is expired, banned, or failed to be checked.
+    Check,
+}
+
+/// The result of checking a single account
+#[derive(Debug, Clone, PartialEq)]
+enum AccountStatus {
+    /// The session is still usable
+    Valid,
+    /// The session is expired or revoked, need to login again
+    Expired(String),
+    /// The account is forbidden from accessing the source
+    Banned(String),
+    /// The account can't be checked, e.g. network or parsing error
+    Broken(String),
+}
+
+impl AccountStatus {
+    fn is_valid(&self) -> bool {
+        matches!(self, AccountStatus::Valid)
+    }
+
+    fn label(&self) -> String {
+        match self {
+            AccountStatus::Valid => cformat!("<g,s>VALID</>"),
+            AccountStatus::Expired(_) => cformat!("<y,s>EXPIRED</>"),
+            AccountStatus::Banned(_) => cformat!("<r,s>BANNED</>"),
+            AccountStatus::Broken(_) => cformat!("<r,s>ERROR</>"),
+        }
+    }
+
+    fn reason(&self) -> Option<&str> {
+        match self {
+            AccountStatus::Valid => None,
+            AccountStatus::Expired(reason)
+            | AccountStatus::Banned(reason)
+            | AccountStatus::Broken(reason) => Some(reason),
+        }
+    }
+}
+
+fn status_from_code(code: reqwest::StatusCode, reason: String) -> AccountStatus {
+    match code {
+        reqwest::StatusCode::UNAUTHORIZED => AccountStatus::Expired(reason),
+        reqwest::StatusCode::FORBIDDEN => AccountStatus::Banned(reason),
+        _ => AccountStatus::Broken(reason),
+    }
+}
+
+/// Classify the error from the authenticated request into an [`AccountStatus`].
+fn classify_error(err: &ToshoError) -> AccountStatus {
+    let reason = err.to_string();
+    match err {
+        ToshoError::AuthError(_) => AccountStatus::Expired(reason),
+        ToshoError::ParseError(ToshoParseError::InvalidStatusCode(code)) => {
+            status_from_code(*code, reason)
+        }
+        ToshoError::RequestError(req_err) => match req_err.status() {
+            Some(code) => status_from_code(code, reason),
+            None => AccountStatus::Broken(reason),
+        },
+        _ => AccountStatus::Broken(reason),
+    }
+}
+
+fn format_date(timestamp: i64) -> String {
+    unix_timestamp_to_string(timestamp).unwrap_or_else(|| timestamp.to_string())
+}
+
+fn fmt_num(num: u64) -> String {
+    num.to_formatted_string(&Locale::en)
+}
+
+async fn check_musq(
+    account: &super::musq::config::Config,
+    proxy: Option<reqwest::Proxy>,
+) -> ToshoResult<Vec<String>> {
+    let client = client::make_musq_client(account)?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    let user_shop = client.get_point_shop().await?;
+    let point = user_shop.user_point().unwrap_or_default();
+    let mut details = vec![format!(
+        "Balance: {}c ({}c free, {}c XP, {}c paid)",
+        fmt_num(point.sum()),
+        fmt_num(point.free()),
+        fmt_num(point.event()),
+        fmt_num(point.paid())
+    )];
+    if let Some(subs) = user_shop.subscriptions().first() {
+        details.push(format!(
+            "Subscription: {} until {}",
+            subs.status().as_name(),
+            format_date(subs.end())
+        ));
+    }
+
+    Ok(details)
+}
+
+async fn check_kmkc(
+    account: &super::kmkc::config::Config,
+    proxy: Option<reqwest::Proxy>,
+) -> ToshoResult<Vec<String>> {
+    let mut details = vec![];
+    if let super::kmkc::config::Config::Web(web) = account {
+        let cookies = [&web.birthday, &web.tos_adult, &web.privacy];
+        if cookies.iter().any(|cookie| cookie.is_none()) {
+            return Err(ToshoAuthError::CommonError("missing session cookies".to_string()).into());
+        }
+
+        let expires = cookies
+            .iter()
+            .filter_map(|cookie| cookie.as_ref().map(|cookie| cookie.expires as i64))
+            .min()
+            .unwrap_or_default();
+        if expires < chrono::Utc::now().timestamp() {
+            return Err(ToshoAuthError::CommonError(format!(
+                "session cookies expired at {}",
+                format_date(expires)
+            ))
+            .into());
+        }
+        details.push(format!("Cookies expire: {}", format_date(expires)));
+    }
+
+    let client = client::make_kmkc_client(&account.clone().into())?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    let user_point = client.get_user_point().await?;
+    super::kmkc::accounts::save_session_config(&client, account);
+
+    let point = user_point.point();
+    details.insert(
+        0,
+        format!(
+            "Balance: {}P ({}P free, {}P paid), {} premium tickets",
+            fmt_num(point.total_point()),
+            fmt_num(point.free_point()),
+            fmt_num(point.paid_point()),
+            fmt_num(user_point.ticket().total_num())
+        ),
+    );
+
+    Ok(details)
+}
+
+async fn check_amap(
+    account: &super::amap::config::Config,
+    proxy: Option<reqwest::Proxy>,
+) -> ToshoResult<Vec<String>> {
+    let client = client::make_amap_client(&account.clone().into())?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    let remainder = client.get_remainder().await?;
+    super::amap::common::save_session_config(&client, account);
+
+    let balance = remainder.info();
+    Ok(vec![format!(
+        "Balance: {}T ({}T purchased, {}T premium), {}p",
+        fmt_num(balance.sum()),
+        fmt_num(balance.purchased()),
+        fmt_num(balance.premium()),
+        fmt_num(balance.sum_point())
+    )])
+}
+
+async fn check_sjv(
+    account: &super::sjv::config::Config,
+    proxy: Option<reqwest::Proxy>,
+) -> ToshoResult<Vec<String>> {
+    let client = client::make_sjv_client(account)?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    let entitlements = client.get_entitlements().await?;
+    let subs = entitlements.subscriptions();
+    let until = |valid_to: Option<i64>| match valid_to {
+        Some(valid_to) => format_date(valid_to),
+        None => "N/A".to_string(),
+    };
+
+    Ok(vec![format!(
+        "Subscription: SJ until {}, VM until {}",
+        until(subs.sj_valid_to()),
+        until(subs.vm_valid_to())
+    )])
+}
+
+async fn check_rbean(
+    account: &super::rbean::config::Config,
+    proxy: Option<reqwest::Proxy>,
+) -> ToshoResult<Vec<String>> {
+    let client = client::make_rbean_client(account)?;
+    let mut client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+    client.set_expiry_at(Some(account.expiry));
+
+    let user = client.get_user().await?;
+    super::rbean::common::save_session_config(&client, account);
+
+    let mut details = vec![];
+    if let Some(expiry_at) = client.get_expiry_at() {
+        details.push(format!("Token expires: {}", format_date(expiry_at)));
+    }
+    match user.premium_expiration_date() {
+        Some(date_at) => details.push(format!("Premium until: {date_at}")),
+        None => details.push("Premium: No".to_string()),
+    }
+
+    Ok(details)
+}
+
+async fn check_mplus(
+    account: &super::mplus::config::Config,
+    proxy: Option<reqwest::Proxy>,
+) -> ToshoResult<Vec<String>> {
+    let client = client::make_mplus_client(
+        account,
+        crate::r#impl::mplus::MPlusLanguage::default().into(),
+    )?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    match client.get_user_settings().await? {
+        tosho_mplus::APIResponse::Success(settings) => {
+            let subs_info = settings.subscription().cloned().unwrap_or_default();
+            Ok(vec![format!(
+                "Subscription: {}",
+                subs_info.plan().to_name()
+            )])
+        }
+        tosho_mplus::APIResponse::Error(e) => Err(make_error!(e.as_string())),
+    }
+}
+
+async fn check_nids(
+    account: &super::nids::config::Config,
+    proxy: Option<reqwest::Proxy>,
+) -> ToshoResult<Vec<String>> {
+    let client = client::make_nids_client(account)?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    let profile = client.get_profile().await?;
+    Ok(vec![format!(
+        "Balance: ${}",
+        tosho_nids::format_price(profile.balance())
+    )])
+}
+
+async fn check_account(
+    config: &ConfigImpl,
+    proxy: Option<reqwest::Proxy>,
+) -> (String, ToshoResult<Vec<String>>) {
+    match config {
+        ConfigImpl::Musq(c) => (c.id.clone(), check_musq(c, proxy).await),
+        ConfigImpl::Kmkc(c) => (c.get_id().to_string(), check_kmkc(c, proxy).await),
+        ConfigImpl::Amap(c) => (c.id.clone(), check_amap(c, proxy).await),
+        ConfigImpl::Sjv(c) => (c.id.clone(), check_sjv(c, proxy).await),
+        ConfigImpl::Rbean(c) => (c.id.clone(), check_rbean(c, proxy).await),
+        ConfigImpl::Mplus(c) => (c.id.clone(), check_mplus(c, proxy).await),
+        ConfigImpl::Nids(c) => (c.id.clone(), check_nids(c, proxy).await),
+    }
+}
+
+fn source_name(implementation: &Implementations) -> &'static str {
+    match implementation {
+        Implementations::Kmkc => "KM",
+        Implementations::Musq => "MU!",
+        Implementations::Amap => "AM",
+        Implementations::Sjv => "SJ/M",
+        Implementations::Rbean => "小豆",
+        Implementations::Mplus => "M+",
+        Implementations::Nids => "NI",
+    }
+}
+
+/// Check every saved account of every source and report the status.
+pub(crate) async fn accounts_check(
+    proxy: Option<reqwest::Proxy>,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let implementations = [
+        Implementations::Musq,
+        Implementations::Kmkc,
+        Implementations::Amap,
+        Implementations::Sjv,
+        Implementations::Rbean,
+        Implementations::Mplus,
+        Implementations::Nids,
+    ];
+
+    let mut statuses: Vec<AccountStatus> = vec![];
+    for implementation in implementations.iter() {
+        let configs = get_all_config(implementation, None);
+        if configs.is_empty() {
+            continue;
+        }
+
+        console.info(cformat!(
+            "Checking <s>{}</> accounts (<m,s>{}</> accounts)...",
+            source_name(implementation),
+            configs.len()
+        ));
+        for config in configs.iter() {
+            let (account_id, result) = check_account(config, proxy.clone()).await;
+            let (status, details) = match result {
+                Ok(details) => (AccountStatus::Valid, details),
+                Err(err) => (classify_error(&err), vec![]),
+            };
+
+            console.info(cformat!("  [{}] <m,s>{}</>", status.label(), account_id));
+            for detail in details.iter() {
+                console.info(format!("    {detail}"));
+            }
+            if let Some(reason) = status.reason() {
+                console.warn(format!("    {reason}"));
+            }
+            statuses.push(status);
+        }
+    }
+
+    if statuses.is_empty() {
+        console.warn("No accounts found!");
+        return 0;
+    }
+
+    let count =
+        |matcher: fn(&AccountStatus) -> bool| statuses.iter().filter(|s| matcher(s)).count();
+    let valid = count(|s| matches!(s, AccountStatus::Valid));
+    let expired = count(|s| matches!(s, AccountStatus::Expired(_)));
+    let banned = count(|s| matches!(s, AccountStatus::Banned(_)));
+    let broken = count(|s| matches!(s, AccountStatus::Broken(_)));
+    console.info(cformat!(
+        "Checked <s>{}</> accounts: <g,s>{}</> valid, <y,s>{}</> expired, <r,s>{}</> banned, <r,s>{}</> errored",
+        statuses.len(),
+        valid,
+        expired,
+        banned,
+        broken
+    ));
+
+    if statuses.iter().all(|status| status.is_valid()) {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_error() {
+        let expired: ToshoError = ToshoAuthError::InvalidSession.into();
+        assert!(matches!(
+            classify_error(&expired),
+            AccountStatus::Expired(_)
+        ));
+
+        let unauthorized: ToshoError = reqwest::StatusCode::UNAUTHORIZED.into();
+        assert!(matches!(
+            classify_error(&unauthorized),
+            AccountStatus::Expired(_)
+        ));
+
+        let forbidden: ToshoError = reqwest::StatusCode::FORBIDDEN.into();
+        assert!(matches!(
+            classify_error(&forbidden),
+            AccountStatus::Banned(_)
+        ));
+
+        let server_error: ToshoError = reqwest::StatusCode::INTERNAL_SERVER_ERROR.into();
+        assert!(matches!(
+            classify_error(&server_error),
+            AccountStatus::Broken(_)
+        ));
+        assert!(matches!(
+            classify_error(&make_error!("Something went wrong")),
+            AccountStatus::Broken(_)
+        ));
+    }
+}
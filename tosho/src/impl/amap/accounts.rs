@@ -5,21 +5,37 @@ use tosho_amap::AMClient;
 use crate::{
     cli::ExitCode,
     config::{get_all_config, save_config, try_remove_config},
-    r#impl::client::make_amap_client,
+    r#impl::{
+        client::make_amap_client,
+        credentials::{CredentialArg, resolve_credential},
+    },
 };
 
 use super::config::Config;
 
 pub async fn amap_account_login(
-    email: String,
-    password: String,
+    email: Option<String>,
+    password: CredentialArg,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
-    console.info(cformat!(
-        "Authenticating with email <m,s>{}</> and password <m,s>{}</>...",
-        email,
-        password
-    ));
+    let Some(email) =
+        resolve_credential(email, "TOSHO_AM_EMAIL", "Email", false, no_input, console)
+    else {
+        return 1;
+    };
+    let Some(password) = resolve_credential(
+        password,
+        "TOSHO_AM_PASSWORD",
+        "Password",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
+    console.info(cformat!("Authenticating with email <m,s>{}</>...", email));
 
     let all_configs = get_all_config(&crate::r#impl::Implementations::Amap, None);
 
@@ -31,7 +47,7 @@ pub async fn amap_account_login(
     let mut old_id: Option<String> = None;
     if let Some(old_config) = old_config {
         console.warn("Email already authenticated!");
-        let abort_it = console.confirm(Some("Do you want to replace it?"));
+        let abort_it = no_input || console.confirm(Some("Do you want to replace it?"));
         if !abort_it {
             console.info("Aborting...");
             return 0;
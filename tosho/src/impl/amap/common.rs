@@ -206,7 +206,7 @@ pub(super) async fn common_purchase_select(
     }
 }
 
-pub(super) fn save_session_config(client: &AMClient, config: &Config) {
+pub(crate) fn save_session_config(client: &AMClient, config: &Config) {
     let mut config = config.clone();
     let store = client.get_cookie_store();
 
@@ -10,12 +10,21 @@ use tosho_amap::{
 use crate::{
     cli::ExitCode,
     r#impl::{
-        common::{check_chapter_folder_existence, check_downloaded_image_count},
-        models::{ChapterDetailDump, MangaDetailDump},
+        common::{
+            INTERRUPTED_EXIT_CODE, check_chapter_folder_existence, check_downloaded_pages,
+            is_chapter_interrupted, is_shutdown_requested, record_chapter_pages,
+            record_written_chapter,
+        },
+        models::{ChapterDetailDump, MangaDetailDump, SourceDump},
+        provenance::{ChapterProvenance, PageSource, embed_chapter_metadata},
     },
 };
 
-use super::{common::common_purchase_select, config::Config};
+use super::{
+    common::common_purchase_select,
+    config::Config,
+    volumes::{dump_volumes, find_episode_volume, map_volumes, volume_key},
+};
 
 #[derive(Clone, Debug, Default)]
 pub(crate) struct AMDownloadCliConfig {
@@ -40,6 +49,14 @@ pub(crate) struct AMDownloadCliConfig {
 
     /// Auto download ignore any images checking but just check for folder existence
     pub(crate) only_check_folder: bool,
+    /// Embed the source metadata into the downloaded pages
+    pub(crate) embed_metadata: bool,
+    /// Only purchase the chapters with the free daily ticket
+    pub(crate) free_daily_only: bool,
+    /// Save the volume mapping into `_volumes.json`, the chapters are kept flat
+    pub(crate) write_volume_map: bool,
+    /// Only download the chapters included in these volumes
+    pub(crate) volumes: Vec<String>,
 }
 
 fn create_chapters_info(manga_detail: &ComicInfo) -> MangaDetailDump {
@@ -59,7 +76,7 @@ fn create_chapters_info(manga_detail: &ComicInfo) -> MangaDetailDump {
     MangaDetailDump::new(manga_detail.title().to_string(), merged_authors, chapters)
 }
 
-fn get_output_directory(
+pub(super) fn get_output_directory(
     output_dir: &Path,
     title_id: u64,
     chapter_id: Option<u64>,
@@ -100,28 +117,52 @@ pub(crate) async fn amap_download(
 
     match (results, manga_detail, user_bal) {
         (Ok(results), Some(manga_detail), Some(coin_purse)) => {
+            let use_volumes = dl_config.write_volume_map || !dl_config.volumes.is_empty();
+            let volume_books = if use_volumes {
+                match client.get_comic(title_id).await {
+                    Ok(comic) => comic.volumes().to_vec(),
+                    Err(err) => {
+                        console.warn(format!("Failed to fetch the volumes list: {err}"));
+                        vec![]
+                    }
+                }
+            } else {
+                vec![]
+            };
+            let volume_groups = map_volumes(&manga_detail, &volume_books);
+            let wanted_volumes: Vec<String> =
+                dl_config.volumes.iter().map(|v| volume_key(v)).collect();
+
             let results: Vec<&ComicEpisodeInfo> = results
                 .iter()
                 .filter(|&ch| {
+                    wanted_volumes.is_empty()
+                        || find_episode_volume(&volume_groups, ch.info().id())
+                            .is_some_and(|group| wanted_volumes.contains(&group.key))
+                })
+                .filter(|&ch| {
+                    // allow if chapter_ids is empty or chapter id is in chapter_ids
+                    let selected = dl_config.chapter_ids.is_empty()
+                        || dl_config.chapter_ids.contains(&(ch.info().id() as usize));
+
                     if dl_config.no_input {
                         // check if chapter id is in range
-                        match (dl_config.start_from, dl_config.end_at) {
-                            (Some(start), Some(end)) => {
-                                // between start and end
-                                ch.info().id() >= start && ch.info().id() <= end
-                            }
-                            (Some(start), None) => {
-                                ch.info().id() >= start // start to end
+                        selected
+                            && match (dl_config.start_from, dl_config.end_at) {
+                                (Some(start), Some(end)) => {
+                                    // between start and end
+                                    ch.info().id() >= start && ch.info().id() <= end
+                                }
+                                (Some(start), None) => {
+                                    ch.info().id() >= start // start to end
+                                }
+                                (None, Some(end)) => {
+                                    ch.info().id() <= end // 0 to end
+                                }
+                                _ => true,
                             }
-                            (None, Some(end)) => {
-                                ch.info().id() <= end // 0 to end
-                            }
-                            _ => true,
-                        }
                     } else {
-                        // allow if chapter_ids is empty or chapter id is in chapter_ids
-                        dl_config.chapter_ids.is_empty()
-                            || dl_config.chapter_ids.contains(&(ch.info().id() as usize))
+                        selected
                     }
                 })
                 .collect();
@@ -166,6 +207,15 @@ pub(crate) async fn amap_download(
                     continue;
                 }
 
+                if dl_config.free_daily_only && !consume.as_ref().is_some_and(|c| c.is_free_daily) {
+                    console.warn(cformat!(
+                        "  Chapter <m,s>{}</> (<s>{}</>) can't be read with the free daily ticket, skipping",
+                        chapter.info().title(),
+                        chapter.info().id()
+                    ));
+                    continue;
+                }
+
                 let mut should_purchase = dl_config.auto_purchase;
                 if !dl_config.auto_purchase && !dl_config.no_input {
                     let prompt = cformat!(
@@ -225,14 +275,27 @@ pub(crate) async fn amap_download(
             download_chapters.sort_by_key(|&a| a.info().id());
 
             let title_dir = get_output_directory(&output_dir, title_id, None, true);
-            let dump_info = create_chapters_info(&manga_detail);
+            let mut dump_info =
+                create_chapters_info(&manga_detail).with_source(SourceDump::Amap, title_id);
 
             let title_dump_path = title_dir.join("_info.json");
+            // keep the chapter information from the previous download
+            if let Some(older_info) = MangaDetailDump::load(&title_dump_path) {
+                dump_info.inherit_chapter_meta(&older_info);
+            }
             dump_info
                 .dump(&title_dump_path)
                 .expect("Failed to dump title info");
 
+            if use_volumes && let Err(err) = dump_volumes(&volume_groups, &title_dir) {
+                console.warn(format!("Failed to save the volume mapping: {err}"));
+            }
+
             for chapter in download_chapters {
+                if is_shutdown_requested() {
+                    console.warn("Shutdown requested, stopping the download");
+                    return INTERRUPTED_EXIT_CODE;
+                }
                 let info = chapter.info();
                 console.info(cformat!(
                     "  Downloading chapter <m,s>{}</> ({})...",
@@ -276,6 +339,17 @@ pub(crate) async fn amap_download(
                 let ch_pages = ch_view.info().pages();
                 let ch_dir = get_output_directory(&output_dir, title_id, Some(info.id()), false);
 
+                record_chapter_pages(
+                    &mut dump_info,
+                    &title_dump_path,
+                    info.id(),
+                    ch_pages.len(),
+                    console,
+                );
+
+                let page_stems: Vec<String> = (0..ch_pages.len())
+                    .map(|idx| format!("p{idx:03}"))
+                    .collect();
                 if dl_config.only_check_folder {
                     if check_chapter_folder_existence(&ch_dir) {
                         console.info(cformat!(
@@ -285,9 +359,7 @@ pub(crate) async fn amap_download(
                         ));
                         continue;
                     }
-                } else if let Some(count) = check_downloaded_image_count(&ch_dir, "jpg")
-                    && count >= ch_pages.len()
-                {
+                } else if check_downloaded_pages(&ch_dir, &page_stems) {
                     console.warn(cformat!(
                         "   Chapter <m,s>{}</> (<s>{}</>) has been downloaded, skipping",
                         info.title(),
@@ -302,6 +374,11 @@ pub(crate) async fn amap_download(
                 // download images
                 let total_image_count = ch_pages.len() as u64;
                 for (idx, image) in ch_pages.iter().enumerate() {
+                    // stop before the next page when shutting down
+                    if is_shutdown_requested() {
+                        break;
+                    }
+
                     let img_fn = format!("p{idx:03}.jpg");
                     let img_dl_path = ch_dir.join(&img_fn);
                     // async download
@@ -331,6 +408,34 @@ pub(crate) async fn amap_download(
                     }
                 }
                 console.stop_progress(Some("Downloaded".to_string()));
+
+                if is_chapter_interrupted(&ch_dir, &page_stems) {
+                    console.warn(cformat!(
+                        "   Shutdown requested, chapter <m,s>{}</> (<s>{}</>) is left incomplete",
+                        info.title(),
+                        info.id()
+                    ));
+                    return INTERRUPTED_EXIT_CODE;
+                }
+                record_written_chapter(&ch_dir);
+
+                if dl_config.embed_metadata {
+                    let provenance = ChapterProvenance {
+                        source: SourceDump::Amap,
+                        title: dump_info.title_name.clone(),
+                        title_id: title_id.into(),
+                        chapter: info.title().to_string(),
+                        chapter_id: info.id().into(),
+                    };
+                    let page_sources: Vec<PageSource> = ch_pages
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, image)| {
+                            PageSource::new(format!("p{idx:03}"), Some(image.info().url()))
+                        })
+                        .collect();
+                    embed_chapter_metadata(&ch_dir, &provenance, page_sources, console).await;
+                }
             }
 
             0
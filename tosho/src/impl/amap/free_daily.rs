@@ -0,0 +1,293 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use color_print::cformat;
+use serde::{Deserialize, Serialize};
+use tosho_amap::{AMClient, models::ComicInfo};
+
+use crate::{
+    cli::ExitCode,
+    config::get_user_path,
+    r#impl::common::{check_chapter_folder_existence, unix_timestamp_to_string},
+};
+
+use super::{
+    config::Config,
+    download::{AMDownloadCliConfig, amap_download, get_output_directory},
+};
+
+/// The free daily state of a single title.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FreeDailyTitle {
+    /// The title name
+    title: String,
+    /// The next free daily time in UNIX timestamp
+    next: u64,
+    /// The episodes that has been collected
+    collected: Vec<u64>,
+}
+
+struct FreeDailyTracker {
+    path: PathBuf,
+    titles: BTreeMap<u64, FreeDailyTitle>,
+}
+
+impl FreeDailyTracker {
+    fn load(account: &Config) -> Self {
+        let path = get_user_path().join(format!("amap_free_daily_{}.json", account.id));
+        let titles = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+
+        Self { path, titles }
+    }
+
+    fn save(&self) {
+        if let Ok(data) = serde_json::to_vec(&self.titles) {
+            let _ = std::fs::write(&self.path, data);
+        }
+    }
+}
+
+/// Get the episodes that should be downloaded for the title.
+///
+/// This includes any rented episodes that has not been collected yet (so they are
+/// downloaded before the rental expires) and the next free daily episode if the free
+/// daily ticket is available now.
+fn free_daily_targets(info: &ComicInfo, collected: &[u64], now: i64) -> Vec<u64> {
+    let mut episodes: Vec<_> = info.episodes().iter().map(|ep| ep.info()).collect();
+    episodes.sort_by_key(|ep| ep.id());
+
+    let mut targets: Vec<u64> = episodes
+        .iter()
+        .filter(|ep| !ep.is_free() && ep.is_available() && !collected.contains(&ep.id()))
+        .map(|ep| ep.id())
+        .collect();
+
+    let ready = info.has_free_daily()
+        && info
+            .free_daily()
+            .is_some_and(|free_daily| free_daily.next() as i64 <= now);
+    if ready
+        && let Some(next_ep) = episodes
+            .iter()
+            .find(|ep| ep.is_free_daily() && !ep.is_available() && !collected.contains(&ep.id()))
+    {
+        targets.push(next_ep.id());
+    }
+
+    targets
+}
+
+fn format_next(next: u64) -> String {
+    unix_timestamp_to_string(next as i64).unwrap_or_else(|| next.to_string())
+}
+
+pub(crate) async fn amap_free_daily(
+    title_ids: Vec<u64>,
+    output_dir: PathBuf,
+    client: &AMClient,
+    account: &Config,
+    console: &mut crate::term::Terminal,
+) -> ExitCode {
+    let title_ids = if title_ids.is_empty() {
+        console.info("Fetching favorites list...");
+        match client.get_favorites().await {
+            Ok(favorites) => favorites
+                .comics()
+                .iter()
+                .map(|comic| comic.info().id())
+                .collect(),
+            Err(err) => {
+                console.error(format!("Failed to fetch favorites: {err}"));
+                return 1;
+            }
+        }
+    } else {
+        title_ids
+    };
+
+    if title_ids.is_empty() {
+        console.warn("No titles to check, add some titles to your favorites or use --titles");
+        return 0;
+    }
+
+    let mut tracker = FreeDailyTracker::load(account);
+    let mut exit_code = 0;
+    for title_id in title_ids {
+        let now = chrono::Utc::now().timestamp();
+        let state = tracker.titles.entry(title_id).or_default();
+        if state.next as i64 > now {
+            console.info(cformat!(
+                "Skipping <m,s>{}</> ({}), next free daily at <s>{}</>",
+                state.title,
+                title_id,
+                format_next(state.next)
+            ));
+            continue;
+        }
+
+        let comic = match client.get_comic(title_id).await {
+            Ok(comic) => comic,
+            Err(err) => {
+                console.error(format!("Failed to fetch title {title_id}: {err}"));
+                exit_code = 1;
+                continue;
+            }
+        };
+
+        let info = comic.info();
+        state.title = info.title().to_string();
+        if !info.has_free_daily() {
+            console.info(cformat!(
+                "Skipping <m,s>{}</> ({}), no free daily available",
+                info.title(),
+                title_id
+            ));
+            continue;
+        }
+
+        let targets = free_daily_targets(info, &state.collected, now);
+        if targets.is_empty() {
+            if let Some(free_daily) = info.free_daily() {
+                state.next = free_daily.next();
+            }
+            console.info(cformat!(
+                "Nothing to collect for <m,s>{}</> ({}), next free daily at <s>{}</>",
+                info.title(),
+                title_id,
+                format_next(state.next)
+            ));
+            continue;
+        }
+
+        console.info(cformat!(
+            "Collecting <s>{}</> episodes for <m,s>{}</> ({})...",
+            targets.len(),
+            info.title(),
+            title_id
+        ));
+        let dl_config = AMDownloadCliConfig {
+            no_input: true,
+            auto_purchase: true,
+            free_daily_only: true,
+            no_premium: true,
+            no_purchased: true,
+            chapter_ids: targets.iter().map(|&id| id as usize).collect(),
+            ..Default::default()
+        };
+        let code = amap_download(
+            title_id,
+            dl_config,
+            output_dir.clone(),
+            client,
+            account,
+            console,
+        )
+        .await;
+        if code != 0 {
+            exit_code = code;
+        }
+
+        // record what has been collected and when the next free daily is
+        let state = tracker.titles.entry(title_id).or_default();
+        for episode_id in targets {
+            let ch_dir = get_output_directory(&output_dir, title_id, Some(episode_id), false);
+            if check_chapter_folder_existence(&ch_dir) && !state.collected.contains(&episode_id) {
+                state.collected.push(episode_id);
+            }
+        }
+
+        match client.get_comic(title_id).await {
+            Ok(comic) => {
+                if let Some(free_daily) = comic.info().free_daily() {
+                    state.next = free_daily.next();
+                    console.info(cformat!(
+                        "  Next free daily at <s>{}</>",
+                        format_next(state.next)
+                    ));
+                }
+            }
+            Err(err) => console.warn(format!("  Failed to fetch the next free daily: {err}")),
+        }
+
+        tracker.save();
+    }
+
+    tracker.save();
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_episode(id: u64, is_free_daily: bool, expiry_time: u64) -> serde_json::Value {
+        serde_json::json!({
+            "comic_body_info": {
+                "story_no": id,
+                "title": format!("Episode {id}"),
+                "i_price": 30,
+                "update_timestamp": 0,
+                "thumbnail": "",
+                "likes": "0",
+                "comments": "0",
+                "total_page_count": 10,
+                "page_start_status": 0,
+                "is_free_daily_episode": is_free_daily,
+                "campaign_end_at": null,
+                "i_expire_time": expiry_time,
+                "close_time": null,
+                "included_volume": null,
+            }
+        })
+    }
+
+    fn make_comic(next: u64, episodes: Vec<serde_json::Value>) -> ComicInfo {
+        serde_json::from_value(serde_json::json!({
+            "title": "Comic",
+            "shoukai": "",
+            "update_date": null,
+            "next_update_date": null,
+            "cover_url": "",
+            "thumbnail_url": "",
+            "cont_url": null,
+            "comic_body_info_list": episodes,
+            "next_update_text": null,
+            "favorite": true,
+            "rental_term": "1",
+            "author_info_list": [],
+            "tag_info_list": [],
+            "likes": "0",
+            "comments": "0",
+            "complete": 2,
+            "production_participants": "",
+            "is_free_daily": true,
+            "free_daily": {
+                "next_free_daily_time": next,
+                "free_daily_term": "1",
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_free_daily_targets() {
+        let now = chrono::Utc::now().timestamp();
+        let rented_until = (now + 3600) as u64;
+        let episodes = vec![
+            make_episode(3, true, 0),
+            make_episode(1, false, rented_until),
+            make_episode(2, true, 0),
+        ];
+
+        // rented episode and the first free daily episode
+        let comic = make_comic(0, episodes.clone());
+        assert_eq!(free_daily_targets(&comic, &[], now), vec![1, 2]);
+        assert_eq!(free_daily_targets(&comic, &[1, 2], now), vec![3]);
+
+        // free daily is not available yet
+        let comic = make_comic(rented_until, episodes);
+        assert_eq!(free_daily_targets(&comic, &[], now), vec![1]);
+    }
+}
@@ -3,7 +3,7 @@ use tosho_amap::{AMClient, constants::BASE_HOST, models::ComicTagInfo};
 
 use crate::{cli::ExitCode, linkify};
 
-use super::{common::do_print_search_information, config::Config};
+use super::{common::do_print_search_information, config::Config, volumes::map_volumes};
 use crate::r#impl::common::unix_timestamp_to_string;
 
 pub(crate) async fn amap_search(
@@ -128,6 +128,32 @@ pub(crate) async fn amap_title_info(
                     {
                         console.info(cformat!("      Expires at: <s>{}</>", expiry_time_str));
                     }
+                    if let Some(included_in) = episode.info().included_in()
+                        && !included_in.trim().is_empty()
+                    {
+                        console.info(cformat!("           Volume: <s>{}</>", included_in));
+                    }
+                }
+            }
+
+            let volume_groups = map_volumes(info, results.volumes());
+            if !volume_groups.is_empty() {
+                println!();
+                console.info(cformat!("  <s>Volumes</>: {} volumes", volume_groups.len()));
+                for group in volume_groups.iter() {
+                    let episodes_text = match (group.episodes.first(), group.episodes.last()) {
+                        (Some(first), Some(last)) => cformat!(
+                            " (<s>{}</> - <s>{}</>, {} chapters)",
+                            first.title(),
+                            last.title(),
+                            group.episodes.len()
+                        ),
+                        _ => String::new(),
+                    };
+                    console.info(cformat!("    - <s>{}</>{}", group.title(), episodes_text));
+                    if let Some(book) = group.book {
+                        console.info(format!("      {}", book.detail_url()));
+                    }
                 }
             }
 
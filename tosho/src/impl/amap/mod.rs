@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use super::parser::{CommaSeparatedNumber, parse_comma_number};
+use super::parser::{
+    CommaSeparatedNumber, CommaSeparatedString, parse_comma_number, parse_comma_string,
+};
 use clap::Subcommand;
 
 pub(crate) mod accounts;
@@ -8,18 +10,33 @@ pub(super) mod common;
 pub(crate) mod config;
 pub(crate) mod download;
 pub(crate) mod favorites;
+pub(crate) mod free_daily;
 pub(crate) mod manga;
 pub(crate) mod purchases;
 pub(crate) mod rankings;
+pub(crate) mod volumes;
 
 #[derive(Subcommand, Clone)]
 pub(crate) enum AMAPCommands {
     /// Authenticate tosho with your AM account.
     Auth {
         /// Email to use
-        email: String,
+        ///
+        /// Uses `TOSHO_AM_EMAIL` (or `TOSHO_AM_EMAIL_FILE`) when omitted.
+        email: Option<String>,
         /// Password to use
-        password: String,
+        ///
+        /// Uses `TOSHO_AM_PASSWORD` (or `TOSHO_AM_PASSWORD_FILE`) when omitted.
+        password: Option<String>,
+        /// Read the password from a file instead
+        #[arg(long = "password-file", conflicts_with_all = ["password", "password_stdin"])]
+        password_file: Option<PathBuf>,
+        /// Read the password from the first line of stdin instead
+        #[arg(long = "password-stdin", conflicts_with = "password")]
+        password_stdin: bool,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Get an account information
     Account,
@@ -48,6 +65,15 @@ pub(crate) enum AMAPCommands {
         /// Disable the use of premium ticket to purchase chapters
         #[arg(long = "no-premium")]
         no_premium_ticket: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
+        /// Save the volume mapping of the chapters into `_volumes.json`
+        #[arg(long = "write-volume-map")]
+        write_volume_map: bool,
+        /// Only download the chapters included in the specified volumes (ex: 1,2,3)
+        #[arg(short = 'v', long = "volumes", default_value = None, value_parser = parse_comma_string)]
+        volumes: Option<CommaSeparatedString>,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
@@ -69,12 +95,33 @@ pub(crate) enum AMAPCommands {
         /// Automatically purchase chapters if needed
         #[arg(short = 'p', long = "auto-purchase")]
         auto_purchase: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
+        /// Save the volume mapping of the chapters into `_volumes.json`
+        #[arg(long = "write-volume-map")]
+        write_volume_map: bool,
+        /// Only download the chapters included in the specified volumes (ex: 1,2,3)
+        #[arg(short = 'v', long = "volumes", default_value = None, value_parser = parse_comma_string)]
+        volumes: Option<CommaSeparatedString>,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
     },
     /// Get your account favorites list
     Favorites,
+    /// Download the episodes that can be read with the free daily ticket
+    ///
+    /// Check your favorites or specific titles, and download the free daily episode
+    /// and any rented episodes before they expire.
+    FreeDaily {
+        /// Specify the title IDs to check instead of your favorites (ex: 1,2,3)
+        #[arg(short = 't', long = "titles", default_value = None, value_parser = parse_comma_number)]
+        titles: Option<CommaSeparatedNumber>,
+        /// Output directory to use
+        #[arg(short = 'o', long = "output", default_value = None)]
+        output: Option<PathBuf>,
+    },
     /// Get a title information
     Info {
         /// Title ID to use
@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use serde::Serialize;
+use tosho_amap::models::{
+    ComicEpisodeInfoNode, ComicInfo, ComicVolumeBookInfo, ComicVolumeBookInfoNode,
+};
+
+/// The file name of the saved volume mapping in the title folder.
+pub(super) const VOLUMES_FILE: &str = "_volumes.json";
+
+/// The episodes grouped into a single volume.
+#[derive(Debug, Clone)]
+pub(super) struct VolumeGroup<'a> {
+    /// The normalized volume key, e.g. `3`
+    pub(super) key: String,
+    /// The volume book information, if it's listed in the title
+    pub(super) book: Option<&'a ComicVolumeBookInfoNode>,
+    /// The episodes included in the volume
+    pub(super) episodes: Vec<&'a ComicEpisodeInfoNode>,
+}
+
+impl VolumeGroup<'_> {
+    pub(super) fn title(&self) -> String {
+        match self.book {
+            Some(book) => book.title().to_string(),
+            None => format!("Volume {}", self.key),
+        }
+    }
+}
+
+/// A volume and the episodes included in it, saved into the title folder.
+#[derive(Debug, Clone, Serialize)]
+struct VolumeDump {
+    /// The volume title
+    title: String,
+    /// The volume cover URL
+    cover_url: Option<String>,
+    /// The volume detail URL
+    detail_url: Option<String>,
+    /// The episode IDs included in the volume
+    episodes: Vec<u64>,
+}
+
+/// Normalize the volume name into a key that can be matched between
+/// the volume list and the episode `included_volume`.
+///
+/// Uses the last number in the name (full-width digits are supported),
+/// or the lowercased name if there is no number.
+pub(super) fn volume_key(name: &str) -> String {
+    let normalized: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '０'..='９' => char::from_digit(c as u32 - '０' as u32, 10).unwrap_or(c),
+            _ => c,
+        })
+        .collect();
+
+    let mut numbers: Vec<String> = vec![];
+    let mut current = String::new();
+    for c in normalized.chars() {
+        if c.is_ascii_digit() {
+            current.push(c);
+        } else if !current.is_empty() {
+            numbers.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        numbers.push(current);
+    }
+
+    match numbers.last().and_then(|num| num.parse::<u64>().ok()) {
+        Some(number) => number.to_string(),
+        None => normalized.to_lowercase(),
+    }
+}
+
+/// Map the episodes into the volumes by the episode `included_volume`.
+///
+/// Volumes are returned in the listed order, then any volume only referenced by the
+/// episodes. Episodes that are not included in any volume are not returned.
+pub(super) fn map_volumes<'a>(
+    info: &'a ComicInfo,
+    volumes: &'a [ComicVolumeBookInfo],
+) -> Vec<VolumeGroup<'a>> {
+    let mut groups: Vec<VolumeGroup> = volumes
+        .iter()
+        .map(|volume| VolumeGroup {
+            key: volume_key(volume.info().title()),
+            book: Some(volume.info()),
+            episodes: vec![],
+        })
+        .collect();
+
+    for episode in info.episodes().iter().map(|ep| ep.info()) {
+        let Some(included_in) = episode.included_in() else {
+            continue;
+        };
+        if included_in.trim().is_empty() {
+            continue;
+        }
+
+        let key = volume_key(included_in);
+        match groups.iter_mut().find(|group| group.key == key) {
+            Some(group) => group.episodes.push(episode),
+            None => groups.push(VolumeGroup {
+                key,
+                book: None,
+                episodes: vec![episode],
+            }),
+        }
+    }
+
+    for group in groups.iter_mut() {
+        group.episodes.sort_by_key(|ep| ep.id());
+    }
+    groups
+}
+
+/// Find the volume of the episode.
+pub(super) fn find_episode_volume<'a>(
+    groups: &'a [VolumeGroup<'a>],
+    episode_id: u64,
+) -> Option<&'a VolumeGroup<'a>> {
+    groups
+        .iter()
+        .find(|group| group.episodes.iter().any(|ep| ep.id() == episode_id))
+}
+
+/// Save the volume mapping into the title folder.
+pub(super) fn dump_volumes(groups: &[VolumeGroup], title_dir: &Path) -> std::io::Result<()> {
+    let dumped: Vec<VolumeDump> = groups
+        .iter()
+        .map(|group| VolumeDump {
+            title: group.title(),
+            cover_url: group.book.map(|book| book.cover_url().to_string()),
+            detail_url: group.book.map(|book| book.detail_url().to_string()),
+            episodes: group.episodes.iter().map(|ep| ep.id()).collect(),
+        })
+        .collect();
+
+    let content = serde_json::to_string_pretty(&dumped)?;
+    std::fs::write(title_dir.join(VOLUMES_FILE), content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volume_key() {
+        assert_eq!(volume_key("第3巻"), "3");
+        assert_eq!(volume_key("第３巻"), "3");
+        assert_eq!(volume_key("Vol. 03"), "3");
+        assert_eq!(volume_key("Season 2 Vol. 10"), "10");
+        assert_eq!(volume_key(" Special "), "special");
+    }
+}
@@ -0,0 +1,936 @@
+//! A local HTTP/JSON API to control tosho remotely, e.g. from a media server.
+//!
+//! Every request needs the `Authorization: Bearer <token>` header. Downloads are queued
+//! and run one at a time with the `autodownload` command of the source, the same way
+//! as a daemon job, into a directory under the output root configured by the server.
+//! The job results are the files of the chapters written by the job.
+
+use std::{
+    net::SocketAddr,
+    path::{Component, Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
+
+use axum::{
+    Json, Router,
+    extract::{Path as UrlPath, Query, Request, State},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use chrono::{DateTime, Local};
+use clap::Parser;
+use color_print::cformat;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch};
+
+use crate::{
+    cli::{ExitCode, ToshoCli},
+    config::{ConfigImpl, get_all_config, get_config, get_user_path},
+    r#impl::{
+        Implementations, client,
+        common::{
+            is_shutdown_requested, request_shutdown, start_recording_chapters,
+            take_written_chapters, written_chapters,
+        },
+        daemon::{DaemonSource, DaemonTitle, SyncTarget, wait_for_shutdown},
+    },
+};
+
+/// The finished jobs kept for polling, the oldest ones are dropped first
+const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Clone, Debug)]
+pub(crate) struct ApiConfig {
+    /// The address to listen on
+    pub(crate) bind: SocketAddr,
+    /// The token required by every request, generated when not set
+    pub(crate) token: Option<String>,
+    /// The directory every download is written under
+    pub(crate) output_root: PathBuf,
+    /// The verbosity passed to every download
+    pub(crate) verbose: u8,
+    /// The raw proxy URL passed to every download
+    pub(crate) proxy_url: Option<String>,
+    pub(crate) proxy: Option<reqwest::Proxy>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobState {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Interrupted,
+}
+
+impl JobState {
+    fn is_done(&self) -> bool {
+        matches!(
+            self,
+            JobState::Finished | JobState::Failed | JobState::Interrupted
+        )
+    }
+}
+
+/// A queued download
+#[derive(Debug, Clone, Serialize)]
+struct ApiJob {
+    id: u64,
+    #[serde(flatten)]
+    target: SyncTarget,
+    state: JobState,
+    exit_code: Option<ExitCode>,
+    error: Option<String>,
+    created_at: String,
+    started_at: Option<String>,
+    finished_at: Option<String>,
+    /// The chapter directories written by the job, relative to the output root
+    chapters: Vec<PathBuf>,
+}
+
+struct ApiState {
+    token: String,
+    output_root: PathBuf,
+    proxy: Option<reqwest::Proxy>,
+    jobs: Mutex<Vec<ApiJob>>,
+    next_id: AtomicU64,
+    queue: mpsc::UnboundedSender<u64>,
+}
+
+type SharedState = Arc<ApiState>;
+
+#[derive(Debug)]
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.1 });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+impl From<color_eyre::Report> for ApiError {
+    fn from(err: color_eyre::Report) -> Self {
+        ApiError(StatusCode::BAD_GATEWAY, err.to_string())
+    }
+}
+
+impl From<tosho_common::ToshoError> for ApiError {
+    fn from(err: tosho_common::ToshoError) -> Self {
+        ApiError(StatusCode::BAD_GATEWAY, err.to_string())
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Debug, Serialize)]
+struct ApiAccount {
+    source: &'static str,
+    id: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiTitle {
+    id: DaemonTitle,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    q: String,
+    account: Option<String>,
+}
+
+/// A download to queue, the options are passed as the `autodownload` flags of the source
+#[derive(Debug, Deserialize)]
+struct DownloadRequest {
+    source: DaemonSource,
+    /// The account ID to use, the first account of the source is used when omitted
+    account: Option<String>,
+    title: DaemonTitle,
+    /// The directory to download into, relative to the output root
+    output: Option<PathBuf>,
+    /// Only download the chapters that are already unlocked
+    #[serde(default)]
+    no_purchase: bool,
+    /// The chapter number to start downloading from
+    start: Option<u64>,
+    /// The chapter number to stop downloading at
+    end: Option<u64>,
+    /// The image quality, as accepted by the `--quality` flag of the source
+    quality: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct JobResults {
+    id: u64,
+    state: JobState,
+    files: Vec<PathBuf>,
+}
+
+/// Drop the oldest finished jobs over [`MAX_FINISHED_JOBS`].
+fn prune_jobs(jobs: &mut Vec<ApiJob>) {
+    let finished = jobs.iter().filter(|job| job.state.is_done()).count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+    jobs.retain(|job| {
+        if excess > 0 && job.state.is_done() {
+            excess -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+fn token_path() -> PathBuf {
+    get_user_path().join("api_token")
+}
+
+/// Save the generated token, only readable by the current user.
+fn save_token(path: &Path, token: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    std::io::Write::write_all(&mut file, token.as_bytes())
+}
+
+/// Compare the token without bailing out on the first mismatched byte.
+fn is_authorized(header: Option<&str>, token: &str) -> bool {
+    let Some(provided) = header.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+
+    provided.len() == token.len()
+        && provided
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, message.into())
+}
+
+/// Resolve the requested output under the root, only plain relative paths are allowed.
+fn resolve_output(root: &Path, output: Option<&Path>) -> Result<PathBuf, ApiError> {
+    let Some(output) = output else {
+        return Ok(root.to_path_buf());
+    };
+
+    let is_relative = output
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !is_relative {
+        return Err(bad_request(format!(
+            "The output must be a relative path without `..`: {}",
+            output.display()
+        )));
+    }
+
+    Ok(root.join(output))
+}
+
+/// Map the download options into the `autodownload` flags of the source.
+fn download_args(request: &DownloadRequest) -> Result<Vec<String>, ApiError> {
+    let source = request.source;
+    let unsupported = |option: &str| {
+        bad_request(format!(
+            "The `{option}` option is not supported for {}",
+            source.slug()
+        ))
+    };
+
+    let mut args = vec![];
+    if request.no_purchase {
+        if !matches!(
+            source,
+            DaemonSource::Musq | DaemonSource::Kmkc | DaemonSource::Amap
+        ) {
+            return Err(unsupported("no_purchase"));
+        }
+        args.push("--no-purchase".to_string());
+    }
+    for (option, flag, value) in [
+        ("start", "--start-from", request.start),
+        ("end", "--end-until", request.end),
+    ] {
+        if let Some(value) = value {
+            if matches!(source, DaemonSource::Rbean | DaemonSource::Nids) {
+                return Err(unsupported(option));
+            }
+            args.push(format!("{flag}={value}"));
+        }
+    }
+    if let Some(quality) = &request.quality {
+        if !matches!(
+            source,
+            DaemonSource::Musq | DaemonSource::Rbean | DaemonSource::Mplus | DaemonSource::Nids
+        ) {
+            return Err(unsupported("quality"));
+        }
+        args.push(format!("--quality={quality}"));
+    }
+
+    Ok(args)
+}
+
+/// Strip the output root from the chapter directories, so the server paths are not exposed.
+fn relative_chapters(root: &Path, chapters: Vec<PathBuf>) -> Vec<PathBuf> {
+    chapters
+        .into_iter()
+        .map(|chapter| match chapter.strip_prefix(root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => chapter,
+        })
+        .collect()
+}
+
+/// Collect the files of the chapter directories, relative to the output root.
+fn collect_chapter_files(root: &Path, chapters: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for chapter in chapters {
+        let Ok(entries) = std::fs::read_dir(root.join(chapter)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|file_type| file_type.is_file()) {
+                files.push(chapter.join(entry.file_name()));
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+fn source_implementation(source: DaemonSource) -> Implementations {
+    match source {
+        DaemonSource::Musq => Implementations::Musq,
+        DaemonSource::Kmkc => Implementations::Kmkc,
+        DaemonSource::Amap => Implementations::Amap,
+        DaemonSource::Sjv => Implementations::Sjv,
+        DaemonSource::Rbean => Implementations::Rbean,
+        DaemonSource::Mplus => Implementations::Mplus,
+        DaemonSource::Nids => Implementations::Nids,
+    }
+}
+
+/// Find the account to use, the first account of the source is used when not specified.
+fn find_account(source: DaemonSource, account: Option<&str>) -> Result<ConfigImpl, ApiError> {
+    let implementation = source_implementation(source);
+    let config = match account {
+        Some(account_id) => get_config(account_id, &implementation, None),
+        None => get_all_config(&implementation, None).into_iter().next(),
+    };
+
+    config.ok_or_else(|| {
+        ApiError(
+            StatusCode::NOT_FOUND,
+            format!("No account found for {}", source.slug()),
+        )
+    })
+}
+
+fn account_summary(config: &ConfigImpl) -> ApiAccount {
+    match config {
+        ConfigImpl::Musq(c) => ApiAccount {
+            source: "mu",
+            id: c.id.clone(),
+            name: None,
+        },
+        ConfigImpl::Kmkc(c) => ApiAccount {
+            source: "km",
+            id: c.get_id().to_string(),
+            name: Some(c.get_username().to_string()),
+        },
+        ConfigImpl::Amap(c) => ApiAccount {
+            source: "am",
+            id: c.id.clone(),
+            name: Some(c.email.clone()),
+        },
+        ConfigImpl::Sjv(c) => ApiAccount {
+            source: "sj",
+            id: c.id.clone(),
+            name: Some(c.username.clone()),
+        },
+        ConfigImpl::Rbean(c) => ApiAccount {
+            source: "rb",
+            id: c.id.clone(),
+            name: Some(c.username.clone()),
+        },
+        ConfigImpl::Mplus(c) => ApiAccount {
+            source: "mp",
+            id: c.id.clone(),
+            name: c.username.clone(),
+        },
+        ConfigImpl::Nids(c) => ApiAccount {
+            source: "ni",
+            id: c.id.clone(),
+            name: c.username.clone().or_else(|| Some(c.email.clone())),
+        },
+    }
+}
+
+async fn search_titles(
+    config: ConfigImpl,
+    query: &str,
+    proxy: Option<reqwest::Proxy>,
+) -> Result<Vec<ApiTitle>, ApiError> {
+    let titles = match config {
+        ConfigImpl::Musq(c) => {
+            let client = client::make_musq_client(&c)?;
+            let client = match proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let results = client.search(query).await?;
+            results
+                .titles()
+                .iter()
+                .map(|title| ApiTitle {
+                    id: DaemonTitle::Id(title.id()),
+                    title: title.title().to_string(),
+                })
+                .collect()
+        }
+        ConfigImpl::Kmkc(c) => {
+            let client = client::make_kmkc_client(&c.clone().into())?;
+            let client = match proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let results = client.search(query, Some(50)).await?;
+            results
+                .iter()
+                .map(|title| ApiTitle {
+                    id: DaemonTitle::Id(title.id().into()),
+                    title: title.title().to_string(),
+                })
+                .collect()
+        }
+        ConfigImpl::Amap(c) => {
+            let client = client::make_amap_client(&c.clone().into())?;
+            let client = match proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let results = client.search(query, None, None, None, None).await?;
+            super::amap::common::save_session_config(&client, &c);
+            results
+                .comics()
+                .iter()
+                .map(|comic| ApiTitle {
+                    id: DaemonTitle::Id(comic.info().id()),
+                    title: comic.info().title().to_string(),
+                })
+                .collect()
+        }
+        ConfigImpl::Sjv(c) => {
+            let client = client::make_sjv_client(&c)?;
+            let client = match proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let results = super::sjv::common::get_cached_store_data(&client).await?;
+            super::sjv::common::search_manga_by_text(&results.series, query)
+                .iter()
+                .map(|manga| ApiTitle {
+                    id: DaemonTitle::Id(manga.id().into()),
+                    title: manga.title().to_string(),
+                })
+                .collect()
+        }
+        ConfigImpl::Rbean(c) => {
+            let client = client::make_rbean_client(&c)?;
+            let mut client = match proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+            client.set_expiry_at(Some(c.expiry));
+
+            let results = client.search(query, Some(0), Some(25), None).await?;
+            super::rbean::common::save_session_config(&client, &c);
+            results
+                .results()
+                .iter()
+                .map(|manga| ApiTitle {
+                    id: DaemonTitle::Slug(manga.uuid().to_string()),
+                    title: manga.title().to_string(),
+                })
+                .collect()
+        }
+        ConfigImpl::Mplus(c) => {
+            let client = client::make_mplus_client(&c, Default::default())?;
+            let client = match proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let results = super::mplus::common::get_cached_titles_data(&client).await?;
+            let merged_titles: Vec<tosho_mplus::proto::Title> =
+                results.iter().flat_map(|x| x.titles().to_vec()).collect();
+            super::mplus::common::search_manga_by_text(&merged_titles, query)
+                .iter()
+                .map(|title| ApiTitle {
+                    id: DaemonTitle::Id(title.id()),
+                    title: title.title().to_string(),
+                })
+                .collect()
+        }
+        ConfigImpl::Nids(c) => {
+            let client = client::make_nids_client(&c)?;
+            let client = match proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let filters = tosho_nids::Filter::new()
+                .add_filter(tosho_nids::FilterType::Title, query)
+                .with_per_page(25);
+            let results = client.get_issues(&filters).await?;
+            results
+                .data()
+                .iter()
+                .map(|issue| ApiTitle {
+                    id: DaemonTitle::Id(issue.id().into()),
+                    title: issue.full_title().to_string(),
+                })
+                .collect()
+        }
+    };
+
+    Ok(titles)
+}
+
+async fn require_token(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let header = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    if is_authorized(header, &state.token) {
+        next.run(request).await
+    } else {
+        ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Invalid or missing token".to_string(),
+        )
+        .into_response()
+    }
+}
+
+async fn list_accounts() -> ApiResult<Vec<ApiAccount>> {
+    let implementations = [
+        Implementations::Musq,
+        Implementations::Kmkc,
+        Implementations::Amap,
+        Implementations::Sjv,
+        Implementations::Rbean,
+        Implementations::Mplus,
+        Implementations::Nids,
+    ];
+
+    let accounts = implementations
+        .iter()
+        .flat_map(|implementation| get_all_config(implementation, None))
+        .map(|config| account_summary(&config))
+        .collect();
+    Ok(Json(accounts))
+}
+
+async fn search(
+    State(state): State<SharedState>,
+    UrlPath(source): UrlPath<DaemonSource>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<Vec<ApiTitle>> {
+    let config = find_account(source, query.account.as_deref())?;
+    let titles = search_titles(config, &query.q, state.proxy.clone()).await?;
+    Ok(Json(titles))
+}
+
+async fn queue_download(
+    State(state): State<SharedState>,
+    Json(request): Json<DownloadRequest>,
+) -> Result<(StatusCode, Json<ApiJob>), ApiError> {
+    if request.title.to_string().starts_with('-') {
+        return Err(bad_request("The title cannot start with `-`"));
+    }
+
+    // always pass the account and output, so the job never prompts
+    let config = find_account(request.source, request.account.as_deref())?;
+    let target = SyncTarget {
+        source: request.source,
+        account: Some(account_summary(&config).id),
+        title: request.title.clone(),
+        output: Some(resolve_output(
+            &state.output_root,
+            request.output.as_deref(),
+        )?),
+        args: download_args(&request)?,
+    };
+    // reject the invalid values (e.g. an unknown quality) before the job is queued
+    if let Err(err) = ToshoCli::try_parse_from(target.argv(0, None)) {
+        return Err(bad_request(err.to_string()));
+    }
+
+    let mut jobs = state.jobs.lock().unwrap();
+    let job = ApiJob {
+        id: state.next_id.fetch_add(1, Ordering::SeqCst),
+        target,
+        state: JobState::Queued,
+        exit_code: None,
+        error: None,
+        created_at: Local::now().to_rfc3339(),
+        started_at: None,
+        finished_at: None,
+        chapters: vec![],
+    };
+    if state.queue.send(job.id).is_err() {
+        return Err(ApiError(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The server is shutting down".to_string(),
+        ));
+    }
+
+    jobs.push(job.clone());
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+async fn list_jobs(State(state): State<SharedState>) -> ApiResult<Vec<ApiJob>> {
+    Ok(Json(state.jobs.lock().unwrap().clone()))
+}
+
+fn find_job(state: &ApiState, id: u64) -> Result<ApiJob, ApiError> {
+    state
+        .jobs
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|job| job.id == id)
+        .cloned()
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Job {id} not found")))
+}
+
+/// Find the job, with the chapters written so far when it's still running.
+fn find_job_progress(state: &ApiState, id: u64) -> Result<ApiJob, ApiError> {
+    let mut job = find_job(state, id)?;
+    if job.state == JobState::Running {
+        job.chapters = relative_chapters(&state.output_root, written_chapters());
+    }
+    Ok(job)
+}
+
+async fn get_job(State(state): State<SharedState>, UrlPath(id): UrlPath<u64>) -> ApiResult<ApiJob> {
+    Ok(Json(find_job_progress(&state, id)?))
+}
+
+async fn get_job_results(
+    State(state): State<SharedState>,
+    UrlPath(id): UrlPath<u64>,
+) -> ApiResult<JobResults> {
+    let job = find_job_progress(&state, id)?;
+    let files = collect_chapter_files(&state.output_root, &job.chapters);
+
+    Ok(Json(JobResults {
+        id: job.id,
+        state: job.state,
+        files,
+    }))
+}
+
+fn update_job(state: &ApiState, id: u64, update: impl FnOnce(&mut ApiJob)) -> Option<ApiJob> {
+    let mut jobs = state.jobs.lock().unwrap();
+    let job = jobs.iter_mut().find(|job| job.id == id)?;
+    update(job);
+    Some(job.clone())
+}
+
+/// Run a queued job in-process, this is done on the main task since the commands are not `Send`.
+///
+/// A panic in the job is caught by [`SyncTarget::run`] and recorded as a failed job,
+/// so it never takes the server down.
+async fn run_job(state: &ApiState, id: u64, config: &ApiConfig, console: &crate::term::Terminal) {
+    let now = SystemTime::now();
+    // only the chapters written by this job are recorded
+    start_recording_chapters();
+    let Some(job) = update_job(state, id, |job| {
+        job.state = JobState::Running;
+        job.started_at = Some(DateTime::<Local>::from(now).to_rfc3339());
+    }) else {
+        return;
+    };
+
+    console.info(cformat!(
+        "Running job <m,s>{}</>: {} from {}",
+        id,
+        job.target.title,
+        job.target.source.slug()
+    ));
+    let result = job
+        .target
+        .run(config.verbose, config.proxy_url.as_deref())
+        .await;
+    let chapters = relative_chapters(&state.output_root, take_written_chapters());
+
+    update_job(state, id, |job| {
+        job.finished_at = Some(Local::now().to_rfc3339());
+        job.chapters = chapters;
+        match result {
+            Ok(code) => {
+                job.exit_code = Some(code);
+                job.state = match code {
+                    _ if is_shutdown_requested() => JobState::Interrupted,
+                    0 => JobState::Finished,
+                    _ => JobState::Failed,
+                };
+            }
+            Err(err) => {
+                job.error = Some(err.to_string());
+                job.state = JobState::Failed;
+            }
+        }
+    });
+    prune_jobs(&mut state.jobs.lock().unwrap());
+}
+
+fn router(state: SharedState) -> Router {
+    Router::new()
+        .route("/accounts", get(list_accounts))
+        .route("/search/{source}", get(search))
+        .route("/downloads", post(queue_download))
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/{id}", get(get_job))
+        .route("/jobs/{id}/results", get(get_job_results))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state)
+}
+
+/// Serve the API until a shutdown is requested.
+pub(crate) async fn tosho_api_serve(
+    config: ApiConfig,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let token_file = token_path();
+    let token = config
+        .token
+        .clone()
+        .or_else(|| std::env::var("TOSHO_API_TOKEN").ok())
+        .or_else(|| std::fs::read_to_string(&token_file).ok())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+    let token = match token {
+        Some(token) => token,
+        None => {
+            let token = uuid::Uuid::new_v4().simple().to_string();
+            if let Err(err) = save_token(&token_file, &token) {
+                console.error(format!("Unable to save the API token: {err}"));
+                return 1;
+            }
+            console.info(cformat!(
+                "Generated API token, saved into <s>{}</>",
+                token_file.display()
+            ));
+            token
+        }
+    };
+
+    if !config.bind.ip().is_loopback() {
+        console.warn(cformat!(
+            "Listening on <s>{}</> exposes the API outside of this machine!",
+            config.bind
+        ));
+    }
+    let listener = match tokio::net::TcpListener::bind(config.bind).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            console.error(format!("Unable to listen on {}: {err}", config.bind));
+            return 1;
+        }
+    };
+
+    let (queue_tx, mut queue_rx) = mpsc::unbounded_channel();
+    let state = Arc::new(ApiState {
+        token,
+        output_root: config.output_root.clone(),
+        proxy: config.proxy.clone(),
+        jobs: Mutex::new(vec![]),
+        next_id: AtomicU64::new(1),
+        queue: queue_tx,
+    });
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        wait_for_shutdown().await;
+        request_shutdown();
+        let _ = shutdown_tx.send(true);
+    });
+
+    let mut server_shutdown = shutdown_rx.clone();
+    let server = axum::serve(listener, router(state.clone())).with_graceful_shutdown(async move {
+        let _ = server_shutdown.wait_for(|stop| *stop).await;
+    });
+    let server = tokio::spawn(async move { server.await });
+    console.info(cformat!("Listening on <m,s>http://{}</>", config.bind));
+
+    let mut shutdown = shutdown_rx;
+    loop {
+        tokio::select! {
+            id = queue_rx.recv() => match id {
+                Some(id) => run_job(&state, id, &config, console).await,
+                None => break,
+            },
+            _ = shutdown.wait_for(|stop| *stop) => break,
+        }
+    }
+
+    // the remaining jobs are dropped with the server
+    queue_rx.close();
+    while let Ok(id) = queue_rx.try_recv() {
+        update_job(&state, id, |job| job.state = JobState::Interrupted);
+    }
+
+    match server.await {
+        Ok(Ok(_)) => {
+            console.info("API server stopped");
+            0
+        }
+        Ok(Err(err)) => {
+            console.error(format!("API server failed: {err}"));
+            1
+        }
+        Err(err) => {
+            console.error(format!("API server failed: {err}"));
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(Some("Bearer secret"), "secret"));
+        assert!(!is_authorized(Some("Bearer secreT"), "secret"));
+        assert!(!is_authorized(Some("Bearer secret2"), "secret"));
+        assert!(!is_authorized(Some("secret"), "secret"));
+        assert!(!is_authorized(None, "secret"));
+    }
+
+    #[test]
+    fn test_prune_jobs() {
+        let make_job = |id: u64, state: JobState| ApiJob {
+            id,
+            target: SyncTarget {
+                source: DaemonSource::Musq,
+                account: None,
+                title: DaemonTitle::Id(1),
+                output: None,
+                args: vec![],
+            },
+            state,
+            exit_code: None,
+            error: None,
+            created_at: String::new(),
+            started_at: None,
+            finished_at: None,
+            chapters: vec![],
+        };
+
+        let mut jobs: Vec<ApiJob> = (1..=MAX_FINISHED_JOBS as u64 + 2)
+            .map(|id| make_job(id, JobState::Finished))
+            .collect();
+        jobs.insert(0, make_job(0, JobState::Queued));
+        prune_jobs(&mut jobs);
+
+        assert_eq!(jobs.len(), MAX_FINISHED_JOBS + 1);
+        assert_eq!(jobs[0].id, 0);
+        assert_eq!(jobs[1].id, 3);
+    }
+
+    #[test]
+    fn test_resolve_output() {
+        let root = Path::new("downloads");
+        assert_eq!(resolve_output(root, None).unwrap(), root);
+        assert_eq!(
+            resolve_output(root, Some(Path::new("manga/mu"))).unwrap(),
+            root.join("manga/mu")
+        );
+        assert!(resolve_output(root, Some(Path::new("../outside"))).is_err());
+        assert!(resolve_output(root, Some(Path::new("manga/../../outside"))).is_err());
+        assert!(resolve_output(root, Some(&std::env::temp_dir())).is_err());
+    }
+
+    #[test]
+    fn test_download_args() {
+        let request: DownloadRequest = serde_json::from_value(serde_json::json!({
+            "source": "mu",
+            "title": 123,
+            "no_purchase": true,
+            "start": 2,
+            "end": 5,
+            "quality": "high",
+        }))
+        .unwrap();
+        assert_eq!(
+            download_args(&request).unwrap(),
+            vec![
+                "--no-purchase",
+                "--start-from=2",
+                "--end-until=5",
+                "--quality=high"
+            ]
+        );
+
+        let request: DownloadRequest = serde_json::from_value(serde_json::json!({
+            "source": "sj",
+            "title": 123,
+            "no_purchase": true,
+        }))
+        .unwrap();
+        assert!(download_args(&request).is_err());
+    }
+
+    #[test]
+    fn test_collect_chapter_files() {
+        let dir = std::env::temp_dir().join("tosho-api-results-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("MU_1").join("10")).unwrap();
+        std::fs::create_dir_all(dir.join("MU_1").join("11")).unwrap();
+        std::fs::write(dir.join("MU_1").join("10").join("p001.avif"), b"page").unwrap();
+        std::fs::write(dir.join("MU_1").join("11").join("p001.avif"), b"page").unwrap();
+        std::fs::write(dir.join("MU_1").join("_info.json"), b"{}").unwrap();
+
+        let chapters = relative_chapters(&dir, vec![dir.join("MU_1").join("10")]);
+        assert_eq!(chapters, vec![PathBuf::from("MU_1").join("10")]);
+        assert_eq!(
+            collect_chapter_files(&dir, &chapters),
+            vec![PathBuf::from("MU_1").join("10").join("p001.avif")]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
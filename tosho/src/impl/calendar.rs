@@ -0,0 +1,635 @@
+//! Aggregate the upcoming releases of the followed titles across sources.
+//!
+//! The weekly sources (MU! and KM) only tell us the weekday a title is updated, so
+//! they are expanded into every matching day in the range. AM and M+ give us the
+//! next update time directly, M+ also gives us the update frequency.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+};
+
+use chrono::{Datelike, NaiveDate, TimeZone};
+use clap::ValueEnum;
+use color_print::cformat;
+
+use crate::{
+    cli::ExitCode,
+    config::{ConfigImpl, get_all_config},
+    r#impl::{Implementations, client},
+};
+
+/// The sources that expose a release schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub(crate) enum CalendarSource {
+    /// MU! by SQ
+    #[value(name = "mu")]
+    Musq,
+    /// KM by KC
+    #[value(name = "km")]
+    Kmkc,
+    /// AM by AP
+    #[value(name = "am")]
+    Amap,
+    /// M+ by S
+    #[value(name = "mp")]
+    Mplus,
+}
+
+impl CalendarSource {
+    fn all() -> Vec<Self> {
+        vec![Self::Musq, Self::Kmkc, Self::Amap, Self::Mplus]
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Self::Musq => "MU!",
+            Self::Kmkc => "KM",
+            Self::Amap => "AM",
+            Self::Mplus => "M+",
+        }
+    }
+
+    fn slug(&self) -> &'static str {
+        match self {
+            Self::Musq => "mu",
+            Self::Kmkc => "km",
+            Self::Amap => "am",
+            Self::Mplus => "mp",
+        }
+    }
+}
+
+/// A single release of a title on a specific day
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct CalendarEntry {
+    /// The release day in local time
+    date: NaiveDate,
+    /// The source of the title
+    source: CalendarSource,
+    /// The title name
+    title: String,
+    /// The title ID in the source
+    id: u64,
+    /// Additional schedule information, e.g. the next update text
+    note: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CalendarConfig {
+    /// The amount of days to show starting from today
+    pub(crate) days: u32,
+    /// The sources to check, all sources if empty
+    pub(crate) sources: Vec<CalendarSource>,
+    /// Export the calendar into an iCalendar file
+    pub(crate) ics: Option<PathBuf>,
+    /// Proxy to use for all requests
+    pub(crate) proxy: Option<reqwest::Proxy>,
+}
+
+/// The range of days shown in the calendar, inclusive.
+#[derive(Debug, Clone, Copy)]
+struct DateRange {
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl DateRange {
+    fn new(start: NaiveDate, days: u32) -> Self {
+        let end = start
+            .checked_add_days(chrono::Days::new(days.saturating_sub(1) as u64))
+            .unwrap_or(start);
+        Self { start, end }
+    }
+
+    fn contains(&self, date: NaiveDate) -> bool {
+        date >= self.start && date <= self.end
+    }
+
+    /// Every day in the range that falls on the weekday, `0` is Monday.
+    fn weekday_dates(&self, weekday: u32) -> Vec<NaiveDate> {
+        self.start
+            .iter_days()
+            .take_while(|date| *date <= self.end)
+            .filter(|date| date.weekday().num_days_from_monday() == weekday)
+            .collect()
+    }
+
+    /// Every occurrence of the release in the range, starting from the `next` UNIX timestamp
+    /// and repeating every `frequency` seconds if provided.
+    fn recurring_dates(&self, next: i64, frequency: Option<u64>) -> Vec<NaiveDate> {
+        let mut dates = vec![];
+        let mut current = next;
+        while let Some(date) = timestamp_to_date(current) {
+            if date > self.end {
+                break;
+            }
+            if self.contains(date) {
+                dates.push(date);
+            }
+
+            match frequency {
+                Some(frequency) if frequency > 0 => current += frequency as i64,
+                _ => break,
+            }
+        }
+        dates
+    }
+}
+
+fn timestamp_to_date(timestamp: i64) -> Option<NaiveDate> {
+    chrono::Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|dt| dt.date_naive())
+}
+
+/// Escape the text value according to RFC 5545.
+fn escape_ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold the content line into 75 octets per line according to RFC 5545.
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut current_len = 0;
+    for c in line.chars() {
+        let char_len = c.len_utf8();
+        // continuation lines start with a space which count toward the limit
+        if current_len + char_len > 75 {
+            folded.push_str("\r\n ");
+            current_len = 1;
+        }
+        folded.push(c);
+        current_len += char_len;
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Render the entries into an iCalendar file with all-day events.
+fn render_ics(entries: &[CalendarEntry], stamp: chrono::DateTime<chrono::Utc>) -> String {
+    let stamp = stamp.format("%Y%m%dT%H%M%SZ").to_string();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!(
+            "PRODID:-//tosho-mango//tosho {}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:tosho releases".to_string(),
+    ];
+
+    for entry in entries {
+        let end = entry.date.succ_opt().unwrap_or(entry.date);
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:{}-{}-{}@tosho-mango",
+            entry.source.slug(),
+            entry.id,
+            entry.date.format("%Y%m%d")
+        ));
+        lines.push(format!("DTSTAMP:{stamp}"));
+        lines.push(format!(
+            "DTSTART;VALUE=DATE:{}",
+            entry.date.format("%Y%m%d")
+        ));
+        lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape_ics_text(&format!("[{}] {}", entry.source.tag(), entry.title))
+        ));
+        let mut description = format!("{} title ID {}", entry.source.tag(), entry.id);
+        if let Some(note) = &entry.note {
+            description.push('\n');
+            description.push_str(note);
+        }
+        lines.push(format!("DESCRIPTION:{}", escape_ics_text(&description)));
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold_ics_line(line)).collect()
+}
+
+async fn musq_entries(
+    account: &crate::r#impl::musq::config::Config,
+    weekly: &mut Option<HashMap<u64, Vec<u32>>>,
+    range: &DateRange,
+    proxy: Option<reqwest::Proxy>,
+) -> color_eyre::Result<Vec<CalendarEntry>> {
+    use tosho_musq::WeeklyCode;
+
+    let client = client::make_musq_client(account)?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    // the weekly list is the same for every account, fetch it once
+    if weekly.is_none() {
+        let mut mapped: HashMap<u64, Vec<u32>> = HashMap::new();
+        for weekday in [
+            WeeklyCode::Monday,
+            WeeklyCode::Tuesday,
+            WeeklyCode::Wednesday,
+            WeeklyCode::Thursday,
+            WeeklyCode::Friday,
+            WeeklyCode::Saturday,
+            WeeklyCode::Sunday,
+        ] {
+            let results = client.get_weekly_titles(weekday).await?;
+            for title in results.titles() {
+                mapped
+                    .entry(title.id())
+                    .or_default()
+                    .push(weekday.get_index() as u32);
+            }
+        }
+        *weekly = Some(mapped);
+    }
+    let weekly = weekly.as_ref().expect("Weekly list is fetched above");
+
+    let my_page = client.get_my_manga().await?;
+    let mut entries = vec![];
+    for title in my_page.favorites() {
+        let Some(weekdays) = weekly.get(&title.id()) else {
+            continue;
+        };
+        for weekday in weekdays {
+            for date in range.weekday_dates(*weekday) {
+                entries.push(CalendarEntry {
+                    date,
+                    source: CalendarSource::Musq,
+                    title: title.title().to_string(),
+                    id: title.id(),
+                    note: None,
+                });
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+async fn kmkc_entries(
+    account: &crate::r#impl::kmkc::config::Config,
+    range: &DateRange,
+    proxy: Option<reqwest::Proxy>,
+) -> color_eyre::Result<Vec<CalendarEntry>> {
+    let client = client::make_kmkc_client(&account.clone().into())?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    let weekly = client.get_weekly().await?;
+    let favorites = client.get_favorites().await?;
+
+    let mut entries = vec![];
+    for favorite in favorites.favorites() {
+        let Some(title) = favorites.titles().iter().find(|t| t.id() == favorite.id()) else {
+            continue;
+        };
+
+        // the weekday index is 1 (Monday) to 7 (Sunday)
+        for content in weekly.contents() {
+            if !content.titles().contains(&favorite.id()) || content.weekday() < 1 {
+                continue;
+            }
+            for date in range.weekday_dates((content.weekday() - 1) as u32) {
+                entries.push(CalendarEntry {
+                    date,
+                    source: CalendarSource::Kmkc,
+                    title: title.title().to_string(),
+                    id: title.id() as u64,
+                    note: None,
+                });
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+async fn amap_entries(
+    account: &crate::r#impl::amap::config::Config,
+    range: &DateRange,
+    proxy: Option<reqwest::Proxy>,
+    console: &crate::term::Terminal,
+) -> color_eyre::Result<Vec<CalendarEntry>> {
+    let client = client::make_amap_client(&account.clone().into())?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    let favorites = client.get_favorites().await?;
+
+    let mut entries = vec![];
+    for comic in favorites.comics() {
+        let title_id = comic.info().id();
+        console.log(cformat!("  Fetching AM title <m,s>{}</>...", title_id));
+        let info = match client.get_comic(title_id).await {
+            Ok(info) => info,
+            Err(err) => {
+                console.warn(format!("  Failed to fetch AM title {title_id}: {err}"));
+                continue;
+            }
+        };
+
+        let info = info.info();
+        let Some(next) = info.next_update_date() else {
+            continue;
+        };
+        for date in range.recurring_dates(next as i64, None) {
+            entries.push(CalendarEntry {
+                date,
+                source: CalendarSource::Amap,
+                title: info.title().to_string(),
+                id: title_id,
+                note: info.next_update_text().map(|text| text.to_string()),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+async fn mplus_entries(
+    account: &crate::r#impl::mplus::config::Config,
+    range: &DateRange,
+    proxy: Option<reqwest::Proxy>,
+    console: &crate::term::Terminal,
+) -> color_eyre::Result<Vec<CalendarEntry>> {
+    use tosho_mplus::{APIResponse, proto::TitleReleaseSchedule};
+
+    let client = client::make_mplus_client(
+        account,
+        crate::r#impl::mplus::MPlusLanguage::default().into(),
+    )?;
+    let client = match proxy {
+        Some(proxy) => client.with_proxy(proxy)?,
+        None => client,
+    };
+
+    let bookmarks = match client.get_bookmarked_titles().await? {
+        APIResponse::Success(bookmarks) => bookmarks,
+        APIResponse::Error(e) => color_eyre::eyre::bail!(e.as_string()),
+    };
+
+    let mut entries = vec![];
+    for title in bookmarks.titles() {
+        console.log(cformat!("  Fetching M+ title <m,s>{}</>...", title.id()));
+        let detail = match client.get_title_details(title.id()).await {
+            Ok(APIResponse::Success(detail)) => detail,
+            Ok(APIResponse::Error(e)) => {
+                console.warn(format!(
+                    "  Failed to fetch M+ title {}: {}",
+                    title.id(),
+                    e.as_string()
+                ));
+                continue;
+            }
+            Err(err) => {
+                console.warn(format!("  Failed to fetch M+ title {}: {err}", title.id()));
+                continue;
+            }
+        };
+
+        let schedule = detail
+            .title_labels()
+            .map(|labels| labels.release_schedule())
+            .unwrap_or(TitleReleaseSchedule::None);
+        if schedule == TitleReleaseSchedule::Completed || detail.next_update() <= 0 {
+            continue;
+        }
+
+        let frequency = match detail.update_frequency() {
+            0 => None,
+            frequency => Some(frequency),
+        };
+        let note = match schedule {
+            TitleReleaseSchedule::None | TitleReleaseSchedule::Unrecognized => None,
+            schedule => Some(schedule.pretty_name()),
+        };
+        for date in range.recurring_dates(detail.next_update(), frequency) {
+            entries.push(CalendarEntry {
+                date,
+                source: CalendarSource::Mplus,
+                title: title.title().to_string(),
+                id: title.id(),
+                note: note.clone(),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+pub(crate) async fn tosho_calendar(
+    config: CalendarConfig,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let sources = if config.sources.is_empty() {
+        CalendarSource::all()
+    } else {
+        config
+            .sources
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    };
+
+    let today = chrono::Local::now().date_naive();
+    let range = DateRange::new(today, config.days.max(1));
+
+    let mut exit_code = 0;
+    // dedupe the same release from multiple accounts
+    let mut entries: BTreeSet<CalendarEntry> = BTreeSet::new();
+    for source in sources {
+        let implementation = match source {
+            CalendarSource::Musq => Implementations::Musq,
+            CalendarSource::Kmkc => Implementations::Kmkc,
+            CalendarSource::Amap => Implementations::Amap,
+            CalendarSource::Mplus => Implementations::Mplus,
+        };
+
+        let accounts = get_all_config(&implementation, None);
+        if accounts.is_empty() {
+            console.log(format!("No {} accounts found, skipping", source.tag()));
+            continue;
+        }
+
+        let mut musq_weekly = None;
+        for account in accounts {
+            let (account_id, result) = match &account {
+                ConfigImpl::Musq(c) => {
+                    console.info(cformat!(
+                        "Fetching <s>MU!</> releases for <m,s>{}</>...",
+                        c.id
+                    ));
+                    let result =
+                        musq_entries(c, &mut musq_weekly, &range, config.proxy.clone()).await;
+                    (c.id.clone(), result)
+                }
+                ConfigImpl::Kmkc(c) => {
+                    let account_id = match c {
+                        crate::r#impl::kmkc::config::Config::Mobile(cc) => cc.id.clone(),
+                        crate::r#impl::kmkc::config::Config::Web(cc) => cc.id.clone(),
+                    };
+                    console.info(cformat!(
+                        "Fetching <s>KM</> releases for <m,s>{}</>...",
+                        account_id
+                    ));
+                    (
+                        account_id,
+                        kmkc_entries(c, &range, config.proxy.clone()).await,
+                    )
+                }
+                ConfigImpl::Amap(c) => {
+                    console.info(cformat!(
+                        "Fetching <s>AM</> releases for <m,s>{}</>...",
+                        c.id
+                    ));
+                    let result = amap_entries(c, &range, config.proxy.clone(), console).await;
+                    (c.id.clone(), result)
+                }
+                ConfigImpl::Mplus(c) => {
+                    console.info(cformat!(
+                        "Fetching <s>M+</> releases for <m,s>{}</>...",
+                        c.id
+                    ));
+                    let result = mplus_entries(c, &range, config.proxy.clone(), console).await;
+                    (c.id.clone(), result)
+                }
+                _ => unreachable!(),
+            };
+
+            match result {
+                Ok(result) => entries.extend(result),
+                Err(err) => {
+                    console.error(format!(
+                        "Failed to fetch {} releases for {}: {}",
+                        source.tag(),
+                        account_id,
+                        err
+                    ));
+                    exit_code = 1;
+                }
+            }
+        }
+    }
+
+    let entries: Vec<CalendarEntry> = entries.into_iter().collect();
+    let mut per_day: BTreeMap<NaiveDate, Vec<&CalendarEntry>> = BTreeMap::new();
+    for entry in entries.iter() {
+        per_day.entry(entry.date).or_default().push(entry);
+    }
+
+    console.info(cformat!(
+        "Upcoming releases from <s>{}</> to <s>{}</> (<m,s>{}</> releases):",
+        range.start.format("%Y-%m-%d"),
+        range.end.format("%Y-%m-%d"),
+        entries.len()
+    ));
+    if per_day.is_empty() {
+        console.warn("  No upcoming releases found for the followed titles");
+    }
+    for (date, day_entries) in per_day.iter() {
+        console.info(cformat!(
+            "  <s>{}</> ({})",
+            date.format("%Y-%m-%d"),
+            date.format("%A")
+        ));
+        for entry in day_entries {
+            let note = entry
+                .note
+                .as_ref()
+                .map(|note| format!(" - {note}"))
+                .unwrap_or_default();
+            console.info(cformat!(
+                "    [<s>{}</>] <m,s>{}</> ({}){}",
+                entry.source.tag(),
+                entry.title,
+                entry.id,
+                note
+            ));
+        }
+    }
+
+    if let Some(ics_path) = config.ics {
+        let content = render_ics(&entries, chrono::Utc::now());
+        match std::fs::write(&ics_path, content) {
+            Ok(_) => console.info(cformat!(
+                "Exported the calendar to <s>{}</>",
+                ics_path.display()
+            )),
+            Err(err) => {
+                console.error(format!("Failed to write the calendar: {err}"));
+                exit_code = 1;
+            }
+        }
+    }
+
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weekday_dates() {
+        // 2024-01-01 is a Monday
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let range = DateRange::new(start, 14);
+
+        assert_eq!(
+            range.weekday_dates(2),
+            vec![
+                NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+            ]
+        );
+        assert_eq!(range.weekday_dates(6).len(), 2);
+        assert!(DateRange::new(start, 1).weekday_dates(1).is_empty());
+    }
+
+    #[test]
+    fn test_render_ics() {
+        let stamp = chrono::Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let entries = vec![CalendarEntry {
+            date: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+            source: CalendarSource::Mplus,
+            title: "Title; with, special\\chars".to_string(),
+            id: 100,
+            note: Some("Weekly".to_string()),
+        }];
+
+        let ics = render_ics(&entries, stamp);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:mp-100-20240103@tosho-mango\r\n"));
+        assert!(ics.contains("DTSTAMP:20240101T120000Z\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240103\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20240104\r\n"));
+        assert!(ics.contains("SUMMARY:[M+] Title\\; with\\, special\\\\chars\r\n"));
+        assert!(ics.contains("DESCRIPTION:M+ title ID 100\\nWeekly\r\n"));
+
+        let folded = fold_ics_line(&"a".repeat(100));
+        assert_eq!(
+            folded,
+            format!("{}\r\n {}\r\n", "a".repeat(75), "a".repeat(25))
+        );
+    }
+}
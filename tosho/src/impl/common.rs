@@ -1,7 +1,13 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use chrono::TimeZone;
 
+use super::models::{DroppedPagesDump, IdDump, MangaDetailDump};
+
 pub(super) fn unix_timestamp_to_string(timestamp: i64) -> Option<String> {
     let dt = chrono::Utc.timestamp_opt(timestamp, 0).single();
 
@@ -16,28 +22,67 @@ pub(super) fn unix_timestamp_to_string(timestamp: i64) -> Option<String> {
     }
 }
 
-pub(super) fn check_downloaded_image_count(image_dir: &PathBuf, extension: &str) -> Option<usize> {
+/// Check if all the expected pages of a chapter have been downloaded.
+///
+/// The pages are matched by their file stem (e.g. `p000`) regardless of the extension,
+/// so changing the image format or quality would not make the chapter look undownloaded.
+///
+/// Zero-byte files and leftover temporary files are not counted, while pages dropped
+/// by `tools dedupe` are counted.
+pub(super) fn check_downloaded_pages<S: AsRef<str>>(
+    image_dir: &Path,
+    expected_pages: &[S],
+) -> bool {
     // check if dir exist
-    if !image_dir.exists() {
-        return None;
+    if !image_dir.is_dir() || expected_pages.is_empty() {
+        return false;
     }
 
-    // check if dir is dir
-    if !image_dir.is_dir() {
-        return None;
-    }
+    let Ok(entries) = std::fs::read_dir(image_dir) else {
+        return false;
+    };
 
-    // check how many .[extension] files in the dir
-    let mut count = 0;
-    for entry in std::fs::read_dir(image_dir).unwrap() {
-        let entry = entry.unwrap();
-        let path = entry.path();
-        if path.is_file() && path.extension().unwrap_or_default() == extension {
-            count += 1;
-        }
-    }
+    let mut downloaded: HashSet<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && !matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("json" | "tmp")
+                )
+                && path.metadata().map(|meta| meta.len() > 0).unwrap_or(false)
+        })
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+
+    // pages dropped by `tools dedupe` count as downloaded
+    downloaded.extend(
+        DroppedPagesDump::load(image_dir)
+            .pages
+            .iter()
+            .filter_map(|page| Some(Path::new(page).file_stem()?.to_str()?.to_string())),
+    );
 
-    Some(count)
+    expected_pages
+        .iter()
+        .all(|page| downloaded.contains(page.as_ref()))
+}
+
+/// Record the expected page count of a chapter into the `_info.json` dump,
+/// so `tools verify` can detect the missing pages later.
+pub(super) fn record_chapter_pages(
+    dump_info: &mut MangaDetailDump,
+    dump_path: &std::path::PathBuf,
+    chapter_id: impl Into<IdDump>,
+    pages: usize,
+    console: &crate::term::Terminal,
+) {
+    if dump_info.set_chapter_pages(&chapter_id.into(), pages as u64)
+        && let Err(err) = dump_info.dump(dump_path)
+    {
+        console.warn(format!("   Failed to save chapter page count: {err}"));
+    }
 }
 
 pub(super) fn check_chapter_folder_existence(image_dir: &std::path::Path) -> bool {
@@ -53,3 +98,110 @@ pub(super) fn check_chapter_folder_existence(image_dir: &std::path::Path) -> boo
 
     true
 }
+
+/// Set when a graceful shutdown is requested (e.g. by the daemon), downloads would stop
+/// after the page currently being downloaded.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn request_shutdown() {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub(super) fn is_shutdown_requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// The exit code when the download is stopped by a shutdown request, so the daemon and
+/// the API can tell it apart from a finished or failed download.
+pub(crate) const INTERRUPTED_EXIT_CODE: crate::cli::ExitCode = 130;
+
+/// Check if the chapter is left incomplete by a shutdown request.
+///
+/// The post-processing (dropping promotional pages, embedding metadata) should be skipped
+/// for it, the remaining pages are downloaded on the next run.
+pub(super) fn is_chapter_interrupted<S: AsRef<str>>(
+    image_dir: &Path,
+    expected_pages: &[S],
+) -> bool {
+    is_shutdown_requested() && !check_downloaded_pages(image_dir, expected_pages)
+}
+
+/// The chapter directories written by the downloads while the API is running a job,
+/// so it can report the results without walking the whole output directory.
+#[cfg(feature = "with-api")]
+static WRITTEN_CHAPTERS: std::sync::Mutex<Option<std::collections::BTreeSet<std::path::PathBuf>>> =
+    std::sync::Mutex::new(None);
+
+/// Record a chapter directory once all of its pages are written.
+///
+/// Nothing is recorded unless [`start_recording_chapters`] has been called.
+#[cfg(feature = "with-api")]
+pub(super) fn record_written_chapter(image_dir: &Path) {
+    if let Some(chapters) = WRITTEN_CHAPTERS.lock().unwrap().as_mut() {
+        chapters.insert(image_dir.to_path_buf());
+    }
+}
+
+#[cfg(not(feature = "with-api"))]
+pub(super) fn record_written_chapter(_image_dir: &Path) {}
+
+/// Start recording the written chapter directories from scratch.
+#[cfg(feature = "with-api")]
+pub(super) fn start_recording_chapters() {
+    *WRITTEN_CHAPTERS.lock().unwrap() = Some(Default::default());
+}
+
+/// The chapter directories written since [`start_recording_chapters`].
+#[cfg(feature = "with-api")]
+pub(super) fn written_chapters() -> Vec<std::path::PathBuf> {
+    WRITTEN_CHAPTERS
+        .lock()
+        .unwrap()
+        .iter()
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Stop recording and take the chapter directories written so far.
+#[cfg(feature = "with-api")]
+pub(super) fn take_written_chapters() -> Vec<std::path::PathBuf> {
+    WRITTEN_CHAPTERS
+        .lock()
+        .unwrap()
+        .take()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_downloaded_pages() {
+        let image_dir = std::env::temp_dir().join(format!("tosho-pages-{}", std::process::id()));
+        std::fs::create_dir_all(&image_dir).unwrap();
+
+        std::fs::write(image_dir.join("p000.avif"), b"data").unwrap();
+        std::fs::write(image_dir.join("p001.jpg"), b"data").unwrap();
+        std::fs::write(image_dir.join("p002.png"), b"").unwrap();
+
+        // extension does not matter
+        assert!(check_downloaded_pages(&image_dir, &["p000", "p001"]));
+        // zero-byte file is not downloaded
+        assert!(!check_downloaded_pages(
+            &image_dir,
+            &["p000", "p001", "p002"]
+        ));
+        assert!(!check_downloaded_pages(&image_dir, &["p003"]));
+        // leftover temporary file from an interrupted write
+        std::fs::write(image_dir.join("p003.tmp"), b"data").unwrap();
+        std::fs::write(image_dir.join("p004.avif.tmp"), b"data").unwrap();
+        assert!(!check_downloaded_pages(&image_dir, &["p003"]));
+        assert!(!check_downloaded_pages(&image_dir, &["p004"]));
+
+        std::fs::remove_dir_all(&image_dir).unwrap();
+    }
+}
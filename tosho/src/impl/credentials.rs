@@ -0,0 +1,154 @@
+//! Resolve the credentials used by the auth commands.
+//!
+//! A credential can be passed as the argument itself, or for secrets, read from a file
+//! (`--password-file`) or stdin (`--password-stdin`). When none of them are given, the
+//! `TOSHO_*` environment variable (or the file pointed by `TOSHO_*_FILE`) is used before
+//! prompting for it.
+
+use std::{io::BufRead, path::PathBuf};
+
+use color_print::cformat;
+
+/// A credential given in the command line.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CredentialArg {
+    /// The credential itself, always used as is
+    value: Option<String>,
+    /// Read the credential from the file
+    file: Option<PathBuf>,
+    /// Read the credential from the first line of stdin
+    stdin: bool,
+}
+
+impl CredentialArg {
+    pub(crate) fn new(value: Option<String>, file: Option<PathBuf>, stdin: bool) -> Self {
+        Self { value, file, stdin }
+    }
+}
+
+impl From<Option<String>> for CredentialArg {
+    fn from(value: Option<String>) -> Self {
+        Self::new(value, None, false)
+    }
+}
+
+/// Read the credential from the command line argument.
+///
+/// Only the trailing newline is removed, since the credential might contain spaces.
+fn read_credential_arg(
+    arg: CredentialArg,
+    stdin: &mut impl BufRead,
+) -> std::io::Result<Option<String>> {
+    let value = if let Some(value) = arg.value {
+        value
+    } else if let Some(path) = arg.file {
+        std::fs::read_to_string(path)?
+    } else if arg.stdin {
+        let mut line = String::new();
+        stdin.read_line(&mut line)?;
+        line
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(value.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn read_credential_env(env_key: &str) -> std::io::Result<Option<String>> {
+    if let Ok(value) = std::env::var(env_key) {
+        return Ok(Some(value));
+    }
+
+    match std::env::var(format!("{env_key}_FILE")) {
+        Ok(path) => {
+            let value = std::fs::read_to_string(path)?;
+            Ok(Some(value.trim_end_matches(['\r', '\n']).to_string()))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Resolve a credential from the argument, environment variable, or a prompt.
+///
+/// Never prompts when `no_input` is set, returns [`None`] after reporting the error
+/// if the credential can't be resolved.
+pub(crate) fn resolve_credential(
+    value: impl Into<CredentialArg>,
+    env_key: &str,
+    label: &str,
+    secret: bool,
+    no_input: bool,
+    console: &crate::term::Terminal,
+) -> Option<String> {
+    let resolved = match read_credential_arg(value.into(), &mut std::io::stdin().lock()) {
+        Ok(None) => read_credential_env(env_key),
+        resolved => resolved,
+    };
+
+    let resolved = match resolved {
+        Ok(Some(value)) => Some(value),
+        Ok(None) if no_input => None,
+        Ok(None) => console.input(&format!("{label}:"), secret),
+        Err(err) => {
+            console.error(format!("Failed to read {}: {err}", label.to_lowercase()));
+            return None;
+        }
+    };
+
+    match resolved {
+        Some(value) if !value.is_empty() => Some(value),
+        _ => {
+            console.error(cformat!(
+                "{} is required, pass it as an argument or set <s>{}</>",
+                label,
+                env_key
+            ));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_credential_arg() {
+        let mut stdin = std::io::Cursor::new("first line\r\nsecond line\n");
+        let from_stdin = CredentialArg::new(None, None, true);
+        assert_eq!(
+            read_credential_arg(from_stdin.clone(), &mut stdin).unwrap(),
+            Some("first line".to_string())
+        );
+        assert_eq!(
+            read_credential_arg(from_stdin, &mut stdin).unwrap(),
+            Some("second line".to_string())
+        );
+        assert_eq!(
+            read_credential_arg(Some(" pass word ".to_string()).into(), &mut stdin).unwrap(),
+            Some(" pass word ".to_string())
+        );
+        // the literal value is never treated as a path or stdin
+        assert_eq!(
+            read_credential_arg(Some("@bc123".to_string()).into(), &mut stdin).unwrap(),
+            Some("@bc123".to_string())
+        );
+        assert_eq!(
+            read_credential_arg(Some("-".to_string()).into(), &mut stdin).unwrap(),
+            Some("-".to_string())
+        );
+        assert_eq!(
+            read_credential_arg(CredentialArg::default(), &mut stdin).unwrap(),
+            None
+        );
+
+        let path = std::env::temp_dir().join("tosho-credential-test.txt");
+        std::fs::write(&path, "from file\n").unwrap();
+        let from_file = CredentialArg::new(None, Some(path.clone()), false);
+        assert_eq!(
+            read_credential_arg(from_file, &mut stdin).unwrap(),
+            Some("from file".to_string())
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+//! A minimal cron expression parser used by the daemon schedule.
+//!
+//! Supports the standard 5 fields (minute, hour, day of month, month, day of week)
+//! with `*`, lists, ranges, steps, and month/weekday names, plus the `@hourly`,
+//! `@daily`, `@weekly`, and `@monthly` shorthands.
+
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed cron expression, each field is a bitmask of the allowed values.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of month field is restricted (not `*`)
+    days_restricted: bool,
+    /// Whether the day of week field is restricted (not `*`)
+    weekdays_restricted: bool,
+}
+
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    let lowered = value.to_lowercase();
+    let parsed = match names.iter().position(|name| *name == lowered) {
+        // names always start at the field minimum (jan = 1, sun = 0)
+        Some(idx) => idx as u32 + min,
+        None => value
+            .parse::<u32>()
+            .map_err(|_| format!("invalid value `{value}`"))?,
+    };
+
+    if parsed < min || parsed > max {
+        return Err(format!("value `{value}` is out of range {min}-{max}"));
+    }
+    Ok(parsed)
+}
+
+/// Parse a single cron field into a bitmask.
+///
+/// Returns the bitmask and whether the field is restricted (not `*`).
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<(u64, bool), String> {
+    let mut mask = 0u64;
+    let mut restricted = false;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid step `{step}`"))?;
+                if step == 0 {
+                    return Err("step can't be zero".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            restricted = true;
+            (
+                parse_value(start, min, max, names)?,
+                parse_value(end, min, max, names)?,
+            )
+        } else {
+            restricted = true;
+            let start = parse_value(range, min, max, names)?;
+            // `5/15` means starting from 5 until the end of the range
+            let end = if part.contains('/') { max } else { start };
+            (start, end)
+        };
+
+        if start > end {
+            return Err(format!("invalid range `{range}`"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+        if range == "*" && step > 1 {
+            restricted = true;
+        }
+    }
+
+    Ok((mask, restricted))
+}
+
+impl CronSchedule {
+    /// Check if the schedule should run at the given minute.
+    ///
+    /// Like the classic cron, when both the day of month and the day of week are
+    /// restricted the schedule runs when either of them matches.
+    pub(crate) fn matches(&self, time: &NaiveDateTime) -> bool {
+        let is_set = |mask: u64, value: u32| mask & (1 << value) != 0;
+
+        let day_match = is_set(self.days, time.day());
+        let weekday_match = is_set(self.weekdays, time.weekday().num_days_from_sunday());
+        let date_match = if self.days_restricted && self.weekdays_restricted {
+            day_match || weekday_match
+        } else {
+            day_match && weekday_match
+        };
+
+        is_set(self.minutes, time.minute())
+            && is_set(self.hours, time.hour())
+            && is_set(self.months, time.month())
+            && date_match
+    }
+}
+
+impl std::str::FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = s.trim();
+        let expanded = match expression {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            expr => expr,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(format!(
+                "expected 5 fields in `{expression}`, got {}",
+                fields.len()
+            ));
+        };
+
+        let (minutes, _) = parse_field(minute, 0, 59, &[])?;
+        let (hours, _) = parse_field(hour, 0, 23, &[])?;
+        let (days, days_restricted) = parse_field(day, 1, 31, &[])?;
+        let (months, _) = parse_field(month, 1, 12, &MONTH_NAMES)?;
+        let (mut weekdays, weekdays_restricted) = parse_field(weekday, 0, 7, &WEEKDAY_NAMES)?;
+        // both 0 and 7 are sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes,
+            hours,
+            days,
+            months,
+            weekdays,
+            days_restricted,
+            weekdays_restricted,
+        })
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let schedule: CronSchedule = "*/15 9-17 * * mon-fri".parse().unwrap();
+        // 2026-10-19 is a monday
+        assert!(schedule.matches(&at("2026-10-19 09:00")));
+        assert!(schedule.matches(&at("2026-10-19 17:45")));
+        assert!(!schedule.matches(&at("2026-10-19 09:10")));
+        assert!(!schedule.matches(&at("2026-10-19 18:00")));
+        assert!(!schedule.matches(&at("2026-10-18 10:00")));
+
+        let daily: CronSchedule = "@daily".parse().unwrap();
+        assert!(daily.matches(&at("2026-10-18 00:00")));
+        assert!(!daily.matches(&at("2026-10-18 00:01")));
+
+        // sunday can be written as 7
+        let sunday: CronSchedule = "30 6 * * 7".parse().unwrap();
+        assert!(sunday.matches(&at("2026-10-18 06:30")));
+
+        // day of month OR day of week when both are restricted
+        let either: CronSchedule = "0 0 1 * fri".parse().unwrap();
+        assert!(either.matches(&at("2026-10-01 00:00")));
+        assert!(either.matches(&at("2026-10-23 00:00")));
+        assert!(!either.matches(&at("2026-10-22 00:00")));
+
+        let stepped: CronSchedule = "5/20 * * jan,dec *".parse().unwrap();
+        assert!(stepped.matches(&at("2026-12-01 10:45")));
+        assert!(!stepped.matches(&at("2026-11-01 10:45")));
+
+        assert!("* * * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert!("10-5 * * * *".parse::<CronSchedule>().is_err());
+    }
+}
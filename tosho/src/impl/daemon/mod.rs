@@ -0,0 +1,689 @@
+//! Keep tosho running in the background and sync titles on a schedule.
+//!
+//! The schedule is a JSON file (`daemon.json` in the user config directory by default),
+//! each job runs the `autodownload` command of its source in-process so it behaves the
+//! same as running it manually, a panic in the command only fails that job. Jobs that
+//! are still queued or interrupted on shutdown are saved into `daemon_queue.json` and
+//! run first on the next start.
+
+use std::{
+    collections::VecDeque,
+    io::Write,
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use chrono::{Local, NaiveDateTime, TimeDelta, Timelike};
+use clap::Parser;
+use color_print::cformat;
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::{
+    cli::{ExitCode, ToshoCli},
+    config::{ConfigImpl, get_all_config, get_user_path},
+    r#impl::{
+        Implementations,
+        common::{is_shutdown_requested, request_shutdown},
+    },
+};
+
+pub(crate) mod cron;
+
+use cron::CronSchedule;
+
+/// The sources that can be synced by the daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) enum DaemonSource {
+    #[serde(rename = "mu")]
+    Musq,
+    #[serde(rename = "km")]
+    Kmkc,
+    #[serde(rename = "am")]
+    Amap,
+    #[serde(rename = "sj")]
+    Sjv,
+    #[serde(rename = "rb")]
+    Rbean,
+    #[serde(rename = "mp")]
+    Mplus,
+    #[serde(rename = "ni")]
+    Nids,
+}
+
+impl DaemonSource {
+    pub(crate) fn slug(&self) -> &'static str {
+        match self {
+            Self::Musq => "mu",
+            Self::Kmkc => "km",
+            Self::Amap => "am",
+            Self::Sjv => "sj",
+            Self::Rbean => "rb",
+            Self::Mplus => "mp",
+            Self::Nids => "ni",
+        }
+    }
+
+    /// The command used to download the title, NI downloads a single issue.
+    fn download_command(&self) -> &'static str {
+        match self {
+            Self::Nids => "download",
+            _ => "autodownload",
+        }
+    }
+}
+
+/// The title to sync, either a numeric ID or a slug/UUID depending on the source
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum DaemonTitle {
+    Id(u64),
+    Slug(String),
+}
+
+impl std::fmt::Display for DaemonTitle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "{id}"),
+            Self::Slug(slug) => write!(f, "{slug}"),
+        }
+    }
+}
+
+/// A title to sync with the `autodownload` command of its source (`download` for NI)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct SyncTarget {
+    pub(crate) source: DaemonSource,
+    /// The account ID to use, the default account is used when omitted
+    pub(crate) account: Option<String>,
+    pub(crate) title: DaemonTitle,
+    /// The output directory, passed as `--output`
+    ///
+    /// NI uses it as the issue folder, so the issue is saved into `<output>/<issue ID>`.
+    pub(crate) output: Option<PathBuf>,
+    /// Additional arguments passed to the `autodownload` command
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+}
+
+impl SyncTarget {
+    /// Build the command line used to sync the title.
+    pub(crate) fn argv(&self, verbose: u8, proxy: Option<&str>) -> Vec<String> {
+        let mut argv = vec!["tosho".to_string()];
+        if verbose > 0 {
+            argv.push(format!("-{}", "v".repeat(verbose as usize)));
+        }
+        if let Some(proxy) = proxy {
+            argv.extend(["--proxy".to_string(), proxy.to_string()]);
+        }
+        argv.push(self.source.slug().to_string());
+        if let Some(account) = &self.account {
+            argv.extend(["--account".to_string(), account.clone()]);
+        }
+        argv.extend([
+            self.source.download_command().to_string(),
+            self.title.to_string(),
+        ]);
+        if let Some(output) = &self.output {
+            let output = match self.source {
+                DaemonSource::Nids => output.join(self.title.to_string()),
+                _ => output.clone(),
+            };
+            argv.extend(["--output".to_string(), output.display().to_string()]);
+        }
+        argv.extend(self.args.iter().cloned());
+        argv
+    }
+
+    /// Sync the title in-process, returning the exit code of the command.
+    ///
+    /// A panic in the command is caught and returned as an error.
+    pub(crate) async fn run(
+        &self,
+        verbose: u8,
+        proxy: Option<&str>,
+    ) -> color_eyre::Result<ExitCode> {
+        let cli = ToshoCli::try_parse_from(self.argv(verbose, proxy))?;
+        match AssertUnwindSafe(Box::pin(crate::entrypoint(cli)))
+            .catch_unwind()
+            .await
+        {
+            Ok(result) => result,
+            Err(panic) => {
+                color_eyre::eyre::bail!("The command panicked: {}", panic_message(&*panic))
+            }
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// A single scheduled sync of a title
+#[derive(Debug, Clone, Deserialize)]
+struct DaemonJob {
+    /// The job name, defaults to `<source>-<title>`
+    name: Option<String>,
+    #[serde(flatten)]
+    target: SyncTarget,
+    /// The cron expression of when to sync the title
+    schedule: CronSchedule,
+}
+
+impl DaemonJob {
+    fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{}-{}", self.target.source.slug(), self.target.title))
+    }
+}
+
+/// The daemon schedule file
+#[derive(Debug, Clone, Deserialize)]
+struct DaemonSchedule {
+    /// The cron expression of when to refresh the tokens/sessions of every account
+    refresh: Option<CronSchedule>,
+    #[serde(default)]
+    jobs: Vec<DaemonJob>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct DaemonConfig {
+    /// The schedule file, defaults to `daemon.json` in the user config directory
+    pub(crate) schedule: Option<PathBuf>,
+    /// The structured log file, defaults to `daemon.log` in the user config directory
+    pub(crate) log_file: Option<PathBuf>,
+    /// The verbosity passed to every job
+    pub(crate) verbose: u8,
+    /// The raw proxy URL passed to every job
+    pub(crate) proxy_url: Option<String>,
+    pub(crate) proxy: Option<reqwest::Proxy>,
+}
+
+/// A single line of the structured log
+#[derive(Debug, Serialize)]
+struct DaemonLogEntry<'a> {
+    time: String,
+    level: &'a str,
+    event: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<ExitCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_secs: Option<f64>,
+    message: String,
+}
+
+/// Write the structured (JSON lines) logs into a file and the console.
+struct DaemonLog<'a> {
+    file: Option<std::fs::File>,
+    console: &'a crate::term::Terminal,
+}
+
+impl<'a> DaemonLog<'a> {
+    fn open(path: &Path, console: &'a crate::term::Terminal) -> Self {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path);
+        let file = match file {
+            Ok(file) => Some(file),
+            Err(err) => {
+                console.warn(format!(
+                    "Unable to open log file {}: {err}, logging to console only",
+                    path.display()
+                ));
+                None
+            }
+        };
+
+        Self { file, console }
+    }
+
+    fn write(&mut self, entry: DaemonLogEntry) {
+        match entry.level {
+            "error" => self.console.error(&entry.message),
+            "warn" => self.console.warn(&entry.message),
+            _ => self.console.info(&entry.message),
+        }
+
+        if let Some(file) = self.file.as_mut()
+            && let Ok(line) = serde_json::to_string(&entry)
+            && let Err(err) = writeln!(file, "{line}")
+        {
+            self.console
+                .warn(format!("Unable to write log file: {err}"));
+        }
+    }
+
+    fn event(&mut self, level: &str, event: &str, message: impl Into<String>) {
+        self.write(DaemonLogEntry {
+            time: Local::now().to_rfc3339(),
+            level,
+            event,
+            job: None,
+            exit_code: None,
+            duration_secs: None,
+            message: message.into(),
+        });
+    }
+
+    fn job(
+        &mut self,
+        event: &str,
+        job: &str,
+        exit_code: ExitCode,
+        duration_secs: f64,
+        message: impl Into<String>,
+    ) {
+        self.write(DaemonLogEntry {
+            time: Local::now().to_rfc3339(),
+            level: if exit_code == 0 { "info" } else { "error" },
+            event,
+            job: Some(job),
+            exit_code: Some(exit_code),
+            duration_secs: Some(duration_secs),
+            message: message.into(),
+        });
+    }
+}
+
+fn queue_path() -> PathBuf {
+    get_user_path().join("daemon_queue.json")
+}
+
+fn load_queue() -> VecDeque<String> {
+    std::fs::read(queue_path())
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_queue(queue: &VecDeque<String>) -> std::io::Result<()> {
+    let path = queue_path();
+    if queue.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+    }
+
+    let user_path = get_user_path();
+    if !user_path.exists() {
+        std::fs::create_dir_all(&user_path)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(queue)?)
+}
+
+fn load_schedule(path: &Path) -> Result<DaemonSchedule, String> {
+    let data = std::fs::read(path).map_err(|err| err.to_string())?;
+    let schedule: DaemonSchedule = serde_json::from_slice(&data).map_err(|err| err.to_string())?;
+
+    let mut names: Vec<String> = vec![];
+    for job in schedule.jobs.iter() {
+        let name = job.name();
+        if names.contains(&name) {
+            return Err(format!("duplicate job name `{name}`"));
+        }
+        names.push(name);
+    }
+
+    Ok(schedule)
+}
+
+fn truncate_minute(time: NaiveDateTime) -> NaiveDateTime {
+    time.with_second(0)
+        .and_then(|time| time.with_nanosecond(0))
+        .unwrap_or(time)
+}
+
+/// Wait until Ctrl+C (or SIGTERM on unix) is received.
+pub(crate) async fn wait_for_shutdown() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = terminate.recv() => {},
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Refresh the NI tokens then check every account, which also saves the rotated sessions.
+async fn refresh_accounts(proxy: Option<reqwest::Proxy>, console: &crate::term::Terminal) -> u32 {
+    let mut exit_code = 0;
+    for config in get_all_config(&Implementations::Nids, None) {
+        let ConfigImpl::Nids(config) = config else {
+            continue;
+        };
+        if config.refresh_token().is_empty() {
+            continue;
+        }
+
+        let client = match super::client::make_nids_client(&config) {
+            Ok(client) => client,
+            Err(err) => {
+                console.error(format!("Failed to create client: {err}"));
+                exit_code = 1;
+                continue;
+            }
+        };
+        let client = match proxy.clone() {
+            Some(proxy) => match client.with_proxy(proxy) {
+                Ok(client) => client,
+                Err(err) => {
+                    console.error(format!("Failed to set proxy: {err}"));
+                    exit_code = 1;
+                    continue;
+                }
+            },
+            None => client,
+        };
+
+        let code =
+            super::nids::accounts::nids_account_refresh(None, &client, &config, console).await;
+        if code != 0 {
+            exit_code = code;
+        }
+    }
+
+    let code = super::accounts::accounts_check(proxy, console).await;
+    if code != 0 { code } else { exit_code }
+}
+
+/// Run the daemon until a shutdown is requested.
+pub(crate) async fn tosho_daemon(
+    config: DaemonConfig,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let schedule_path = config
+        .schedule
+        .clone()
+        .unwrap_or_else(|| get_user_path().join("daemon.json"));
+    let schedule = match load_schedule(&schedule_path) {
+        Ok(schedule) => schedule,
+        Err(err) => {
+            console.error(format!(
+                "Failed to load schedule {}: {err}",
+                schedule_path.display()
+            ));
+            return 1;
+        }
+    };
+
+    // validate every job before starting, so we don't fail in the middle of the night
+    let mut jobs: Vec<(String, DaemonJob)> = vec![];
+    for job in schedule.jobs.iter() {
+        let name = job.name();
+        let argv = job.target.argv(config.verbose, config.proxy_url.as_deref());
+        match ToshoCli::try_parse_from(&argv) {
+            Ok(_) => jobs.push((name, job.clone())),
+            Err(err) => {
+                console.error(cformat!("Invalid job <m,s>{}</>:", name));
+                console.error(err.to_string());
+                return 1;
+            }
+        }
+    }
+
+    if jobs.is_empty() && schedule.refresh.is_none() {
+        console.warn("Nothing is scheduled, exiting");
+        return 0;
+    }
+
+    let log_path = config
+        .log_file
+        .clone()
+        .unwrap_or_else(|| get_user_path().join("daemon.log"));
+    let mut log = DaemonLog::open(&log_path, console);
+
+    let notify = Arc::new(Notify::new());
+    let shutdown_notify = notify.clone();
+    tokio::spawn(async move {
+        wait_for_shutdown().await;
+        request_shutdown();
+        shutdown_notify.notify_one();
+    });
+
+    let mut queue: VecDeque<String> = load_queue()
+        .into_iter()
+        .filter(|name| jobs.iter().any(|(job_name, _)| job_name == name))
+        .collect();
+    log.event(
+        "info",
+        "started",
+        format!(
+            "Daemon started with {} jobs, {} resumed from the queue",
+            jobs.len(),
+            queue.len()
+        ),
+    );
+
+    let mut last_tick = truncate_minute(Local::now().naive_local());
+    loop {
+        while let Some(name) = queue.pop_front() {
+            if is_shutdown_requested() {
+                queue.push_front(name);
+                break;
+            }
+            let Some((_, job)) = jobs.iter().find(|(job_name, _)| *job_name == name) else {
+                continue;
+            };
+
+            log.event(
+                "info",
+                "job_started",
+                format!(
+                    "Syncing {name} ({}) from {}",
+                    job.target.title,
+                    job.target.source.slug()
+                ),
+            );
+            let started = Instant::now();
+            let exit_code = match job
+                .target
+                .run(config.verbose, config.proxy_url.as_deref())
+                .await
+            {
+                Ok(code) => code,
+                Err(err) => {
+                    log.event("error", "job_error", format!("Job {name} failed: {err}"));
+                    1
+                }
+            };
+            let duration = started.elapsed().as_secs_f64();
+
+            if is_shutdown_requested() {
+                // finish it on the next start, downloaded chapters would be skipped
+                queue.push_front(name.clone());
+                log.job(
+                    "job_interrupted",
+                    &name,
+                    exit_code,
+                    duration,
+                    format!("Job {name} is interrupted, it will be resumed on the next start"),
+                );
+                break;
+            }
+
+            log.job(
+                "job_finished",
+                &name,
+                exit_code,
+                duration,
+                format!("Job {name} finished with exit code {exit_code} in {duration:.1}s"),
+            );
+            if let Err(err) = save_queue(&queue) {
+                log.event(
+                    "warn",
+                    "queue_error",
+                    format!("Unable to save queue: {err}"),
+                );
+            }
+        }
+
+        if is_shutdown_requested() {
+            break;
+        }
+
+        let next_tick = last_tick + TimeDelta::minutes(1);
+        let wait = (next_tick - Local::now().naive_local())
+            .to_std()
+            .unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {},
+            _ = notify.notified() => {},
+        }
+        if is_shutdown_requested() {
+            break;
+        }
+
+        // check every minute since the last tick, in case we were suspended or slept long
+        let now = truncate_minute(Local::now().naive_local());
+        let mut refresh_due = false;
+        let mut minute = last_tick + TimeDelta::minutes(1);
+        while minute <= now {
+            for (name, job) in jobs.iter() {
+                if job.schedule.matches(&minute) && !queue.contains(name) {
+                    queue.push_back(name.clone());
+                }
+            }
+            if schedule
+                .refresh
+                .as_ref()
+                .is_some_and(|refresh| refresh.matches(&minute))
+            {
+                refresh_due = true;
+            }
+            minute += TimeDelta::minutes(1);
+        }
+        last_tick = now.max(last_tick);
+
+        if refresh_due {
+            log.event("info", "refresh_started", "Refreshing account sessions");
+            let started = Instant::now();
+            let exit_code = refresh_accounts(config.proxy.clone(), console).await;
+            log.job(
+                "refresh_finished",
+                "refresh",
+                exit_code,
+                started.elapsed().as_secs_f64(),
+                format!("Account refresh finished with exit code {exit_code}"),
+            );
+        }
+    }
+
+    match save_queue(&queue) {
+        Ok(_) if !queue.is_empty() => log.event(
+            "info",
+            "queue_saved",
+            format!("Saved {} pending jobs into the queue", queue.len()),
+        ),
+        Ok(_) => {}
+        Err(err) => log.event(
+            "error",
+            "queue_error",
+            format!("Unable to save queue: {err}"),
+        ),
+    }
+    log.event("info", "stopped", "Daemon stopped");
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_argv() {
+        let job: DaemonJob = serde_json::from_str(
+            r#"{
+                "source": "mu",
+                "account": "abc",
+                "title": 123,
+                "schedule": "0 */6 * * *",
+                "output": "/tmp/manga",
+                "args": ["--no-purchase"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(job.name(), "mu-123");
+        let argv = job.target.argv(2, Some("socks5://127.0.0.1:1080"));
+        assert_eq!(
+            argv,
+            [
+                "tosho",
+                "-vv",
+                "--proxy",
+                "socks5://127.0.0.1:1080",
+                "mu",
+                "--account",
+                "abc",
+                "autodownload",
+                "123",
+                "--output",
+                "/tmp/manga",
+                "--no-purchase"
+            ]
+        );
+        assert!(ToshoCli::try_parse_from(&argv).is_ok());
+
+        // NI downloads a single issue into its own folder
+        let job: DaemonJob = serde_json::from_str(
+            r#"{"source": "ni", "title": 42, "schedule": "0 0 * * *", "output": "/tmp/comics"}"#,
+        )
+        .unwrap();
+        let argv = job.target.argv(0, None);
+        assert_eq!(
+            argv,
+            [
+                "tosho",
+                "ni",
+                "download",
+                "42",
+                "--output",
+                "/tmp/comics/42"
+            ]
+        );
+        assert!(ToshoCli::try_parse_from(&argv).is_ok());
+
+        let invalid: Result<DaemonJob, _> =
+            serde_json::from_str(r#"{"source": "km", "title": "1", "schedule": "0 0 * *"}"#);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let panic = std::panic::catch_unwind(|| panic!("Failed to dump title info")).unwrap_err();
+        assert_eq!(panic_message(&*panic), "Failed to dump title info");
+
+        let page = 3;
+        let panic = std::panic::catch_unwind(|| panic!("page {page}")).unwrap_err();
+        assert_eq!(panic_message(&*panic), "page 3");
+    }
+}
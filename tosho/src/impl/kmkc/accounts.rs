@@ -3,13 +3,16 @@ use std::path::PathBuf;
 use clap::ValueEnum;
 use color_print::cformat;
 use num_format::{Locale, ToFormattedString};
-use tosho_kmkc::{KMClient, KMConfig, KMConfigMobile, KMConfigMobilePlatform};
+use tosho_kmkc::{KMClient, KMConfig, KMConfigMobile, KMConfigMobilePlatform, KMConfigWeb};
 use tosho_macros::EnumName;
 
 use crate::{
     cli::ExitCode,
     config::{get_all_config, save_config, try_remove_config},
-    r#impl::client::make_kmkc_client,
+    r#impl::{
+        client::make_kmkc_client,
+        credentials::{CredentialArg, resolve_credential},
+    },
     term::ConsoleChoice,
 };
 
@@ -85,9 +88,27 @@ impl TryFrom<DeviceKind> for KMConfigMobilePlatform {
 }
 
 pub(crate) async fn kmkc_account_login_web(
-    cookies_path: PathBuf,
+    cookies_path: Option<PathBuf>,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
+    let cookies_path = match cookies_path {
+        Some(cookies_path) => cookies_path,
+        None => {
+            let Some(cookies_path) = resolve_credential(
+                None,
+                "TOSHO_KM_COOKIES",
+                "Path to Netscape cookies file",
+                false,
+                no_input,
+                console,
+            ) else {
+                return 1;
+            };
+            PathBuf::from(cookies_path)
+        }
+    };
+
     console.info("Authenticating your account...");
 
     // parse netscape cookies
@@ -117,7 +138,8 @@ pub(crate) async fn kmkc_account_login_web(
 
                     if let Some(old_config) = old_config {
                         console.warn("Session ID already exists!");
-                        let abort_it = console.confirm(Some("Do you want to replace it?"));
+                        let abort_it =
+                            no_input || console.confirm(Some("Do you want to replace it?"));
                         if !abort_it {
                             console.info("Aborting...");
                             return 0;
@@ -153,20 +175,45 @@ pub(crate) async fn kmkc_account_login_web(
 }
 
 pub(crate) async fn kmkc_account_login_mobile(
-    user_id: u32,
-    hash_key: String,
+    user_id: Option<String>,
+    hash_key: CredentialArg,
     platform: DeviceKind,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
+    let Some(user_id) = resolve_credential(
+        user_id,
+        "TOSHO_KM_USER_ID",
+        "User ID",
+        false,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+    let Ok(user_id) = user_id.parse::<u32>() else {
+        console.error(format!("Invalid user ID: {user_id}"));
+        return 1;
+    };
+    let Some(hash_key) = resolve_credential(
+        hash_key,
+        "TOSHO_KM_HASH_KEY",
+        "Hash key",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
     if platform == DeviceKind::Web {
         console.warn("Invalid platform!");
         return 1;
     }
 
     console.info(cformat!(
-        "Authenticating with <m,s>{}</> and key <m,s>{}</> [{}]",
+        "Authenticating with <m,s>{}</> [{}]",
         user_id,
-        hash_key,
         platform.to_name()
     ));
 
@@ -183,7 +230,7 @@ pub(crate) async fn kmkc_account_login_mobile(
     let mut old_id: Option<String> = None;
     if let Some(old_config) = old_config {
         console.warn("Session ID already authenticated!");
-        let abort_it = console.confirm(Some("Do you want to replace it?"));
+        let abort_it = no_input || console.confirm(Some("Do you want to replace it?"));
         if !abort_it {
             console.info("Aborting...");
             return 0;
@@ -240,16 +287,29 @@ pub(crate) async fn kmkc_account_login_mobile(
 }
 
 pub async fn kmkc_account_login(
-    email: String,
-    password: String,
+    email: Option<String>,
+    password: CredentialArg,
     platform: DeviceKind,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
-    console.info(cformat!(
-        "Authenticating with email <m,s>{}</> and password <m,s>{}</>...",
-        email,
-        password
-    ));
+    let Some(email) =
+        resolve_credential(email, "TOSHO_KM_EMAIL", "Email", false, no_input, console)
+    else {
+        return 1;
+    };
+    let Some(password) = resolve_credential(
+        password,
+        "TOSHO_KM_PASSWORD",
+        "Password",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
+    console.info(cformat!("Authenticating with email <m,s>{}</>...", email));
 
     let all_configs = get_all_config(&crate::r#impl::Implementations::Kmkc, None);
 
@@ -264,7 +324,7 @@ pub async fn kmkc_account_login(
     let mut old_id: Option<String> = None;
     if let Some(old_config) = old_config {
         console.warn("Session ID already authenticated!");
-        let abort_it = console.confirm(Some("Do you want to replace it?"));
+        let abort_it = no_input || console.confirm(Some("Do you want to replace it?"));
         if !abort_it {
             console.info("Aborting...");
             return 0;
@@ -551,6 +611,59 @@ pub(crate) async fn kmkc_balance(
     }
 }
 
+/// Save the rotated web cookies back into the config, if any of them changed.
+pub(crate) fn save_session_config(client: &KMClient, account: &Config) {
+    let Config::Web(web) = account else {
+        return;
+    };
+
+    let store = client.get_cookie_store().clone();
+    if let Ok(session) = KMConfigWeb::try_from(store) {
+        let updated = web.with_session(&session);
+        if &updated != web {
+            save_config(crate::config::ConfigImpl::Kmkc(Config::Web(updated)), None);
+        }
+    }
+}
+
+pub(crate) fn kmkc_account_export_cookies(
+    client: &KMClient,
+    account: &Config,
+    output: PathBuf,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    if !matches!(account, Config::Web(_)) {
+        console.error("Only web accounts can be exported as cookies!");
+        return 1;
+    }
+
+    let store = client.get_cookie_store().clone();
+    let content = KMConfigWeb::try_from(store).and_then(|session| session.to_netscape());
+    let content = match content {
+        Ok(content) => content,
+        Err(err) => {
+            console.error(format!("Failed to export cookies: {err}"));
+            return 1;
+        }
+    };
+
+    match std::fs::write(&output, content) {
+        Ok(_) => {
+            console.info(cformat!(
+                "Exported cookies of <m,s>{}</> to <s>{}</>",
+                account.get_id(),
+                output.display()
+            ));
+            console.warn("The file contains your session, do not share it with anyone!");
+            0
+        }
+        Err(err) => {
+            console.error(format!("Failed to write cookies file: {err}"));
+            1
+        }
+    }
+}
+
 pub(crate) fn kmkc_account_revoke(account: &Config, console: &crate::term::Terminal) -> ExitCode {
     let confirm = console.confirm(Some(&cformat!(
         "Are you sure you want to delete <m,s>{}</>?\nThis action is irreversible!",
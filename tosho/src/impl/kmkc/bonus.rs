@@ -0,0 +1,308 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use color_print::cformat;
+use num_format::{Locale, ToFormattedString};
+use serde::{Deserialize, Serialize};
+use tosho_kmkc::{KMClient, models::EpisodeNode};
+
+use crate::{cli::ExitCode, config::get_user_path};
+
+use super::config::Config;
+
+/// The default maximum amount of episodes to claim the bonus point from per day.
+pub(crate) const DEFAULT_DAILY_CLAIM_LIMIT: u32 = 30;
+
+/// A log of the claimed bonus point, saved per account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BonusClaimLog {
+    /// The day (in JST) of the current counter, in `YYYY-MM-DD` format
+    #[serde(default)]
+    day: String,
+    /// Amount of episodes claimed on that day
+    #[serde(default)]
+    claimed_today: u32,
+    /// Amount of bonus point gained on that day
+    #[serde(default)]
+    points_today: u64,
+    /// All the episodes that has been claimed
+    #[serde(default)]
+    claimed: BTreeSet<u32>,
+}
+
+impl BonusClaimLog {
+    /// Reset the daily counter if the day has changed
+    fn roll_over(&mut self, today: &str) {
+        if self.day != today {
+            self.day = today.to_string();
+            self.claimed_today = 0;
+            self.points_today = 0;
+        }
+    }
+
+    fn remaining(&self, limit: u32) -> u32 {
+        limit.saturating_sub(self.claimed_today)
+    }
+
+    fn record(&mut self, episode_id: u32, points: i32) {
+        self.claimed.insert(episode_id);
+        self.claimed_today += 1;
+        self.points_today += points.max(0) as u64;
+    }
+}
+
+/// Get the current day in JST since KM daily reset follows it
+fn today_jst() -> String {
+    let jst = chrono::Utc::now() + chrono::Duration::hours(9);
+    jst.format("%Y-%m-%d").to_string()
+}
+
+/// Claim the bonus point of the finished episodes while respecting the daily limit.
+pub(super) struct BonusClaimer {
+    path: PathBuf,
+    log: BonusClaimLog,
+    limit: u32,
+    /// Whether the daily limit warning has been shown
+    limit_warned: bool,
+}
+
+impl BonusClaimer {
+    pub(super) fn load(account: &Config, limit: u32) -> Self {
+        let path = get_user_path().join(format!("kmkc_bonus_claims_{}.json", account.get_id()));
+        let mut log: BonusClaimLog = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        log.roll_over(&today_jst());
+
+        Self {
+            path,
+            log,
+            limit,
+            limit_warned: false,
+        }
+    }
+
+    fn save(&self) {
+        if let Ok(data) = serde_json::to_vec(&self.log) {
+            let _ = std::fs::write(&self.path, data);
+        }
+    }
+
+    /// The amount of episodes that can still be claimed today
+    pub(super) fn remaining(&self) -> u32 {
+        self.log.remaining(self.limit)
+    }
+
+    /// Check if the episode has any bonus point that has not been claimed yet
+    pub(super) fn is_claimable(&self, episode: &EpisodeNode) -> bool {
+        episode.bonus_point() > 0 && !self.log.claimed.contains(&episode.id())
+    }
+
+    /// Finish the episode viewer to claim the bonus point.
+    ///
+    /// The episode viewer must be fetched before calling this.
+    /// Returns the amount of bonus point gained.
+    pub(super) async fn claim(
+        &mut self,
+        client: &KMClient,
+        episode: &EpisodeNode,
+        console: &crate::term::Terminal,
+    ) -> Option<i32> {
+        if self.remaining() == 0 {
+            if !self.limit_warned {
+                self.limit_warned = true;
+                console.warn(cformat!(
+                    "   Reached the daily limit of <m,s>{}</> bonus claims, skipping the rest for today",
+                    self.limit
+                ));
+            }
+            return None;
+        }
+
+        match client.finish_episode_viewer(episode).await {
+            Ok(finish) => {
+                self.log.record(episode.id(), finish.bonus_point());
+                self.save();
+                console.info(cformat!(
+                    "   Claimed <y,s>{}</> bonus point from <m,s>{}</> ({})",
+                    finish.bonus_point(),
+                    episode.title(),
+                    episode.id()
+                ));
+                Some(finish.bonus_point())
+            }
+            Err(err) => {
+                console.error(cformat!(
+                    "   Failed to claim bonus point from <m,s>{}</> ({}): {}",
+                    episode.title(),
+                    episode.id(),
+                    err
+                ));
+                None
+            }
+        }
+    }
+
+    /// Print the daily claim summary
+    pub(super) fn report(&self, console: &crate::term::Terminal) {
+        console.info(cformat!(
+            "Claimed <m,s>{}</>/<m,s>{}</> episodes today for a total of <y,s>{}</> bonus point",
+            self.log.claimed_today,
+            self.limit,
+            self.log.points_today.to_formatted_string(&Locale::en)
+        ));
+    }
+}
+
+async fn fetch_title_episodes(
+    client: &KMClient,
+    title_id: u32,
+    console: &crate::term::Terminal,
+) -> Option<Vec<EpisodeNode>> {
+    let titles = match client.get_titles(vec![title_id]).await {
+        Ok(titles) => titles,
+        Err(err) => {
+            console.error(format!("Failed to get title information: {err}"));
+            return None;
+        }
+    };
+
+    let Some(title) = titles.first() else {
+        console.warn(cformat!("Unable to find title <m,s>{}</>", title_id));
+        return None;
+    };
+
+    console.info(cformat!(
+        "Fetching <m,s>{}</> <s>{}</> chapters...",
+        title.title(),
+        title.episode_ids().len()
+    ));
+
+    let mut episodes = vec![];
+    for chunk in title.episode_ids().chunks(50) {
+        match client.get_episodes(chunk.to_vec()).await {
+            Ok(chapters) => episodes.extend(chapters),
+            Err(err) => {
+                console.error(format!("Failed to get chapters: {err}"));
+                return None;
+            }
+        }
+    }
+
+    Some(episodes)
+}
+
+pub(crate) async fn kmkc_claim_bonus(
+    title_ids: Vec<u32>,
+    limit: u32,
+    client: &KMClient,
+    account: &Config,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let mut claimer = BonusClaimer::load(account, limit);
+    if claimer.remaining() == 0 {
+        console.warn("Daily bonus point claim limit reached, try again tomorrow");
+        claimer.report(console);
+        return 0;
+    }
+
+    let title_ids = if title_ids.is_empty() {
+        console.info(cformat!(
+            "Getting user purchased title for <m,s>{}</>...",
+            account.get_username()
+        ));
+        match client.get_purchased().await {
+            Ok(purchased) => purchased
+                .iter()
+                .filter_map(|title| u32::try_from(title.id()).ok())
+                .collect(),
+            Err(err) => {
+                console.error(format!("Failed to get purchased title: {err}"));
+                return 1;
+            }
+        }
+    } else {
+        title_ids
+    };
+
+    let mut claimed_count = 0;
+    let mut claimed_point = 0;
+    'titles: for title_id in title_ids {
+        let Some(episodes) = fetch_title_episodes(client, title_id, console).await else {
+            continue;
+        };
+
+        // only chapters that can be read can be finished
+        let claimable: Vec<&EpisodeNode> = episodes
+            .iter()
+            .filter(|ep| ep.is_available() && claimer.is_claimable(ep))
+            .collect();
+        for episode in claimable {
+            if claimer.remaining() == 0 {
+                console.warn("Daily bonus point claim limit reached, stopping");
+                break 'titles;
+            }
+
+            // the viewer needs to be opened first before finishing it
+            if let Err(err) = client.get_episode_viewer(episode).await {
+                console.warn(cformat!(
+                    "   Chapter <m,s>{}</> ({}) is not available: {}",
+                    episode.title(),
+                    episode.id(),
+                    err
+                ));
+                continue;
+            }
+
+            if let Some(point) = claimer.claim(client, episode, console).await {
+                claimed_count += 1;
+                claimed_point += point.max(0) as u64;
+            }
+        }
+    }
+
+    console.info(cformat!(
+        "Claimed <y,s>{}</> bonus point from <m,s>{}</> episodes",
+        claimed_point.to_formatted_string(&Locale::en),
+        claimed_count
+    ));
+    claimer.report(console);
+
+    if let Ok(balance) = client.get_user_point().await {
+        console.info(cformat!(
+            "Current point balance: <cyan!,bold>{}</>c",
+            balance
+                .point()
+                .total_point()
+                .to_formatted_string(&Locale::en)
+        ));
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bonus_claim_log_limit() {
+        let mut log = BonusClaimLog::default();
+        log.roll_over("2026-01-01");
+        log.record(1, 10);
+        log.record(2, 5);
+
+        assert_eq!(log.remaining(3), 1);
+        assert_eq!(log.points_today, 15);
+
+        // same day should keep the counter
+        log.roll_over("2026-01-01");
+        assert_eq!(log.remaining(2), 0);
+
+        // new day reset the counter but keep the claimed episodes
+        log.roll_over("2026-01-02");
+        assert_eq!(log.remaining(2), 2);
+        assert_eq!(log.points_today, 0);
+        assert!(log.claimed.contains(&1));
+    }
+}
@@ -50,7 +50,12 @@ pub(super) fn do_print_search_information(
 pub(super) fn parse_netscape_cookies(cookie_path: PathBuf) -> KMConfigWeb {
     let term = get_console(0);
 
-    let read_cookie = match std::fs::read_to_string(cookie_path) {
+    let read_cookie = if cookie_path.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(cookie_path)
+    };
+    let read_cookie = match read_cookie {
         Ok(read_cookie) => read_cookie,
         Err(e) => {
             term.error(format!("Failed to read cookie file: {e}"));
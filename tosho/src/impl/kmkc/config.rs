@@ -245,6 +245,18 @@ impl ConfigWeb {
         config
     }
 
+    /// Combine the config with the rotated cookies from [`tosho_kmkc::KMConfigWeb`].
+    pub fn with_session(&self, session: &tosho_kmkc::KMConfigWeb) -> Self {
+        let mut config = self.clone();
+
+        config.uwt = session.uwt().to_string();
+        config.birthday = Some(session.birthday().clone().into());
+        config.tos_adult = Some(session.tos_adult().clone().into());
+        config.privacy = Some(session.privacy().clone().into());
+
+        config
+    }
+
     /// Combine the config with the old ID.
     pub fn with_id(&self, id: String) -> Self {
         let mut config = self.clone();
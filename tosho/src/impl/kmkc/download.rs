@@ -8,14 +8,21 @@ use tosho_kmkc::{
     models::{EpisodeNode, EpisodeViewerResponse, ScrambleSeed, TicketInfoType, TitleNode},
 };
 
-use crate::r#impl::common::{check_chapter_folder_existence, check_downloaded_image_count};
+use crate::r#impl::common::{
+    INTERRUPTED_EXIT_CODE, check_chapter_folder_existence, check_downloaded_pages,
+    is_chapter_interrupted, is_shutdown_requested, record_chapter_pages, record_written_chapter,
+};
 use crate::term::Terminal;
 use crate::{
     cli::ExitCode,
-    r#impl::models::{ChapterDetailDump, MangaDetailDump},
+    r#impl::{
+        models::{ChapterDetailDump, MangaDetailDump, SourceDump},
+        pool::{PoolPayment, PoolReceipts},
+        provenance::{ChapterProvenance, PageSource, embed_chapter_metadata},
+    },
 };
 
-use super::{common::common_purchase_select, config::Config};
+use super::{bonus::BonusClaimer, common::common_purchase_select, config::Config};
 
 #[derive(Clone, Debug, Default)]
 pub(crate) struct KMDownloadCliConfig {
@@ -46,6 +53,14 @@ pub(crate) struct KMDownloadCliConfig {
 
     /// Auto download ignore any images checking but just check for folder existence
     pub(crate) only_check_folder: bool,
+    /// Embed the source metadata into the downloaded pages
+    pub(crate) embed_metadata: bool,
+    /// Claim the bonus point of the downloaded chapters
+    pub(crate) claim_bonus: bool,
+    /// Maximum amount of chapters to claim the bonus point from per day
+    ///
+    /// Default to [`super::bonus::DEFAULT_DAILY_CLAIM_LIMIT`].
+    pub(crate) bonus_limit: Option<u32>,
 }
 
 fn create_chapters_info(title: &TitleNode, chapters: &[EpisodeNode]) -> MangaDetailDump {
@@ -59,7 +74,7 @@ fn create_chapters_info(title: &TitleNode, chapters: &[EpisodeNode]) -> MangaDet
     )
 }
 
-fn get_output_directory(
+pub(super) fn get_output_directory(
     output_dir: &Path,
     title_id: u32,
     chapter_id: Option<u32>,
@@ -95,6 +110,11 @@ async fn kmkc_actual_downloader(
     console: Terminal,
     progress: Arc<indicatif::ProgressBar>,
 ) -> color_eyre::eyre::Result<()> {
+    // stop before the next page when shutting down
+    if is_shutdown_requested() {
+        return Ok(());
+    }
+
     let image_fn = format!("p{:03}.{}", node.idx, node.extension);
     let img_dl_path = image_dir.join(&image_fn);
 
@@ -139,6 +159,28 @@ pub(crate) async fn kmkc_download(
     client: &KMClient,
     account: &Config,
     console: &mut crate::term::Terminal,
+) -> ExitCode {
+    kmkc_download_with_receipts(
+        title_id,
+        dl_config,
+        output_dir,
+        client,
+        account,
+        &mut PoolReceipts::default(),
+        console,
+    )
+    .await
+}
+
+/// Same as [`kmkc_download`], but also report the chapters that are unlocked and saved.
+pub(crate) async fn kmkc_download_with_receipts(
+    title_id: u32,
+    dl_config: KMDownloadCliConfig,
+    output_dir: PathBuf,
+    client: &KMClient,
+    account: &Config,
+    receipts: &mut PoolReceipts,
+    console: &mut crate::term::Terminal,
 ) -> ExitCode {
     if let (Some(start), Some(end)) = (dl_config.start_from, dl_config.end_at)
         && start > end
@@ -163,24 +205,28 @@ pub(crate) async fn kmkc_download(
             let results: Vec<&EpisodeNode> = results
                 .iter()
                 .filter(|&ch| {
+                    // allow if chapter_ids is empty or chapter id is in chapter_ids
+                    let selected = dl_config.chapter_ids.is_empty()
+                        || dl_config.chapter_ids.contains(&(ch.id() as usize));
+
                     if dl_config.no_input {
                         // check if chapter id is in range
-                        match (dl_config.start_from, dl_config.end_at) {
-                            (Some(start), Some(end)) => {
-                                // between start and end
-                                ch.id() >= start && ch.id() <= end
-                            }
-                            (Some(start), None) => {
-                                ch.id() >= start // start to end
-                            }
-                            (None, Some(end)) => {
-                                ch.id() <= end // 0 to end
+                        selected
+                            && match (dl_config.start_from, dl_config.end_at) {
+                                (Some(start), Some(end)) => {
+                                    // between start and end
+                                    ch.id() >= start && ch.id() <= end
+                                }
+                                (Some(start), None) => {
+                                    ch.id() >= start // start to end
+                                }
+                                (None, Some(end)) => {
+                                    ch.id() <= end // 0 to end
+                                }
+                                _ => true,
                             }
-                            _ => true,
-                        }
                     } else {
-                        dl_config.chapter_ids.is_empty()
-                            || dl_config.chapter_ids.contains(&(ch.id() as usize))
+                        selected
                     }
                 })
                 .collect();
@@ -197,6 +243,12 @@ pub(crate) async fn kmkc_download(
             // let mut chapters_with_bonus = vec![];
             for chapter in results {
                 if chapter.is_available() {
+                    receipts.unlocked(
+                        chapter.id() as u64,
+                        chapter.title(),
+                        PoolPayment::Owned,
+                        "owned",
+                    );
                     download_chapters.push(chapter);
                     continue;
                 }
@@ -215,6 +267,7 @@ pub(crate) async fn kmkc_download(
                 if should_purchase {
                     if chapter.is_ticketable() && !dl_config.no_ticket {
                         let mut ticket_info = None;
+                        let mut ticket_cost = "";
                         if ticket_entry.is_title_available() {
                             console.info(cformat!(
                                 "  Using title ticket to purchase chapter <m,s>{}</> (<s>{}</>)...",
@@ -223,6 +276,7 @@ pub(crate) async fn kmkc_download(
                             ));
                             ticket_info =
                                 Some(TicketInfoType::Title(ticket_entry.info().title().unwrap()));
+                            ticket_cost = "title ticket";
                             ticket_entry.subtract_title();
                         } else if ticket_entry.is_premium_available() {
                             console.info(cformat!(
@@ -233,6 +287,7 @@ pub(crate) async fn kmkc_download(
                             ticket_info = Some(TicketInfoType::Premium(
                                 ticket_entry.info().premium().unwrap(),
                             ));
+                            ticket_cost = "premium ticket";
                             ticket_entry.subtract_premium();
                         }
 
@@ -242,6 +297,12 @@ pub(crate) async fn kmkc_download(
                                 .await
                             {
                                 Ok(_) => {
+                                    receipts.unlocked(
+                                        chapter.id() as u64,
+                                        chapter.title(),
+                                        PoolPayment::Free,
+                                        ticket_cost,
+                                    );
                                     download_chapters.push(chapter);
                                     // if chapter.bonus_point > 0 {
                                     //     chapters_with_bonus.push(chapter.id);
@@ -281,8 +342,20 @@ pub(crate) async fn kmkc_download(
                         chapter.id(),
                         chapter.point()
                     ));
+                    let price: u64 = chapter.point().try_into().unwrap_or(0);
+                    let payment = if wallet_copy.free_point() >= price {
+                        PoolPayment::Free
+                    } else {
+                        PoolPayment::Paid
+                    };
                     match client.claim_episode(chapter, &mut wallet_copy).await {
                         Ok(_) => {
+                            receipts.unlocked(
+                                chapter.id() as u64,
+                                chapter.title(),
+                                payment,
+                                format!("{price}P"),
+                            );
                             download_chapters.push(chapter);
                             // if chapter.bonus_point > 0 {
                             //     chapters_with_bonus.push(chapter.id);
@@ -303,14 +376,32 @@ pub(crate) async fn kmkc_download(
             download_chapters.sort_by_key(|&a| a.id());
 
             let title_dir = get_output_directory(&output_dir, title_id, None, true);
-            let dump_info = create_chapters_info(&title_detail, &all_chapters);
+            let mut dump_info = create_chapters_info(&title_detail, &all_chapters)
+                .with_source(SourceDump::Kmkc, title_id as u64);
 
             let title_dump_path = title_dir.join("_info.json");
+            // keep the chapter information from the previous download
+            if let Some(older_info) = MangaDetailDump::load(&title_dump_path) {
+                dump_info.inherit_chapter_meta(&older_info);
+            }
             dump_info
                 .dump(&title_dump_path)
                 .expect("Failed to dump title info");
 
+            let mut bonus_claimer = dl_config.claim_bonus.then(|| {
+                let limit = dl_config
+                    .bonus_limit
+                    .unwrap_or(super::bonus::DEFAULT_DAILY_CLAIM_LIMIT);
+                BonusClaimer::load(account, limit)
+            });
+
+            let mut exit_code = 0;
             for chapter in download_chapters {
+                if is_shutdown_requested() {
+                    console.warn("Shutdown requested, stopping the download");
+                    exit_code = INTERRUPTED_EXIT_CODE;
+                    break;
+                }
                 console.info(cformat!(
                     "  Downloading chapter <m,s>{}</> ({})...",
                     chapter.title(),
@@ -358,23 +449,47 @@ pub(crate) async fn kmkc_download(
                     continue;
                 }
 
-                if dl_config.only_check_folder {
-                    if check_chapter_folder_existence(&image_dir) {
+                record_chapter_pages(
+                    &mut dump_info,
+                    &title_dump_path,
+                    chapter.id() as u64,
+                    total_count,
+                    console,
+                );
+
+                let page_stems: Vec<String> =
+                    (0..total_count).map(|idx| format!("p{idx:03}")).collect();
+                let already_downloaded = if dl_config.only_check_folder {
+                    let exists = check_chapter_folder_existence(&image_dir);
+                    if exists {
                         console.info(cformat!(
                             "   Chapter <m,s>{}</> (<s>{}</>) folder exists, skipping",
                             chapter.title(),
                             chapter.id()
                         ));
-                        continue;
                     }
-                } else if let Some(count) = check_downloaded_image_count(&image_dir, "jpg")
-                    && count >= total_count
-                {
-                    console.warn(cformat!(
-                        "   Chapter <m,s>{}</> (<s>{}</>) already downloaded, skipping",
-                        chapter.title(),
-                        chapter.id()
-                    ));
+                    exists
+                } else {
+                    let downloaded = check_downloaded_pages(&image_dir, &page_stems);
+                    if downloaded {
+                        console.warn(cformat!(
+                            "   Chapter <m,s>{}</> (<s>{}</>) already downloaded, skipping",
+                            chapter.title(),
+                            chapter.id()
+                        ));
+                    }
+                    downloaded
+                };
+
+                if already_downloaded {
+                    receipts.downloaded(chapter.id() as u64);
+
+                    // chapters downloaded in an earlier run can still have their bonus unclaimed
+                    if let Some(claimer) = bonus_claimer.as_mut()
+                        && claimer.is_claimable(chapter)
+                    {
+                        claimer.claim(client, chapter, console).await;
+                    }
                     continue;
                 }
 
@@ -467,9 +582,50 @@ pub(crate) async fn kmkc_download(
                 }
 
                 progress.finish_with_message("Downloaded");
+
+                if is_chapter_interrupted(&image_dir, &page_stems) {
+                    console.warn(cformat!(
+                        "   Shutdown requested, chapter <m,s>{}</> (<s>{}</>) is left incomplete",
+                        chapter.title(),
+                        chapter.id()
+                    ));
+                    exit_code = INTERRUPTED_EXIT_CODE;
+                    break;
+                }
+                record_written_chapter(&image_dir);
+
+                if dl_config.embed_metadata {
+                    let provenance = ChapterProvenance {
+                        source: SourceDump::Kmkc,
+                        title: dump_info.title_name.clone(),
+                        title_id: (title_id as u64).into(),
+                        chapter: chapter.title().to_string(),
+                        chapter_id: (chapter.id() as u64).into(),
+                    };
+                    let page_sources: Vec<PageSource> = image_blocks
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, image)| {
+                            PageSource::new(format!("p{idx:03}"), Some(image.url()))
+                        })
+                        .collect();
+                    embed_chapter_metadata(&image_dir, &provenance, page_sources, console).await;
+                }
+
+                receipts.downloaded(chapter.id() as u64);
+
+                if let Some(claimer) = bonus_claimer.as_mut()
+                    && claimer.is_claimable(chapter)
+                {
+                    claimer.claim(client, chapter, console).await;
+                }
+            }
+
+            if let Some(claimer) = &bonus_claimer {
+                claimer.report(console);
             }
 
-            0
+            exit_code
         }
         _ => 1,
     }
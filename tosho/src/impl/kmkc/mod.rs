@@ -2,16 +2,21 @@ use std::{ops::RangeInclusive, path::PathBuf};
 
 use clap::Subcommand;
 
-use super::parser::{CommaSeparatedNumber, WeeklyCodeCli, parse_comma_number};
+use super::parser::{
+    CommaSeparatedNumber, CommaSeparatedString, WeeklyCodeCli, parse_comma_number,
+    parse_comma_string,
+};
 
 use self::rankings::RankingType;
 
 pub(crate) mod accounts;
+pub(crate) mod bonus;
 pub(super) mod common;
 pub(crate) mod config;
 pub(crate) mod download;
 pub(crate) mod favorites;
 pub(crate) mod manga;
+pub(crate) mod pool;
 pub(crate) mod purchases;
 pub(crate) mod rankings;
 
@@ -22,31 +27,62 @@ pub(crate) enum KMKCCommands {
     /// The following use email/password authentication
     Auth {
         /// Email to use
-        email: String,
+        ///
+        /// Uses `TOSHO_KM_EMAIL` (or `TOSHO_KM_EMAIL_FILE`) when omitted.
+        email: Option<String>,
         /// Password to use
-        password: String,
+        ///
+        /// Uses `TOSHO_KM_PASSWORD` (or `TOSHO_KM_PASSWORD_FILE`) when omitted.
+        password: Option<String>,
+        /// Read the password from a file instead
+        #[arg(long = "password-file", conflicts_with_all = ["password", "password_stdin"])]
+        password_file: Option<PathBuf>,
+        /// Read the password from the first line of stdin instead
+        #[arg(long = "password-stdin", conflicts_with = "password")]
+        password_stdin: bool,
         /// Device kind/type to use
         #[arg(short, long, value_enum, default_value = "android")]
         r#type: crate::r#impl::kmkc::accounts::DeviceKind,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Authenticate tosho with your KM account.
     ///
     /// The following use user ID/hash key to authenticate as mobile.
     AuthMobile {
         /// User ID to use
-        user_id: u32,
+        ///
+        /// Uses `TOSHO_KM_USER_ID` (or `TOSHO_KM_USER_ID_FILE`) when omitted.
+        user_id: Option<String>,
         /// Hash key to use
-        hash_key: String,
+        ///
+        /// Uses `TOSHO_KM_HASH_KEY` (or `TOSHO_KM_HASH_KEY_FILE`) when omitted.
+        hash_key: Option<String>,
+        /// Read the hash key from a file instead
+        #[arg(long = "hash-key-file", conflicts_with_all = ["hash_key", "hash_key_stdin"])]
+        hash_key_file: Option<PathBuf>,
+        /// Read the hash key from the first line of stdin instead
+        #[arg(long = "hash-key-stdin", conflicts_with = "hash_key")]
+        hash_key_stdin: bool,
         /// Device kind/type to use
         #[arg(short, long, value_enum, default_value = "android")]
         r#type: crate::r#impl::kmkc::accounts::DeviceKind,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Authenticate tosho with your KM account.
     ///
     /// The following use Netscape cookies to authenticate as web.
     AuthWeb {
-        /// Path to Netscape cookies file
-        cookies: PathBuf,
+        /// Path to Netscape cookies file, `-` to read the cookies from stdin
+        ///
+        /// Uses `TOSHO_KM_COOKIES` when omitted.
+        cookies: Option<PathBuf>,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Adapt web config/account to mobile config/account
     AuthAdapt {
@@ -81,6 +117,17 @@ pub(crate) enum KMKCCommands {
         /// Disable the use of points to purchase chapters
         #[arg(long)]
         no_point: bool,
+        /// Claim the bonus point of the downloaded chapters (respects the daily claim limit)
+        #[arg(long = "claim-bonus")]
+        claim_bonus: bool,
+        /// Maximum amount of chapters to claim the bonus point from per day
+        ///
+        /// Needs to be used with `--claim-bonus` flag.
+        #[arg(long = "bonus-limit", default_value_t = self::bonus::DEFAULT_DAILY_CLAIM_LIMIT)]
+        bonus_limit: u32,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
@@ -95,9 +142,31 @@ pub(crate) enum KMKCCommands {
         /// Needs to be used with `--parallel` flag.
         #[arg(short = 't', long = "threads", default_value = "4")]
         threads: usize,
+        /// Use the accounts in the named pool, see the `pool` command
+        ///
+        /// Each chapter is unlocked by the account that owns it or can pay with free currency first.
+        #[arg(long = "pool", default_value = None)]
+        pool: Option<String>,
     },
     /// Get your account point balance
     Balance,
+    /// Export the web account cookies into a Netscape cookies file
+    ///
+    /// The file can be imported into a browser or used with `auth-web` later.
+    #[command(name = "export-cookies")]
+    ExportCookies {
+        /// Path to the output Netscape cookies file
+        output: PathBuf,
+    },
+    /// Claim the bonus point from finishing the viewer of read chapters
+    #[command(name = "claim-bonus")]
+    ClaimBonus {
+        /// Title ID to claim, default to all of your purchased titles
+        title_ids: Vec<u32>,
+        /// Maximum amount of chapters to claim per day
+        #[arg(short = 'l', long = "limit", default_value_t = self::bonus::DEFAULT_DAILY_CLAIM_LIMIT)]
+        limit: u32,
+    },
     /// Download a chapters from a title
     Download {
         /// Title ID to use
@@ -111,6 +180,17 @@ pub(crate) enum KMKCCommands {
         /// Automatically purchase chapters if needed
         #[arg(short = 'p', long = "auto-purchase")]
         auto_purchase: bool,
+        /// Claim the bonus point of the downloaded chapters (respects the daily claim limit)
+        #[arg(long = "claim-bonus")]
+        claim_bonus: bool,
+        /// Maximum amount of chapters to claim the bonus point from per day
+        ///
+        /// Needs to be used with `--claim-bonus` flag.
+        #[arg(long = "bonus-limit", default_value_t = self::bonus::DEFAULT_DAILY_CLAIM_LIMIT)]
+        bonus_limit: u32,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
@@ -138,6 +218,19 @@ pub(crate) enum KMKCCommands {
     },
     /// Get magazines list information
     Magazines,
+    /// Manage the account pools used by `autodownload --pool`
+    ///
+    /// Show all pools when no name is given, or show the pool when no accounts is given.
+    Pool {
+        /// The pool name
+        name: Option<String>,
+        /// The account IDs in the pool, in order of preference (ex: id1,id2)
+        #[arg(short = 'a', long = "accounts", default_value = None, value_parser = parse_comma_string)]
+        account_ids: Option<CommaSeparatedString>,
+        /// Remove the pool
+        #[arg(short = 'r', long = "remove")]
+        remove: bool,
+    },
     /// Purchases chapters for a title
     Purchase {
         /// Title ID to use
@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+
+use tosho_kmkc::{
+    KMClient,
+    models::{EpisodeNode, TitleTicketListNode, UserPoint},
+};
+
+use crate::{
+    cli::ExitCode,
+    config::ConfigImpl,
+    r#impl::{
+        Implementations,
+        client::make_kmkc_client,
+        pool::{PoolPayment, PoolRange, PoolReceipts, PoolSource, is_chapter_downloaded},
+    },
+};
+
+use super::{
+    accounts::save_session_config,
+    config::Config,
+    download::{KMDownloadCliConfig, get_output_directory, kmkc_download_with_receipts},
+};
+
+/// A pool member with the chapters and balance as seen by the account
+pub(crate) struct KMPoolMember {
+    account: Config,
+    client: KMClient,
+    episodes: Vec<EpisodeNode>,
+    wallet: UserPoint,
+    ticket: TitleTicketListNode,
+}
+
+/// How the account is going to unlock the chapter
+#[derive(Debug, Clone, Copy)]
+enum KMUnlock {
+    Owned,
+    TitleTicket,
+    PremiumTicket,
+    Point(u64),
+}
+
+impl KMUnlock {
+    fn payment(&self, wallet: &UserPoint) -> PoolPayment {
+        match self {
+            KMUnlock::Owned => PoolPayment::Owned,
+            KMUnlock::TitleTicket | KMUnlock::PremiumTicket => PoolPayment::Free,
+            KMUnlock::Point(price) if wallet.free_point() >= *price => PoolPayment::Free,
+            KMUnlock::Point(_) => PoolPayment::Paid,
+        }
+    }
+}
+
+/// Download a title with every KM account in a pool.
+pub(crate) struct KMPool {
+    pub(crate) title_id: u32,
+    pub(crate) dl_config: KMDownloadCliConfig,
+    pub(crate) output_dir: PathBuf,
+}
+
+impl KMPool {
+    fn member_unlock(&self, member: &KMPoolMember, chapter_id: u64) -> Option<KMUnlock> {
+        let episode = member
+            .episodes
+            .iter()
+            .find(|ep| ep.id() as u64 == chapter_id)?;
+        if episode.is_available() {
+            return Some(KMUnlock::Owned);
+        }
+        if !self.dl_config.auto_purchase {
+            return None;
+        }
+
+        if episode.is_ticketable() && !self.dl_config.no_ticket {
+            if member.ticket.is_title_available() {
+                return Some(KMUnlock::TitleTicket);
+            }
+            if member.ticket.is_premium_available() {
+                return Some(KMUnlock::PremiumTicket);
+            }
+        }
+
+        let price: u64 = episode.point().try_into().unwrap_or(0);
+        if !self.dl_config.no_point && member.wallet.can_purchase(price) {
+            return Some(KMUnlock::Point(price));
+        }
+
+        None
+    }
+}
+
+impl PoolSource for KMPool {
+    type Account = Config;
+    type Member = KMPoolMember;
+
+    const IMPLEMENTATION: Implementations = Implementations::Kmkc;
+
+    fn title_id(&self) -> u64 {
+        self.title_id as u64
+    }
+
+    fn range(&self) -> PoolRange {
+        PoolRange {
+            chapter_ids: self.dl_config.chapter_ids.clone(),
+            start_from: self.dl_config.start_from.map(u64::from),
+            end_at: self.dl_config.end_at.map(u64::from),
+        }
+    }
+
+    fn account(config: ConfigImpl) -> Option<Config> {
+        match config {
+            ConfigImpl::Kmkc(config) => Some(config),
+            _ => None,
+        }
+    }
+
+    fn account_id(account: &Config) -> &str {
+        account.get_id()
+    }
+
+    async fn fetch_member(
+        &self,
+        account: Config,
+        proxy: Option<reqwest::Proxy>,
+    ) -> color_eyre::Result<KMPoolMember> {
+        let client = make_kmkc_client(&account.clone().into())?;
+        let client = match proxy {
+            Some(proxy) => client.with_proxy(proxy)?,
+            None => client,
+        };
+
+        let user_point = client.get_user_point().await?;
+        let titles = client.get_titles(vec![self.title_id]).await?;
+        let Some(title) = titles.first() else {
+            color_eyre::eyre::bail!("Unable to find title information");
+        };
+        let ticket = client.get_title_ticket(self.title_id).await?;
+
+        let mut episodes = vec![];
+        for chunk in title.episode_ids().chunks(50) {
+            episodes.extend(client.get_episodes(chunk.to_vec()).await?);
+        }
+
+        Ok(KMPoolMember {
+            account,
+            client,
+            episodes,
+            wallet: user_point.point().clone(),
+            ticket,
+        })
+    }
+
+    fn balance(&self, member: &KMPoolMember) -> String {
+        format!(
+            "{}P free, {}P paid, tickets: {}",
+            member.wallet.free_point(),
+            member.wallet.paid_point(),
+            member.ticket.has_ticket()
+        )
+    }
+
+    fn chapters(&self, member: &KMPoolMember) -> Vec<(u64, String)> {
+        member
+            .episodes
+            .iter()
+            .map(|ep| (ep.id() as u64, ep.title().to_string()))
+            .collect()
+    }
+
+    fn is_downloaded(&self, chapter_id: u64) -> bool {
+        let title_dir = get_output_directory(&self.output_dir, self.title_id, None, false);
+        let ch_dir = get_output_directory(
+            &self.output_dir,
+            self.title_id,
+            Some(chapter_id as u32),
+            false,
+        );
+        is_chapter_downloaded(
+            &title_dir,
+            &ch_dir,
+            chapter_id,
+            self.dl_config.only_check_folder,
+        )
+    }
+
+    fn payment(&self, member: &KMPoolMember, chapter_id: u64) -> Option<PoolPayment> {
+        self.member_unlock(member, chapter_id)
+            .map(|unlock| unlock.payment(&member.wallet))
+    }
+
+    fn reserve(&self, member: &mut KMPoolMember, chapter_id: u64) {
+        match self.member_unlock(member, chapter_id) {
+            Some(KMUnlock::TitleTicket) => member.ticket.subtract_title(),
+            Some(KMUnlock::PremiumTicket) => member.ticket.subtract_premium(),
+            Some(KMUnlock::Point(price)) => member.wallet.subtract(price),
+            Some(KMUnlock::Owned) | None => {}
+        }
+    }
+
+    async fn download(
+        &self,
+        member: &KMPoolMember,
+        chapter_ids: Vec<usize>,
+        receipts: &mut PoolReceipts,
+        console: &mut crate::term::Terminal,
+    ) -> ExitCode {
+        let member_config = KMDownloadCliConfig {
+            no_input: true,
+            chapter_ids,
+            start_from: None,
+            end_at: None,
+            ..self.dl_config.clone()
+        };
+        kmkc_download_with_receipts(
+            self.title_id,
+            member_config,
+            self.output_dir.clone(),
+            &member.client,
+            &member.account,
+            receipts,
+            console,
+        )
+        .await
+    }
+
+    fn finish(&self, member: &KMPoolMember) {
+        save_session_config(&member.client, &member.account);
+    }
+}
@@ -1,12 +1,20 @@
+pub(crate) mod accounts;
 pub(crate) mod amap;
+#[cfg(feature = "with-api")]
+pub(crate) mod api;
+pub(crate) mod calendar;
 pub(crate) mod client;
 pub(super) mod common;
+pub(crate) mod credentials;
+pub(crate) mod daemon;
 pub(crate) mod kmkc;
 pub(crate) mod models;
 pub(crate) mod mplus;
 pub(crate) mod musq;
 pub(crate) mod nids;
 pub(super) mod parser;
+pub(crate) mod pool;
+pub(crate) mod provenance;
 pub(crate) mod rbean;
 pub(crate) mod sjv;
 pub(crate) mod tools;
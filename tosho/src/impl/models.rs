@@ -3,7 +3,10 @@ use std::str::FromStr;
 use serde::{Deserialize, Serialize};
 use tosho_amap::models::{ComicEpisodeInfo, ComicEpisodeInfoNode};
 use tosho_kmkc::models::EpisodeNode;
-use tosho_mplus::proto::Chapter as MPChapter;
+use tosho_mplus::proto::{
+    Chapter as MPChapter, ChapterPageBanner as MPChapterPageBanner,
+    ChapterViewer as MPChapterViewer, Comment as MPComment,
+};
 use tosho_musq::proto::ChapterV2;
 use tosho_rbean::models::Chapter;
 use tosho_sjv::models::MangaChapterDetail;
@@ -64,6 +67,135 @@ pub struct ChapterDetailDump {
     timestamp: Option<i64>,
     /// The sub chapter name, if any.
     sub_name: Option<String>,
+    /// Extra metadata of the chapter, collected when downloading.
+    ///
+    /// Only available for some sources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    meta: Option<ChapterMetaDump>,
+    /// The expected page count of the chapter, recorded when downloading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) pages: Option<u64>,
+}
+
+/// A dump info of a chapter comment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentDump {
+    /// The comment ID.
+    id: u64,
+    /// The commenter name.
+    user_name: String,
+    /// The commenter avatar URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_avatar: Option<String>,
+    /// The comment content.
+    content: String,
+    /// The number of likes.
+    likes: u64,
+    /// The timestamp of the comment.
+    timestamp: i64,
+}
+
+/// A dump info of a banner shown in the chapter viewer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BannerDump {
+    /// The title of the banner section.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// The banner image URL.
+    image: String,
+    /// The link of the banner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link: Option<String>,
+}
+
+/// A dump info of the extra chapter metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterMetaDump {
+    /// The previous chapter ID.
+    previous_chapter: Option<IdDump>,
+    /// The next chapter ID.
+    next_chapter: Option<IdDump>,
+    /// The timestamp of when the next chapter will be released.
+    next_chapter_at: Option<i64>,
+    /// The number of comments of the chapter.
+    comment_count: Option<u64>,
+    /// The top comments of the chapter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    top_comments: Vec<CommentDump>,
+    /// The banner pages of the chapter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    page_banners: Vec<BannerDump>,
+    /// The banners inserted in between the pages of the chapter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    insert_banners: Vec<BannerDump>,
+}
+
+/// The source of a dumped manga.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceDump {
+    /// KM by KC
+    Kmkc,
+    /// MU! by SQ
+    Musq,
+    /// AM by AP
+    Amap,
+    /// SJ/M by V
+    Sjv,
+    /// 小豆 by KRKR
+    Rbean,
+    /// M+ by S
+    Mplus,
+    /// NI by DS
+    Nids,
+}
+
+impl clap::ValueEnum for SourceDump {
+    fn from_str(input: &str, ignore_case: bool) -> Result<Self, String> {
+        let input = if ignore_case {
+            input.to_lowercase()
+        } else {
+            input.to_string()
+        };
+        match input.as_str() {
+            "km" | "kmkc" => Ok(Self::Kmkc),
+            "mu" | "musq" => Ok(Self::Musq),
+            "am" | "amap" => Ok(Self::Amap),
+            "sj" | "sjv" => Ok(Self::Sjv),
+            "rb" | "rbean" => Ok(Self::Rbean),
+            "mp" | "mplus" => Ok(Self::Mplus),
+            "ni" | "nids" => Ok(Self::Nids),
+            _ => Err(format!("Invalid source: {input}")),
+        }
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        let name = match self {
+            Self::Kmkc => "km",
+            Self::Musq => "mu",
+            Self::Amap => "am",
+            Self::Sjv => "sj",
+            Self::Rbean => "rb",
+            Self::Mplus => "mp",
+            Self::Nids => "ni",
+        };
+        Some(clap::builder::PossibleValue::new(name))
+    }
+
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Kmkc,
+            Self::Musq,
+            Self::Amap,
+            Self::Sjv,
+            Self::Rbean,
+            Self::Mplus,
+            Self::Nids,
+        ]
+    }
 }
 
 /// A dump info of a manga.
@@ -73,6 +205,17 @@ pub struct MangaDetailDump {
     pub(crate) title_name: String,
     author_name: String,
     pub(crate) chapters: Vec<ChapterDetailDump>,
+    /// The source where the manga is downloaded from.
+    ///
+    /// Older dumps does not have this field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) source: Option<SourceDump>,
+    /// The title ID in the source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) title_id: Option<IdDump>,
+    /// The language code of the title, only recorded for M+ (e.g. `eng`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) language: Option<String>,
 }
 
 impl MangaDetailDump {
@@ -81,9 +224,80 @@ impl MangaDetailDump {
             title_name: title,
             author_name: author,
             chapters,
+            source: None,
+            title_id: None,
+            language: None,
         }
     }
 
+    /// Attach the source information of the manga.
+    ///
+    /// # Arguments
+    /// * `source` - The source where the manga is downloaded from.
+    /// * `title_id` - The title ID in the source.
+    pub fn with_source(mut self, source: SourceDump, title_id: impl Into<IdDump>) -> Self {
+        self.source = Some(source);
+        self.title_id = Some(title_id.into());
+        self
+    }
+
+    /// Attach the language code of the title.
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Attach the extra metadata to a chapter.
+    ///
+    /// Returns `false` if the chapter is not found.
+    pub fn set_chapter_meta(&mut self, chapter_id: &IdDump, meta: ChapterMetaDump) -> bool {
+        match self.chapters.iter_mut().find(|ch| &ch.id == chapter_id) {
+            Some(chapter) => {
+                chapter.meta = Some(meta);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the expected page count of a chapter.
+    ///
+    /// Returns `true` if the page count is changed.
+    pub fn set_chapter_pages(&mut self, chapter_id: &IdDump, pages: u64) -> bool {
+        match self.chapters.iter_mut().find(|ch| &ch.id == chapter_id) {
+            Some(chapter) if chapter.pages != Some(pages) => {
+                chapter.pages = Some(pages);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Carry over the chapter metadata and page count from an older dump,
+    /// this is used to keep the information of chapters that are not downloaded again.
+    pub fn inherit_chapter_meta(&mut self, older: &MangaDetailDump) {
+        for chapter in self.chapters.iter_mut() {
+            let Some(older_chapter) = older.chapters.iter().find(|ch| ch.id == chapter.id) else {
+                continue;
+            };
+            if chapter.meta.is_none() {
+                chapter.meta = older_chapter.meta.clone();
+            }
+            if chapter.pages.is_none() {
+                chapter.pages = older_chapter.pages;
+            }
+        }
+    }
+
+    /// Load the `_info.json` dump.
+    ///
+    /// # Arguments
+    /// * `save_path` - The path of the dump.
+    pub fn load(save_path: &std::path::Path) -> Option<Self> {
+        let content = std::fs::read_to_string(save_path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
     /// Dump the info into `_info.json` format.
     ///
     /// # Arguments
@@ -95,6 +309,47 @@ impl MangaDetailDump {
     }
 }
 
+/// The pages dropped from a chapter by `tools dedupe`, saved in `_dropped_pages.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DroppedPagesDump {
+    /// The file name of the dropped pages
+    pub(crate) pages: Vec<String>,
+}
+
+impl DroppedPagesDump {
+    /// The file name of the dump inside the chapter folder.
+    pub const FILE_NAME: &str = "_dropped_pages.json";
+
+    /// Load the dropped pages of a chapter folder.
+    ///
+    /// # Arguments
+    /// * `chapter_dir` - The chapter folder.
+    pub fn load(chapter_dir: &std::path::Path) -> Self {
+        std::fs::read_to_string(chapter_dir.join(Self::FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Add the dropped pages into the existing dump of a chapter folder.
+    ///
+    /// # Arguments
+    /// * `chapter_dir` - The chapter folder.
+    /// * `pages` - The file name of the newly dropped pages.
+    pub fn append(chapter_dir: &std::path::Path, pages: &[String]) -> std::io::Result<()> {
+        let mut dropped = Self::load(chapter_dir);
+        for page in pages {
+            if !dropped.pages.contains(page) {
+                dropped.pages.push(page.clone());
+            }
+        }
+        dropped.pages.sort();
+
+        let content = serde_json::to_string_pretty(&dropped)?;
+        std::fs::write(chapter_dir.join(Self::FILE_NAME), content)
+    }
+}
+
 impl From<&ChapterV2> for ChapterDetailDump {
     /// Convert from [`tosho_musq::proto::ChapterV2`] into [`ChapterDetailDump`]
     /// `_info.json` format.
@@ -127,6 +382,8 @@ impl From<&ChapterV2> for ChapterDetailDump {
             } else {
                 Some(value.subtitle().to_string())
             },
+            meta: None,
+            pages: None,
         }
     }
 }
@@ -142,6 +399,8 @@ impl From<&EpisodeNode> for ChapterDetailDump {
             id: (value.id() as u64).into(),
             timestamp: Some(start_time_ts),
             sub_name: None,
+            meta: None,
+            pages: None,
         }
     }
 }
@@ -155,6 +414,8 @@ impl From<&ComicEpisodeInfoNode> for ChapterDetailDump {
             id: value.id().into(),
             timestamp: Some(value.update_date() as i64),
             sub_name: None,
+            meta: None,
+            pages: None,
         }
     }
 }
@@ -176,6 +437,8 @@ impl From<&MangaChapterDetail> for ChapterDetailDump {
             id: (value.id() as u64).into(),
             timestamp: value.published_at().map(|d| d.timestamp()),
             sub_name: None,
+            meta: None,
+            pages: None,
         }
     }
 }
@@ -189,6 +452,8 @@ impl From<&Chapter> for ChapterDetailDump {
             main_name: value.formatted_title(),
             timestamp: value.published().map(|d| d.timestamp()),
             sub_name: None,
+            meta: None,
+            pages: None,
         }
     }
 }
@@ -206,6 +471,89 @@ impl From<&MPChapter> for ChapterDetailDump {
             } else {
                 Some(value.subtitle().to_string())
             },
+            meta: None,
+            pages: None,
+        }
+    }
+}
+
+impl From<&MPComment> for CommentDump {
+    /// Convert from [`tosho_mplus::proto::Comment`] into [`CommentDump`]
+    fn from(value: &MPComment) -> Self {
+        Self {
+            id: value.id(),
+            user_name: value.user_name().to_string(),
+            user_avatar: Some(value.user_avatar().to_string()).filter(|url| !url.is_empty()),
+            content: value.content().to_string(),
+            likes: value.likes(),
+            timestamp: value.timestamp(),
+        }
+    }
+}
+
+impl BannerDump {
+    /// Collect the banners of a [`tosho_mplus::proto::ChapterPageBanner`]
+    fn from_page_banner(value: &MPChapterPageBanner) -> Vec<Self> {
+        let title = Some(value.title().to_string()).filter(|title| !title.is_empty());
+
+        value
+            .banners()
+            .iter()
+            .map(|banner| Self {
+                title: title.clone(),
+                image: banner.image().to_string(),
+                link: banner
+                    .action()
+                    .map(|action| action.url().to_string())
+                    .filter(|url| !url.is_empty()),
+            })
+            .collect()
+    }
+}
+
+impl From<&MPChapterViewer> for ChapterMetaDump {
+    /// Convert from [`tosho_mplus::proto::ChapterViewer`] into [`ChapterMetaDump`]
+    ///
+    /// The metadata is collected from the last page, the banner pages and
+    /// the chapter list of the viewer.
+    fn from(value: &MPChapterViewer) -> Self {
+        let last_page = value.pages().iter().find_map(|page| page.last_page());
+
+        let position = value
+            .chapters()
+            .iter()
+            .position(|ch| ch.chapter_id() == value.chapter_id());
+        let previous_chapter = position
+            .and_then(|idx| idx.checked_sub(1))
+            .and_then(|idx| value.chapters().get(idx))
+            .map(|ch| ch.chapter_id().into());
+        let next_chapter = last_page
+            .and_then(|page| page.next_chapter())
+            .or_else(|| position.and_then(|idx| value.chapters().get(idx + 1)))
+            .map(|ch| ch.chapter_id().into());
+
+        Self {
+            previous_chapter,
+            next_chapter,
+            next_chapter_at: last_page
+                .map(|page| page.next_chapter_at())
+                .filter(|&at| at > 0),
+            comment_count: Some(value.comment_count()),
+            top_comments: last_page
+                .map(|page| page.top_comments().iter().map(CommentDump::from).collect())
+                .unwrap_or_default(),
+            page_banners: value
+                .pages()
+                .iter()
+                .filter_map(|page| page.banner())
+                .flat_map(BannerDump::from_page_banner)
+                .collect(),
+            insert_banners: value
+                .pages()
+                .iter()
+                .filter_map(|page| page.insert_banner())
+                .flat_map(BannerDump::from_page_banner)
+                .collect(),
         }
     }
 }
@@ -257,4 +605,128 @@ mod tests {
         assert_eq!(chapter.timestamp, Some(1620000000));
         assert_eq!(chapter.sub_name, Some("Sub Chapter".to_string()));
     }
+
+    #[test]
+    fn test_inherit_chapter_meta() {
+        let json = r#"{
+            "titleName": "Manga",
+            "authorName": "Author",
+            "chapters": [
+                {"id": 1, "mainName": "Chapter 1", "meta": {"nextChapter": 2, "nextChapterAt": 1620000000, "commentCount": 12}},
+                {"id": 2, "mainName": "Chapter 2"}
+            ]
+        }"#;
+
+        let older: super::MangaDetailDump = serde_json::from_str(json).unwrap();
+        let mut manga = super::MangaDetailDump::new(
+            "Manga".to_string(),
+            "Author".to_string(),
+            older
+                .chapters
+                .iter()
+                .map(|ch| super::ChapterDetailDump {
+                    meta: None,
+                    pages: None,
+                    ..ch.clone()
+                })
+                .collect(),
+        );
+        manga.inherit_chapter_meta(&older);
+
+        let meta = manga.chapters[0].meta.as_ref().unwrap();
+        assert_eq!(meta.next_chapter, Some(super::IdDump::Number(2)));
+        assert_eq!(meta.next_chapter_at, Some(1620000000));
+        assert_eq!(meta.comment_count, Some(12));
+        assert!(meta.top_comments.is_empty());
+        assert!(manga.chapters[1].meta.is_none());
+
+        assert!(
+            manga.set_chapter_meta(&super::IdDump::Number(2), super::ChapterMetaDump::default())
+        );
+        assert!(
+            !manga.set_chapter_meta(&super::IdDump::Number(3), super::ChapterMetaDump::default())
+        );
+    }
+
+    #[test]
+    fn test_chapter_meta_banners() {
+        use prost::Message;
+
+        fn make_banner(image: &str, url: &str) -> Vec<u8> {
+            let mut action = vec![];
+            prost::encoding::string::encode(2, &url.to_string(), &mut action);
+            let mut banner = vec![];
+            prost::encoding::string::encode(1, &image.to_string(), &mut banner);
+            prost::encoding::bytes::encode(2, &action, &mut banner);
+            banner
+        }
+
+        let mut page_banner = vec![];
+        prost::encoding::string::encode(1, &"Recommended".to_string(), &mut page_banner);
+        prost::encoding::bytes::encode(
+            2,
+            &make_banner("https://example.com/a.jpg", "https://example.com/a"),
+            &mut page_banner,
+        );
+        let mut insert_banner = vec![];
+        prost::encoding::bytes::encode(
+            2,
+            &make_banner("https://example.com/b.jpg", ""),
+            &mut insert_banner,
+        );
+
+        let mut banner_page = vec![];
+        prost::encoding::bytes::encode(2, &page_banner, &mut banner_page);
+        let mut insert_page = vec![];
+        prost::encoding::bytes::encode(5, &insert_banner, &mut insert_page);
+
+        let mut buf = vec![];
+        prost::encoding::bytes::encode(1, &banner_page, &mut buf);
+        prost::encoding::bytes::encode(1, &insert_page, &mut buf);
+        let viewer = super::MPChapterViewer::decode(buf.as_slice()).unwrap();
+
+        let meta = super::ChapterMetaDump::from(&viewer);
+        assert_eq!(meta.page_banners.len(), 1);
+        assert_eq!(meta.page_banners[0].title.as_deref(), Some("Recommended"));
+        assert_eq!(meta.page_banners[0].image, "https://example.com/a.jpg");
+        assert_eq!(
+            meta.page_banners[0].link.as_deref(),
+            Some("https://example.com/a")
+        );
+        assert_eq!(meta.insert_banners.len(), 1);
+        assert_eq!(meta.insert_banners[0].title, None);
+        assert_eq!(meta.insert_banners[0].image, "https://example.com/b.jpg");
+        assert_eq!(meta.insert_banners[0].link, None);
+    }
+
+    #[test]
+    fn test_deser_manga_without_source() {
+        let json = r#"{
+            "titleName": "Manga",
+            "authorName": "Author",
+            "chapters": []
+        }"#;
+
+        let manga: super::MangaDetailDump = serde_json::from_str(json).unwrap();
+
+        assert_eq!(manga.title_name, "Manga");
+        assert!(manga.source.is_none());
+        assert!(manga.title_id.is_none());
+    }
+
+    #[test]
+    fn test_deser_manga_with_source() {
+        let json = r#"{
+            "titleName": "Manga",
+            "authorName": "Author",
+            "chapters": [],
+            "source": "mplus",
+            "titleId": 100010
+        }"#;
+
+        let manga: super::MangaDetailDump = serde_json::from_str(json).unwrap();
+
+        assert_eq!(manga.source, Some(super::SourceDump::Mplus));
+        assert_eq!(manga.title_id, Some(super::IdDump::Number(100010)));
+    }
 }
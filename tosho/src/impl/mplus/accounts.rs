@@ -5,6 +5,7 @@ use tosho_mplus::MPClient;
 use crate::{
     cli::ExitCode,
     config::{get_all_config, save_config, try_remove_config},
+    r#impl::credentials::{CredentialArg, resolve_credential},
 };
 
 use super::config::{Config, DeviceType};
@@ -39,10 +40,22 @@ impl ValueEnum for DeviceKind {
 }
 
 pub(crate) async fn mplus_auth_session(
-    session_id: String,
+    session_id: CredentialArg,
     device_kind: DeviceKind,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
+    let Some(session_id) = resolve_credential(
+        session_id,
+        "TOSHO_MP_SESSION",
+        "Session ID",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
     let device_type = match device_kind {
         DeviceKind::Android => DeviceType::Android,
     };
@@ -58,7 +71,7 @@ pub(crate) async fn mplus_auth_session(
     let mut old_id: Option<String> = None;
     if let Some(old_config) = old_config {
         console.warn("Session ID already authenticated!");
-        let abort_it = console.confirm(Some("Do you want to replace it?"));
+        let abort_it = no_input || console.confirm(Some("Do you want to replace it?"));
         if !abort_it {
             console.info("Aborting...");
             return 0;
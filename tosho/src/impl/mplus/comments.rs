@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use color_print::cformat;
+use num_format::{Locale, ToFormattedString};
+use tosho_mplus::{APIResponse, MPClient, proto::Comment};
+
+use crate::{
+    cli::ExitCode,
+    r#impl::{common::unix_timestamp_to_string, models::CommentDump},
+};
+
+/// The file name of the saved comments in the chapter folder.
+pub(crate) const COMMENTS_FILE: &str = "_comments.json";
+
+async fn fetch_comments(client: &MPClient, chapter_id: u64) -> Result<Vec<Comment>, String> {
+    match client.get_comments(chapter_id).await {
+        Ok(APIResponse::Success(comments)) => Ok(comments.comments().to_vec()),
+        Ok(APIResponse::Error(e)) => Err(e.as_string()),
+        Err(e) => Err(format!("Unable to connect to M+: {e}")),
+    }
+}
+
+/// Save the comments of a chapter into the chapter folder.
+pub(super) async fn save_chapter_comments(
+    client: &MPClient,
+    chapter_id: u64,
+    chapter_dir: &Path,
+    console: &crate::term::Terminal,
+) {
+    let comments = match fetch_comments(client, chapter_id).await {
+        Ok(comments) => comments,
+        Err(err) => {
+            console.warn(format!("   Failed to get comments: {err}"));
+            return;
+        }
+    };
+
+    let dumped: Vec<CommentDump> = comments.iter().map(CommentDump::from).collect();
+    let result = serde_json::to_string_pretty(&dumped)
+        .map_err(|err| err.to_string())
+        .and_then(|content| {
+            std::fs::write(chapter_dir.join(COMMENTS_FILE), content).map_err(|err| err.to_string())
+        });
+
+    match result {
+        Ok(_) => {
+            if console.is_debug() {
+                console.log(cformat!("   Saved <s>{}</> comments", dumped.len()));
+            }
+        }
+        Err(err) => console.warn(format!("   Failed to save comments: {err}")),
+    }
+}
+
+pub(crate) async fn mplus_comments(
+    chapter_id: u64,
+    page: usize,
+    limit: usize,
+    client: &MPClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    console.info(cformat!(
+        "Fetching comments for chapter <magenta,bold>{}</>...",
+        chapter_id
+    ));
+
+    let comments = match fetch_comments(client, chapter_id).await {
+        Ok(comments) => comments,
+        Err(err) => {
+            console.error(format!("Failed to get comments: {err}"));
+            return 1;
+        }
+    };
+
+    if comments.is_empty() {
+        console.warn("No comments found");
+        return 0;
+    }
+
+    let total_pages = comments.len().div_ceil(limit);
+    if page > total_pages {
+        console.warn(cformat!(
+            "Page <s>{}</> is out of range, there is only <s>{}</> pages",
+            page,
+            total_pages
+        ));
+        return 1;
+    }
+
+    console.info(cformat!(
+        "Comments (<magenta,bold>{}</> comments, page <s>{}</>/<s>{}</>):",
+        comments.len().to_formatted_string(&Locale::en),
+        page,
+        total_pages
+    ));
+
+    for comment in comments.iter().skip((page - 1) * limit).take(limit) {
+        let posted_at = unix_timestamp_to_string(comment.timestamp()).unwrap_or_default();
+        let self_mark = if comment.is_self() {
+            cformat!(" <g,s>[You]</>")
+        } else {
+            String::new()
+        };
+        console.info(cformat!(
+            "  <s>{}</>{} ({}) <r!,s>♥ {}</>",
+            comment.user_name(),
+            self_mark,
+            posted_at,
+            comment.likes().to_formatted_string(&Locale::en)
+        ));
+        for line in comment.content().split('\n') {
+            console.info(format!("    {line}"));
+        }
+    }
+
+    if page < total_pages {
+        console.info(cformat!(
+            "Use <s>--page {}</> to see the next page",
+            page + 1
+        ));
+    }
+
+    0
+}
@@ -56,7 +56,7 @@ pub(super) fn do_print_search_information(
 }
 
 /// Search the big cache proto for specific title
-pub(super) fn search_manga_by_text(contents: &[Title], target: &str) -> Vec<Title> {
+pub(crate) fn search_manga_by_text(contents: &[Title], target: &str) -> Vec<Title> {
     // Remove diacritics and lower case the target string
     let clean_target = secular::lower_lay_string(target);
     // Split target by spaces and collect patterns
@@ -116,7 +116,7 @@ pub(super) struct TitleListCache {
     pub last_updated: i64,
 }
 
-pub(super) async fn get_cached_titles_data(
+pub(crate) async fn get_cached_titles_data(
     client: &MPClient,
 ) -> color_eyre::eyre::Result<Vec<TitleListV2>> {
     let term = get_console(0);
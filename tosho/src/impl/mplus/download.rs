@@ -1,16 +1,25 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use clap::ValueEnum;
 use color_print::cformat;
+use serde::{Deserialize, Serialize};
 use tosho_mplus::proto::{Chapter, ChapterPage, TitleDetail};
 use tosho_mplus::{APIResponse, ImageQuality, MPClient};
 
-use crate::r#impl::common::{check_chapter_folder_existence, check_downloaded_image_count};
+use crate::r#impl::common::{
+    INTERRUPTED_EXIT_CODE, check_chapter_folder_existence, check_downloaded_pages,
+    is_chapter_interrupted, is_shutdown_requested, record_chapter_pages, record_written_chapter,
+};
+use crate::r#impl::mplus::comments::{COMMENTS_FILE, save_chapter_comments};
+use crate::r#impl::mplus::plans::{can_read_chapter, report_locked_chapters};
+use crate::r#impl::provenance::{ChapterProvenance, PageSource, embed_chapter_metadata};
+use crate::r#impl::tools::dedupe::{PromoPages, drop_promo_pages};
 use crate::term::Terminal;
 use crate::{
     cli::ExitCode,
-    r#impl::models::{ChapterDetailDump, MangaDetailDump},
+    r#impl::models::{ChapterDetailDump, ChapterMetaDump, MangaDetailDump, SourceDump},
     term::ConsoleChoice,
 };
 
@@ -88,6 +97,20 @@ pub(crate) struct MPDownloadCliConfig {
 
     /// Auto download ignore any images checking but just check for folder existence
     pub(crate) only_check_folder: bool,
+    /// Drop the known promotional pages from `tools dedupe` after downloading
+    pub(crate) drop_promo: bool,
+    /// Embed the source metadata into the downloaded pages
+    pub(crate) embed_metadata: bool,
+    /// Save the chapter comments next to the downloaded pages
+    pub(crate) save_comments: bool,
+    /// Download the title in these languages side by side.
+    ///
+    /// Chapters are mapped across the language editions by the chapter number.
+    pub(crate) languages: Vec<tosho_mplus::proto::Language>,
+    /// Override the title directory, e.g. to re-download a language edition into its folder.
+    ///
+    /// Not used when downloading multiple languages.
+    pub(crate) title_dir: Option<PathBuf>,
 }
 
 fn create_chapters_info(title: &TitleDetail) -> MangaDetailDump {
@@ -104,6 +127,7 @@ fn create_chapters_info(title: &TitleDetail) -> MangaDetailDump {
         act_title.author().to_string(),
         dumped_chapters,
     )
+    .with_language(act_title.language().as_language_code())
 }
 
 fn get_output_directory(
@@ -207,6 +231,11 @@ async fn mplus_actual_downloader(
     console: Terminal,
     progress: Arc<indicatif::ProgressBar>,
 ) -> color_eyre::eyre::Result<()> {
+    // stop before the next page when shutting down
+    if is_shutdown_requested() {
+        return Ok(());
+    }
+
     let image_fn = format!("p{:03}.{}", node.idx, node.extension);
     let img_dl_path = image_dir.join(&image_fn);
 
@@ -238,6 +267,410 @@ async fn mplus_actual_downloader(
     Ok(())
 }
 
+/// The file name of the chapter mapping across the language editions.
+const LANGUAGES_FILE: &str = "_languages.json";
+
+/// A chapter mapped across the language editions by the chapter number.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct EditionChapterDump {
+    /// The chapter number, e.g. `#001`
+    number: String,
+    /// The chapter ID for each language code
+    chapters: BTreeMap<String, u64>,
+}
+
+/// Normalize the chapter number so it can be matched across language editions.
+///
+/// `#001` and `#1` would be matched, while non-numbered chapters (e.g. `Ex`)
+/// are matched case-insensitively.
+fn chapter_number_key(number: &str) -> String {
+    let number = number.trim().trim_start_matches('#').trim();
+    match number.parse::<f64>() {
+        Ok(parsed) => parsed.to_string(),
+        Err(_) => number.to_lowercase(),
+    }
+}
+
+/// Merge the chapter mapping of the current run into the previously saved mapping.
+fn merge_language_mapping(
+    mut existing: Vec<EditionChapterDump>,
+    current: Vec<EditionChapterDump>,
+) -> Vec<EditionChapterDump> {
+    for entry in current {
+        let key = chapter_number_key(&entry.number);
+        match existing
+            .iter_mut()
+            .find(|old| chapter_number_key(&old.number) == key)
+        {
+            Some(old) => old.chapters.extend(entry.chapters),
+            None => existing.push(entry),
+        }
+    }
+    existing
+}
+
+/// Download the selected chapters from each of the requested language editions.
+///
+/// The chapters are mapped by the chapter number and saved side by side
+/// in `MP_{title_id}/{language}/{chapter_id}`.
+async fn mplus_download_editions(
+    results: &TitleDetail,
+    download_chapters: Vec<&Chapter>,
+    dl_config: &MPDownloadCliConfig,
+    output_dir: &Path,
+    client: &MPClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let title = results.title().unwrap();
+    let title_dir = get_output_directory(output_dir, title.id(), None, true);
+
+    let mut mapping: Vec<EditionChapterDump> = download_chapters
+        .iter()
+        .map(|ch| EditionChapterDump {
+            number: ch.title().to_string(),
+            chapters: BTreeMap::new(),
+        })
+        .collect();
+
+    let mut exit_code = 0;
+    for language in dl_config.languages.iter() {
+        let edition_id = if title.language() == *language {
+            Some(title.id())
+        } else {
+            results
+                .other_languages()
+                .iter()
+                .find(|x| x.language() == *language)
+                .map(|x| x.id())
+        };
+
+        let Some(edition_id) = edition_id else {
+            console.warn(cformat!(
+                "<s>{}</> edition is not available, skipping",
+                language.pretty_name()
+            ));
+            continue;
+        };
+
+        console.info(cformat!(
+            "Fetching <s>{}</> edition <magenta,bold>{}</>...",
+            language.pretty_name(),
+            edition_id
+        ));
+
+        let edition_client = client.with_language(*language);
+        let edition = match edition_client.get_title_details(edition_id).await {
+            Ok(APIResponse::Success(edition)) => edition,
+            Ok(APIResponse::Error(e)) => {
+                console.error(format!("Failed to get title info: {}", e.as_string()));
+                exit_code = 1;
+                continue;
+            }
+            Err(e) => {
+                console.error(format!("Unable to connect to M+: {e}"));
+                exit_code = 1;
+                continue;
+            }
+        };
+
+        let edition_chapters = edition.flat_chapters_group();
+        let mut mapped_chapters: Vec<&Chapter> = vec![];
+        for entry in mapping.iter_mut() {
+            let key = chapter_number_key(&entry.number);
+            if let Some(chapter) = edition_chapters
+                .iter()
+                .find(|ch| chapter_number_key(ch.title()) == key)
+            {
+                entry.chapters.insert(
+                    language.as_language_code().to_string(),
+                    chapter.chapter_id(),
+                );
+                mapped_chapters.push(chapter);
+            }
+        }
+
+        report_locked_chapters(&edition, &mapped_chapters, console);
+        mapped_chapters.retain(|&ch| can_read_chapter(&edition, ch));
+
+        if mapped_chapters.len() < download_chapters.len() {
+            console.warn(cformat!(
+                "Only <s>{}</> of <s>{}</> chapters are available in <s>{}</>",
+                mapped_chapters.len(),
+                download_chapters.len(),
+                language.pretty_name()
+            ));
+        }
+        if mapped_chapters.is_empty() {
+            continue;
+        }
+
+        let edition_dir = title_dir.join(language.as_language_code());
+        std::fs::create_dir_all(&edition_dir).unwrap();
+        let code = mplus_download_chapters(
+            edition_id,
+            &edition,
+            mapped_chapters,
+            edition_dir,
+            dl_config,
+            &edition_client,
+            console,
+        )
+        .await;
+        if code != 0 {
+            exit_code = code;
+        }
+    }
+
+    let languages_path = title_dir.join(LANGUAGES_FILE);
+    let existing: Vec<EditionChapterDump> = std::fs::read_to_string(&languages_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let mapping = merge_language_mapping(existing, mapping);
+
+    match serde_json::to_string_pretty(&mapping) {
+        Ok(content) => {
+            if let Err(err) = std::fs::write(&languages_path, content) {
+                console.warn(format!("Failed to save the language mapping: {err}"));
+            }
+        }
+        Err(err) => console.warn(format!("Failed to save the language mapping: {err}")),
+    }
+
+    exit_code
+}
+
+/// Download the chapters of a title into the title directory.
+async fn mplus_download_chapters(
+    title_id: u64,
+    results: &TitleDetail,
+    download_chapters: Vec<&Chapter>,
+    title_dir: PathBuf,
+    dl_config: &MPDownloadCliConfig,
+    client: &MPClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let mut dump_info = create_chapters_info(results).with_source(SourceDump::Mplus, title_id);
+
+    let title_dump_path = title_dir.join("_info.json");
+    // keep the chapter information from the previous download
+    if let Some(older_info) = MangaDetailDump::load(&title_dump_path) {
+        dump_info.inherit_chapter_meta(&older_info);
+    }
+    dump_info
+        .dump(&title_dump_path)
+        .expect("Failed to dump title info");
+
+    let promo_pages = if dl_config.drop_promo {
+        let promo_pages = PromoPages::load(&title_dir);
+        if promo_pages.is_none() {
+            console.warn(
+                "No known promotional pages found, run `tools dedupe` on the title folder first",
+            );
+        }
+        promo_pages
+    } else {
+        None
+    };
+
+    for chapter in download_chapters {
+        if is_shutdown_requested() {
+            console.warn("Shutdown requested, stopping the download");
+            return INTERRUPTED_EXIT_CODE;
+        }
+        console.info(cformat!(
+            "  Downloading chapter <m,s>{}</> ({})...",
+            chapter.as_chapter_title(),
+            chapter.chapter_id()
+        ));
+
+        let view_req = client
+            .get_chapter_viewer(chapter, results, dl_config.quality.clone().into(), true)
+            .await;
+
+        if let Err(e) = view_req {
+            console.error(format!("Failed to get viewer info: {e}"));
+            return 1;
+        }
+
+        let viewer = view_req.unwrap();
+
+        if let APIResponse::Error(e) = viewer {
+            console.error(format!("Failed to get viewer info: {}", e.as_string()));
+            return 1;
+        }
+
+        let viewer = viewer.unwrap();
+
+        // Save the last page metadata (next chapter, comments, etc.)
+        if dump_info.set_chapter_meta(&chapter.chapter_id().into(), ChapterMetaDump::from(&viewer))
+            && let Err(err) = dump_info.dump(&title_dump_path)
+        {
+            console.warn(format!("   Failed to save chapter metadata: {err}"));
+        }
+
+        // Get viewer token
+        let view_token = viewer.opt_viewer_token().map(|x| x.to_string());
+        let view_token = if let Some(token) = view_token {
+            token
+        } else {
+            console.error("Failed to get viewer token");
+            return 1;
+        };
+
+        let chapter_images: Vec<tosho_mplus::proto::ChapterPage> = viewer
+            .pages()
+            .iter()
+            .filter_map(|page| page.page().cloned())
+            .collect();
+
+        let image_dir = title_dir.join(chapter.chapter_id().to_string());
+
+        record_chapter_pages(
+            &mut dump_info,
+            &title_dump_path,
+            chapter.chapter_id(),
+            chapter_images.len(),
+            console,
+        );
+
+        let page_stems: Vec<String> = (0..chapter_images.len())
+            .map(|idx| format!("p{idx:03}"))
+            .collect();
+        if dl_config.only_check_folder {
+            if check_chapter_folder_existence(&image_dir) {
+                console.info(cformat!(
+                    "   Chapter <m,s>{}</> (<s>{}</>) folder exists, skipping",
+                    chapter.as_chapter_title(),
+                    chapter.chapter_id()
+                ));
+                continue;
+            }
+        } else if check_downloaded_pages(&image_dir, &page_stems) {
+            console.warn(cformat!(
+                "   Chapter <m,s>{}</> (<s>{}</>) has been downloaded, skipping",
+                chapter.as_chapter_title(),
+                chapter.chapter_id()
+            ));
+            // backfill the comments for older downloads
+            if dl_config.save_comments && !image_dir.join(COMMENTS_FILE).exists() {
+                save_chapter_comments(client, chapter.chapter_id(), &image_dir, console).await;
+            }
+            continue;
+        }
+
+        // create chapter dir
+        std::fs::create_dir_all(&image_dir).unwrap();
+
+        let progress = console.make_progress_arc(chapter_images.len() as u64, Some("Downloading"));
+
+        if dl_config.parallel {
+            let semaphore = Arc::new(tokio::sync::Semaphore::new(dl_config.threads));
+
+            let tasks: Vec<_> = chapter_images
+                .iter()
+                .enumerate()
+                .map(|(idx, image)| {
+                    // wrap function for async block
+                    let wrap_client = client.clone();
+                    let image_dir = image_dir.clone();
+                    let cnsl = console.clone();
+                    let image = image.clone();
+                    let view_tok = view_token.clone();
+                    let progress = Arc::clone(&progress);
+                    let semaphore = Arc::clone(&semaphore);
+
+                    tokio::spawn(async move {
+                        let _permit = semaphore.acquire().await.unwrap();
+
+                        match mplus_actual_downloader(
+                            MPDownloadNode {
+                                client: wrap_client,
+                                image,
+                                idx,
+                                view_token: view_tok,
+                                extension: "webp".to_string(),
+                            },
+                            image_dir,
+                            cnsl.clone(),
+                            progress,
+                        )
+                        .await
+                        {
+                            Ok(_) => {}
+                            Err(e) => {
+                                cnsl.error(format!("    Failed to download image: {e}"));
+                            }
+                        }
+                    })
+                })
+                .collect();
+
+            futures_util::future::join_all(tasks).await;
+        } else {
+            for (idx, image) in chapter_images.iter().enumerate() {
+                match mplus_actual_downloader(
+                    MPDownloadNode {
+                        client: client.clone(),
+                        image: image.clone(),
+                        idx,
+                        extension: "webp".to_string(),
+                        view_token: view_token.clone(),
+                    },
+                    image_dir.clone(),
+                    console.clone(),
+                    Arc::clone(&progress),
+                )
+                .await
+                {
+                    Ok(_) => {}
+                    Err(e) => {
+                        console.error(format!("    Failed to download image: {e}"));
+                    }
+                }
+            }
+        }
+
+        progress.finish_with_message("Downloaded");
+
+        if is_chapter_interrupted(&image_dir, &page_stems) {
+            console.warn(cformat!(
+                "   Shutdown requested, chapter <m,s>{}</> (<s>{}</>) is left incomplete",
+                chapter.as_chapter_title(),
+                chapter.chapter_id()
+            ));
+            return INTERRUPTED_EXIT_CODE;
+        }
+        record_written_chapter(&image_dir);
+
+        if let Some(promo_pages) = &promo_pages {
+            drop_promo_pages(promo_pages, &title_dir, &image_dir, console).await;
+        }
+
+        if dl_config.embed_metadata {
+            let provenance = ChapterProvenance {
+                source: SourceDump::Mplus,
+                title: dump_info.title_name.clone(),
+                title_id: title_id.into(),
+                chapter: chapter.as_chapter_title(),
+                chapter_id: chapter.chapter_id().into(),
+            };
+            let page_sources: Vec<PageSource> = chapter_images
+                .iter()
+                .enumerate()
+                .map(|(idx, image)| PageSource::new(format!("p{idx:03}"), Some(image.url())))
+                .collect();
+            embed_chapter_metadata(&image_dir, &provenance, page_sources, console).await;
+        }
+
+        if dl_config.save_comments {
+            save_chapter_comments(client, chapter.chapter_id(), &image_dir, console).await;
+        }
+    }
+
+    0
+}
+
 pub(crate) async fn mplus_download(
     title_id: u64,
     dl_config: MPDownloadCliConfig,
@@ -267,41 +700,40 @@ pub(crate) async fn mplus_download(
                 do_chapter_select(&results, dl_config.show_all, console)
             };
 
-            let title_labels_subs = results
-                .title_labels()
-                .map(|x| x.plan_type())
-                .unwrap_or(tosho_mplus::helper::SubscriptionPlan::Basic);
-            let user_subs = results
-                .user_subscription()
-                .map(|x| x.plan())
-                .unwrap_or(tosho_mplus::helper::SubscriptionPlan::Basic);
-
             let mut download_chapters: Vec<&Chapter> = select_chapters
                 .iter()
                 .filter(|&ch| {
+                    // allow if chapter_ids is empty or chapter id is in chapter_ids
+                    let selected = dl_config.chapter_ids.is_empty()
+                        || dl_config.chapter_ids.contains(&(ch.chapter_id() as usize));
+
                     if dl_config.no_input {
                         // check if chapter id is in range
-                        match (dl_config.start_from, dl_config.end_at) {
-                            (Some(start), Some(end)) => {
-                                // between start and end
-                                ch.chapter_id() >= start && ch.chapter_id() <= end
-                            }
-                            (Some(start), None) => {
-                                ch.chapter_id() >= start // start to end
-                            }
-                            (None, Some(end)) => {
-                                ch.chapter_id() <= end // 0 to end
+                        selected
+                            && match (dl_config.start_from, dl_config.end_at) {
+                                (Some(start), Some(end)) => {
+                                    // between start and end
+                                    ch.chapter_id() >= start && ch.chapter_id() <= end
+                                }
+                                (Some(start), None) => {
+                                    ch.chapter_id() >= start // start to end
+                                }
+                                (None, Some(end)) => {
+                                    ch.chapter_id() <= end // 0 to end
+                                }
+                                _ => true,
                             }
-                            _ => true,
-                        }
                     } else {
-                        dl_config.chapter_ids.is_empty()
-                            || dl_config.chapter_ids.contains(&(ch.chapter_id() as usize))
+                        selected
                     }
                 })
-                .filter(|&ch| ch.is_free() || ch.is_ticketed() || user_subs >= title_labels_subs)
                 .collect();
 
+            if dl_config.no_input || dl_config.show_all {
+                report_locked_chapters(&results, &download_chapters, console);
+            }
+            download_chapters.retain(|&ch| can_read_chapter(&results, ch));
+
             if download_chapters.is_empty() {
                 console.warn("No chapters after filtered by selected chapter ids");
                 return 1;
@@ -309,154 +741,35 @@ pub(crate) async fn mplus_download(
 
             download_chapters.sort_by_key(|&a| a.published_at());
 
-            let title_dir = get_output_directory(&output_dir, title_id, None, true);
-            let dump_info = create_chapters_info(&results);
-
-            let title_dump_path = title_dir.join("_info.json");
-            dump_info
-                .dump(&title_dump_path)
-                .expect("Failed to dump title info");
-
-            for chapter in download_chapters {
-                console.info(cformat!(
-                    "  Downloading chapter <m,s>{}</> ({})...",
-                    chapter.as_chapter_title(),
-                    chapter.chapter_id()
-                ));
-
-                let view_req = client
-                    .get_chapter_viewer(chapter, &results, dl_config.quality.clone().into(), true)
-                    .await;
-
-                if let Err(e) = view_req {
-                    console.error(format!("Failed to get viewer info: {e}"));
-                    return 1;
-                }
-
-                let viewer = view_req.unwrap();
-
-                if let APIResponse::Error(e) = viewer {
-                    console.error(format!("Failed to get viewer info: {}", e.as_string()));
-                    return 1;
-                }
-
-                let viewer = viewer.unwrap();
-
-                // Get viewer token
-                let view_token = viewer.opt_viewer_token().map(|x| x.to_string());
-                let view_token = if let Some(token) = view_token {
-                    token
-                } else {
-                    console.error("Failed to get viewer token");
-                    return 1;
-                };
-
-                let chapter_images: Vec<tosho_mplus::proto::ChapterPage> = viewer
-                    .pages()
-                    .iter()
-                    .filter_map(|page| page.page().cloned())
-                    .collect();
-
-                let image_dir =
-                    get_output_directory(&output_dir, title_id, Some(chapter.chapter_id()), false);
-
-                if dl_config.only_check_folder {
-                    if check_chapter_folder_existence(&image_dir) {
-                        console.info(cformat!(
-                            "   Chapter <m,s>{}</> (<s>{}</>) folder exists, skipping",
-                            chapter.as_chapter_title(),
-                            chapter.chapter_id()
-                        ));
-                        continue;
-                    }
-                } else if let Some(count) = check_downloaded_image_count(&image_dir, "webp")
-                    && count >= chapter_images.len()
-                {
-                    console.warn(cformat!(
-                        "   Chapter <m,s>{}</> (<s>{}</>) has been downloaded, skipping",
-                        chapter.as_chapter_title(),
-                        chapter.chapter_id()
-                    ));
-                    continue;
-                }
-
-                // create chapter dir
-                std::fs::create_dir_all(&image_dir).unwrap();
-
-                let progress =
-                    console.make_progress_arc(chapter_images.len() as u64, Some("Downloading"));
-
-                if dl_config.parallel {
-                    let semaphore = Arc::new(tokio::sync::Semaphore::new(dl_config.threads));
-
-                    let tasks: Vec<_> = chapter_images
-                        .iter()
-                        .enumerate()
-                        .map(|(idx, image)| {
-                            // wrap function for async block
-                            let wrap_client = client.clone();
-                            let image_dir = image_dir.clone();
-                            let cnsl = console.clone();
-                            let image = image.clone();
-                            let view_tok = view_token.clone();
-                            let progress = Arc::clone(&progress);
-                            let semaphore = Arc::clone(&semaphore);
-
-                            tokio::spawn(async move {
-                                let _permit = semaphore.acquire().await.unwrap();
-
-                                match mplus_actual_downloader(
-                                    MPDownloadNode {
-                                        client: wrap_client,
-                                        image,
-                                        idx,
-                                        view_token: view_tok,
-                                        extension: "webp".to_string(),
-                                    },
-                                    image_dir,
-                                    cnsl.clone(),
-                                    progress,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        cnsl.error(format!("    Failed to download image: {e}"));
-                                    }
-                                }
-                            })
-                        })
-                        .collect();
-
-                    futures_util::future::join_all(tasks).await;
-                } else {
-                    for (idx, image) in chapter_images.iter().enumerate() {
-                        match mplus_actual_downloader(
-                            MPDownloadNode {
-                                client: client.clone(),
-                                image: image.clone(),
-                                idx,
-                                extension: "webp".to_string(),
-                                view_token: view_token.clone(),
-                            },
-                            image_dir.clone(),
-                            console.clone(),
-                            Arc::clone(&progress),
-                        )
-                        .await
-                        {
-                            Ok(_) => {}
-                            Err(e) => {
-                                console.error(format!("    Failed to download image: {e}"));
-                            }
-                        }
+            if dl_config.languages.is_empty() {
+                let title_dir = match &dl_config.title_dir {
+                    Some(title_dir) => {
+                        std::fs::create_dir_all(title_dir).unwrap();
+                        title_dir.clone()
                     }
-                }
-
-                progress.finish_with_message("Downloaded");
+                    None => get_output_directory(&output_dir, title_id, None, true),
+                };
+                mplus_download_chapters(
+                    title_id,
+                    &results,
+                    download_chapters,
+                    title_dir,
+                    &dl_config,
+                    client,
+                    console,
+                )
+                .await
+            } else {
+                mplus_download_editions(
+                    &results,
+                    download_chapters,
+                    &dl_config,
+                    &output_dir,
+                    client,
+                    console,
+                )
+                .await
             }
-
-            0
         }
         Ok(tosho_mplus::APIResponse::Error(e)) => {
             console.error(format!("Failed to get title info: {}", e.as_string()));
@@ -468,3 +781,37 @@ pub(crate) async fn mplus_download(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chapter_number_key() {
+        assert_eq!(chapter_number_key("#001"), chapter_number_key("#1"));
+        assert_eq!(chapter_number_key("#010.5"), "10.5");
+        assert_ne!(chapter_number_key("#010"), chapter_number_key("#001"));
+        assert_eq!(chapter_number_key(" Ex "), chapter_number_key("ex"));
+    }
+
+    #[test]
+    fn test_merge_language_mapping() {
+        let entry = |number: &str, chapters: &[(&str, u64)]| EditionChapterDump {
+            number: number.to_string(),
+            chapters: chapters
+                .iter()
+                .map(|(lang, id)| (lang.to_string(), *id))
+                .collect(),
+        };
+
+        let existing = vec![entry("#001", &[("en", 1)]), entry("#002", &[("en", 2)])];
+        let current = vec![entry("#1", &[("id", 11)]), entry("#003", &[("en", 3)])];
+        let merged = merge_language_mapping(existing, current);
+
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].chapters.get("en"), Some(&1));
+        assert_eq!(merged[0].chapters.get("id"), Some(&11));
+        assert_eq!(merged[1].chapters.len(), 1);
+        assert_eq!(merged[2].number, "#003");
+    }
+}
@@ -5,11 +5,13 @@ use clap::{Subcommand, ValueEnum};
 use super::parser::{CommaSeparatedNumber, parse_comma_number};
 
 pub(crate) mod accounts;
+pub(crate) mod comments;
 pub(super) mod common;
 pub(crate) mod config;
 pub(crate) mod download;
 pub(crate) mod favorites;
 pub(crate) mod manga;
+pub(crate) mod plans;
 pub(crate) mod rankings;
 
 #[derive(Subcommand, Clone)]
@@ -17,10 +19,21 @@ pub(crate) enum MPlusCommands {
     /// Authenticate tosho with your M+ account
     Auth {
         /// Session ID
-        session_id: String,
+        ///
+        /// Uses `TOSHO_MP_SESSION` (or `TOSHO_MP_SESSION_FILE`) when omitted.
+        session_id: Option<String>,
+        /// Read the session ID from a file instead
+        #[arg(long = "session-id-file", conflicts_with_all = ["session_id", "session_id_stdin"])]
+        session_id_file: Option<PathBuf>,
+        /// Read the session ID from the first line of stdin instead
+        #[arg(long = "session-id-stdin", conflicts_with = "session_id")]
+        session_id_stdin: bool,
         /// Device kind/type to use
         #[arg(short, long, value_enum, default_value = "android")]
         r#type: crate::r#impl::mplus::accounts::DeviceKind,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Get an account information
     Account,
@@ -43,10 +56,33 @@ pub(crate) enum MPlusCommands {
         /// Specify the image quality to download
         #[arg(short = 'q', long = "quality", default_value = "high", value_enum)]
         quality: crate::r#impl::mplus::download::DownloadImageQuality,
+        /// Drop the known promotional pages detected by `tools dedupe`
+        #[arg(long = "drop-promo")]
+        drop_promo: bool,
+        /// Save the chapter comments as JSON next to the downloaded pages
+        #[arg(long = "save-comments")]
+        save_comments: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
+        /// Download the title in multiple languages side by side (ex: en,es,id)
+        #[arg(short = 'L', long = "languages", value_enum, value_delimiter = ',')]
+        languages: Vec<MPlusLanguage>,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
     },
+    /// Get the comments of a chapter
+    Comments {
+        /// Chapter ID to use
+        chapter_id: u64,
+        /// The page of the comments to show
+        #[arg(short = 'p', long = "page", default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+        page: u32,
+        /// Amount of comments to show per page
+        #[arg(short = 'l', long = "limit", default_value = "25", value_parser = clap::value_parser!(u32).range(1..))]
+        limit: u32,
+    },
     /// Download a chapters from a title
     Download {
         /// Title ID to use
@@ -60,12 +96,27 @@ pub(crate) enum MPlusCommands {
         /// Specify the image quality to download
         #[arg(short = 'q', long = "quality", default_value = "high", value_enum)]
         quality: crate::r#impl::mplus::download::DownloadImageQuality,
+        /// Drop the known promotional pages detected by `tools dedupe`
+        #[arg(long = "drop-promo")]
+        drop_promo: bool,
+        /// Save the chapter comments as JSON next to the downloaded pages
+        #[arg(long = "save-comments")]
+        save_comments: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
+        /// Download the title in multiple languages side by side (ex: en,es,id)
+        #[arg(short = 'L', long = "languages", value_enum, value_delimiter = ',')]
+        languages: Vec<MPlusLanguage>,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
     },
     /// Get your account favorites list
     Favorites,
+    /// Get the free titles list and what each subscription plan unlocks
+    #[command(name = "free-titles")]
+    FreeTitles,
     /// Get a title information
     Info {
         /// Title ID to use
@@ -160,6 +211,15 @@ impl ValueEnum for MPlusLanguage {
     }
 }
 
+/// Find the language by the code saved in `_info.json`, see [`tosho_mplus::proto::Language::as_language_code`].
+pub(crate) fn language_from_code(code: &str) -> Option<tosho_mplus::proto::Language> {
+    MPlusLanguage::value_variants()
+        .iter()
+        .cloned()
+        .map(tosho_mplus::proto::Language::from)
+        .find(|language| language.as_language_code() == code)
+}
+
 impl From<MPlusLanguage> for tosho_mplus::proto::Language {
     fn from(lang: MPlusLanguage) -> Self {
         match lang {
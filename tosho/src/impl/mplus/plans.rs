@@ -0,0 +1,228 @@
+use color_print::cformat;
+use tosho_mplus::{
+    APIResponse, MPClient,
+    helper::SubscriptionPlan,
+    proto::{Chapter, Title, TitleDetail},
+};
+
+use crate::{cli::ExitCode, r#impl::common::unix_timestamp_to_string};
+
+use super::common::do_print_search_information;
+
+/// The reason why a chapter can't be downloaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum LockReason {
+    /// The chapter need a higher subscription plan
+    Plan(SubscriptionPlan),
+    /// The chapter can be unlocked with a ticket
+    Ticket,
+}
+
+/// Get the user subscription plan and the minimum plan required by the title.
+fn title_plans(title: &TitleDetail) -> (SubscriptionPlan, SubscriptionPlan) {
+    let user_subs = title
+        .user_subscription()
+        .map(|x| x.plan())
+        .unwrap_or(SubscriptionPlan::Basic);
+    let title_labels_subs = title
+        .title_labels()
+        .map(|x| x.plan_type())
+        .unwrap_or(SubscriptionPlan::Basic);
+
+    (user_subs, title_labels_subs)
+}
+
+/// Check if the chapter can be read with the current user subscription
+pub(super) fn can_read_chapter(title: &TitleDetail, chapter: &Chapter) -> bool {
+    let (user_subs, title_labels_subs) = title_plans(title);
+    chapter.is_free() || chapter.is_ticketed() || user_subs >= title_labels_subs
+}
+
+/// Check why the chapter can't be read with the current user subscription.
+///
+/// Returns `None` if the chapter can be read.
+pub(super) fn chapter_lock_reason(title: &TitleDetail, chapter: &Chapter) -> Option<LockReason> {
+    if can_read_chapter(title, chapter) {
+        return None;
+    }
+
+    let ticket_chapter = title
+        .ticket_chapters()
+        .iter()
+        .any(|ch| ch.chapter_id() == chapter.chapter_id());
+    if ticket_chapter {
+        Some(LockReason::Ticket)
+    } else {
+        let (_, title_labels_subs) = title_plans(title);
+        Some(LockReason::Plan(title_labels_subs))
+    }
+}
+
+/// Report the chapters that can't be downloaded and what is needed to unlock them.
+pub(super) fn report_locked_chapters(
+    title: &TitleDetail,
+    chapters: &[&Chapter],
+    console: &crate::term::Terminal,
+) {
+    let (user_subs, _) = title_plans(title);
+    let mut plan_locked: Vec<(&Chapter, SubscriptionPlan)> = vec![];
+    let mut ticket_locked: Vec<&Chapter> = vec![];
+    for &chapter in chapters {
+        match chapter_lock_reason(title, chapter) {
+            Some(LockReason::Plan(plan)) => plan_locked.push((chapter, plan)),
+            Some(LockReason::Ticket) => ticket_locked.push(chapter),
+            None => {}
+        }
+    }
+
+    if let Some((_, required)) = plan_locked.first() {
+        console.warn(cformat!(
+            "<s>{}</> chapters need the <m,s>{}</> plan (current plan: <s>{}</>):",
+            plan_locked.len(),
+            required.to_name(),
+            user_subs.to_name()
+        ));
+        for (chapter, _) in plan_locked.iter() {
+            console.warn(cformat!(
+                "  - <s>{}</> ({})",
+                chapter.as_chapter_title(),
+                chapter.chapter_id()
+            ));
+        }
+    }
+
+    if !ticket_locked.is_empty() {
+        let tickets = title.user_tickets().unwrap_or_default();
+        console.warn(cformat!(
+            "<s>{}</> chapters can be unlocked with a ticket, you have <m,s>{}</> ticket(s):",
+            ticket_locked.len(),
+            tickets.ticket()
+        ));
+        for chapter in ticket_locked.iter() {
+            console.warn(cformat!(
+                "  - <s>{}</> ({})",
+                chapter.as_chapter_title(),
+                chapter.chapter_id()
+            ));
+        }
+        if tickets.ticket() > 0 {
+            console.warn("  Use the ticket from the app to unlock them");
+        } else if let Some(next_refresh) = unix_timestamp_to_string(tickets.next_refresh()) {
+            console.warn(cformat!("  Next ticket refresh at <s>{}</>", next_refresh));
+        }
+    }
+}
+
+pub(crate) async fn mplus_free_titles(
+    client: &MPClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    console.info("Getting free titles list for M+");
+
+    let results = match client.get_free_titles().await {
+        Ok(APIResponse::Success(results)) => results,
+        Ok(APIResponse::Error(e)) => {
+            console.error(format!("Failed to get free titles: {}", e.as_string()));
+            return 1;
+        }
+        Err(e) => {
+            console.error(format!("Unable to connect to M+: {e}"));
+            return 1;
+        }
+    };
+
+    let free_titles: Vec<Title> = results
+        .titles()
+        .iter()
+        .filter_map(|title| title.title().cloned())
+        .collect();
+
+    if free_titles.is_empty() {
+        console.warn("No free titles found");
+    } else {
+        console.info(cformat!(
+            "Free titles (<m,s>{}</> titles):",
+            free_titles.len()
+        ));
+        do_print_search_information(&free_titles, true, None);
+    }
+
+    // show what the other plans would unlock
+    match client.get_subscriptions().await {
+        Ok(APIResponse::Success(subs)) => {
+            let user_subs = subs
+                .subscription()
+                .map(|x| x.plan())
+                .unwrap_or(SubscriptionPlan::Basic);
+            console.info("");
+            console.info(cformat!("Current plan: <m,s>{}</>", user_subs.to_name()));
+            for plan_titles in subs.titles() {
+                let plan = plan_titles.plan();
+                let status = if user_subs >= plan {
+                    cformat!("<g,s>included</>")
+                } else {
+                    cformat!("<r,s>upgrade required</>")
+                };
+                console.info(cformat!(
+                    "  - <s>{}</>: <m,s>{}</> titles ({})",
+                    plan.to_name(),
+                    plan_titles.titles().len(),
+                    status
+                ));
+            }
+        }
+        Ok(APIResponse::Error(e)) => {
+            console.warn(format!("Failed to get subscriptions: {}", e.as_string()));
+        }
+        Err(e) => {
+            console.warn(format!("Unable to connect to M+: {e}"));
+        }
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+
+    fn make_chapter(chapter_id: u64) -> Chapter {
+        let mut buf = vec![];
+        prost::encoding::uint64::encode(2, &chapter_id, &mut buf);
+        Chapter::decode(buf.as_slice()).unwrap()
+    }
+
+    fn make_title(user_plan: &str, title_plan: &str, ticket_chapter: u64) -> TitleDetail {
+        let mut labels = vec![];
+        prost::encoding::string::encode(3, &title_plan.to_string(), &mut labels);
+        let mut subscription = vec![];
+        prost::encoding::string::encode(1, &user_plan.to_string(), &mut subscription);
+
+        let mut buf = vec![];
+        prost::encoding::message::encode(22, &make_chapter(ticket_chapter), &mut buf);
+        prost::encoding::bytes::encode(32, &labels, &mut buf);
+        prost::encoding::bytes::encode(33, &subscription, &mut buf);
+        TitleDetail::decode(buf.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_chapter_lock_reason() {
+        let title = make_title("standard", "deluxe", 2);
+
+        assert!(!can_read_chapter(&title, &make_chapter(1)));
+        assert_eq!(
+            chapter_lock_reason(&title, &make_chapter(1)),
+            Some(LockReason::Plan(SubscriptionPlan::Deluxe))
+        );
+        assert_eq!(
+            chapter_lock_reason(&title, &make_chapter(2)),
+            Some(LockReason::Ticket)
+        );
+
+        let title = make_title("deluxe", "deluxe", 2);
+        assert!(can_read_chapter(&title, &make_chapter(1)));
+        assert_eq!(chapter_lock_reason(&title, &make_chapter(1)), None);
+    }
+}
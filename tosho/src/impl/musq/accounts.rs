@@ -6,7 +6,10 @@ use tosho_musq::MUClient;
 use crate::{
     cli::ExitCode,
     config::{get_all_config, save_config, try_remove_config},
-    r#impl::common::unix_timestamp_to_string,
+    r#impl::{
+        common::unix_timestamp_to_string,
+        credentials::{CredentialArg, resolve_credential},
+    },
 };
 
 use super::config::{Config, DeviceType};
@@ -47,10 +50,22 @@ impl ValueEnum for DeviceKind {
 }
 
 pub(crate) async fn musq_auth_session(
-    session_id: String,
+    session_id: CredentialArg,
     device_kind: DeviceKind,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
+    let Some(session_id) = resolve_credential(
+        session_id,
+        "TOSHO_MU_SESSION",
+        "Session ID",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
     let r#type = match device_kind {
         DeviceKind::Android => DeviceType::Android,
         DeviceKind::Apple => DeviceType::Apple,
@@ -66,7 +81,7 @@ pub(crate) async fn musq_auth_session(
     let mut old_id: Option<String> = None;
     if let Some(old_config) = old_config {
         console.warn("Session ID already authenticated!");
-        let abort_it = console.confirm(Some("Do you want to replace it?"));
+        let abort_it = no_input || console.confirm(Some("Do you want to replace it?"));
         if !abort_it {
             console.info("Aborting...");
             return 0;
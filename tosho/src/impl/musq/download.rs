@@ -11,12 +11,19 @@ use tosho_musq::{
 use crate::{
     cli::ExitCode,
     r#impl::{
-        common::{check_chapter_folder_existence, check_downloaded_image_count},
-        models::{ChapterDetailDump, MangaDetailDump},
+        common::{
+            INTERRUPTED_EXIT_CODE, check_chapter_folder_existence, check_downloaded_pages,
+            is_chapter_interrupted, is_shutdown_requested, record_chapter_pages,
+            record_written_chapter,
+        },
+        models::{ChapterDetailDump, MangaDetailDump, SourceDump},
+        pool::{PoolPayment, PoolReceipts},
+        provenance::{ChapterProvenance, PageSource, embed_chapter_metadata},
+        tools::dedupe::{PromoPages, drop_promo_pages},
     },
 };
 
-use super::common::common_purchase_select;
+use super::{common::common_purchase_select, points::warn_expiring_points};
 
 #[derive(Debug, Clone, Default)]
 pub(crate) enum DownloadImageQuality {
@@ -84,6 +91,10 @@ pub(crate) struct MUDownloadCliConfig {
 
     /// Auto download ignore any images checking but just check for folder existence
     pub(crate) only_check_folder: bool,
+    /// Drop the known promotional pages from `tools dedupe` after downloading
+    pub(crate) drop_promo: bool,
+    /// Embed the source metadata into the downloaded pages
+    pub(crate) embed_metadata: bool,
 }
 
 fn create_chapters_info(manga_detail: MangaDetailV2) -> MangaDetailDump {
@@ -100,7 +111,7 @@ fn create_chapters_info(manga_detail: MangaDetailV2) -> MangaDetailDump {
     )
 }
 
-fn get_output_directory(
+pub(super) fn get_output_directory(
     output_dir: &Path,
     title_id: u64,
     chapter_id: Option<u64>,
@@ -126,6 +137,26 @@ pub(crate) async fn musq_download(
     output_dir: PathBuf,
     client: &MUClient,
     console: &mut crate::term::Terminal,
+) -> ExitCode {
+    musq_download_with_receipts(
+        title_id,
+        dl_config,
+        output_dir,
+        client,
+        &mut PoolReceipts::default(),
+        console,
+    )
+    .await
+}
+
+/// Same as [`musq_download`], but also report the chapters that are unlocked and saved.
+pub(crate) async fn musq_download_with_receipts(
+    title_id: u64,
+    dl_config: MUDownloadCliConfig,
+    output_dir: PathBuf,
+    client: &MUClient,
+    receipts: &mut PoolReceipts,
+    console: &mut crate::term::Terminal,
 ) -> ExitCode {
     let (results, manga_detail, user_bal) = common_purchase_select(
         title_id,
@@ -142,25 +173,28 @@ pub(crate) async fn musq_download(
             let results: Vec<&ChapterV2> = results
                 .iter()
                 .filter(|&ch| {
+                    // allow if chapter_ids is empty or chapter id is in chapter_ids
+                    let selected = dl_config.chapter_ids.is_empty()
+                        || dl_config.chapter_ids.contains(&(ch.id() as usize));
+
                     if dl_config.no_input {
                         // check if chapter id is in range
-                        match (dl_config.start_from, dl_config.end_at) {
-                            (Some(start), Some(end)) => {
-                                // between start and end
-                                ch.id() >= start && ch.id() <= end
-                            }
-                            (Some(start), None) => {
-                                ch.id() >= start // start to end
-                            }
-                            (None, Some(end)) => {
-                                ch.id() <= end // 0 to end
+                        selected
+                            && match (dl_config.start_from, dl_config.end_at) {
+                                (Some(start), Some(end)) => {
+                                    // between start and end
+                                    ch.id() >= start && ch.id() <= end
+                                }
+                                (Some(start), None) => {
+                                    ch.id() >= start // start to end
+                                }
+                                (None, Some(end)) => {
+                                    ch.id() <= end // 0 to end
+                                }
+                                _ => true,
                             }
-                            _ => true,
-                        }
                     } else {
-                        // allow if chapter_ids is empty or chapter id is in chapter_ids
-                        dl_config.chapter_ids.is_empty()
-                            || dl_config.chapter_ids.contains(&(ch.id() as usize))
+                        selected
                     }
                 })
                 .collect();
@@ -180,8 +214,10 @@ pub(crate) async fn musq_download(
 
             console.info(format!("Downloading {} chapters...", results.len()));
             let mut download_chapters = vec![];
+            let mut skipped_chapters: Vec<&ChapterV2> = vec![];
             for chapter in results {
                 if chapter.is_free() {
+                    receipts.unlocked(chapter.id(), chapter.title(), PoolPayment::Owned, "owned");
                     download_chapters.push(chapter);
                     continue;
                 }
@@ -214,6 +250,7 @@ pub(crate) async fn musq_download(
                         ));
                     }
 
+                    skipped_chapters.push(chapter);
                     continue;
                 }
 
@@ -228,6 +265,10 @@ pub(crate) async fn musq_download(
                     should_purchase = console.confirm(Some(&prompt));
                 }
 
+                if !should_purchase {
+                    skipped_chapters.push(chapter);
+                }
+
                 if should_purchase {
                     console.info(cformat!(
                         "  Purchasing chapter <m,s>{}</> (<s>{}</>) with consumption <s>{:?}</>...",
@@ -261,6 +302,22 @@ pub(crate) async fn musq_download(
                                     chapter.id()
                                 ));
                             } else {
+                                let payment = if consume.get_paid() == 0 {
+                                    PoolPayment::Free
+                                } else {
+                                    PoolPayment::Paid
+                                };
+                                receipts.unlocked(
+                                    chapter.id(),
+                                    chapter.title(),
+                                    payment,
+                                    format!(
+                                        "{}c free, {}c XP, {}c paid",
+                                        consume.get_free(),
+                                        consume.get_event(),
+                                        consume.get_paid()
+                                    ),
+                                );
                                 download_chapters.push(chapter);
                                 coin_purse.subtract_free(consume.get_free());
                                 coin_purse.subtract_event(consume.get_event());
@@ -271,6 +328,11 @@ pub(crate) async fn musq_download(
                 }
             }
 
+            if dl_config.no_input && !skipped_chapters.is_empty() {
+                let skipped_price = skipped_chapters.iter().map(|ch| ch.price()).sum();
+                warn_expiring_points(client, skipped_chapters.len(), skipped_price, console).await;
+            }
+
             if download_chapters.is_empty() {
                 console.warn("No chapters to be download after filtering, aborting");
                 return 1;
@@ -279,15 +341,36 @@ pub(crate) async fn musq_download(
             download_chapters.sort_by_key(|&a| a.id());
 
             let title_dir = get_output_directory(&output_dir, title_id, None, true);
-            let dump_info = create_chapters_info(manga_detail);
+            let mut dump_info =
+                create_chapters_info(manga_detail).with_source(SourceDump::Musq, title_id);
 
             let title_dump_path = title_dir.join("_info.json");
+            // keep the chapter information from the previous download
+            if let Some(older_info) = MangaDetailDump::load(&title_dump_path) {
+                dump_info.inherit_chapter_meta(&older_info);
+            }
             dump_info
                 .dump(&title_dump_path)
                 .expect("Failed to dump title info");
 
+            let promo_pages = if dl_config.drop_promo {
+                let promo_pages = PromoPages::load(&title_dir);
+                if promo_pages.is_none() {
+                    console.warn(
+                        "No known promotional pages found, run `tools dedupe` on the title folder first",
+                    );
+                }
+                promo_pages
+            } else {
+                None
+            };
+
             let mut stored_blocks: Vec<tosho_musq::proto::PageBlock> = vec![];
             for chapter in download_chapters {
+                if is_shutdown_requested() {
+                    console.warn("Shutdown requested, stopping the download");
+                    return INTERRUPTED_EXIT_CODE;
+                }
                 console.info(cformat!(
                     "  Downloading chapter <m,s>{}</> ({})...",
                     chapter.title(),
@@ -365,6 +448,18 @@ pub(crate) async fn musq_download(
 
                 let ch_dir = get_output_directory(&output_dir, title_id, Some(chapter.id()), false);
 
+                record_chapter_pages(
+                    &mut dump_info,
+                    &title_dump_path,
+                    chapter.id(),
+                    image_blocks.len(),
+                    console,
+                );
+
+                let page_stems: Vec<String> = image_blocks
+                    .iter()
+                    .map(|image| format!("p{:03}", image.file_stem().parse::<u64>().unwrap()))
+                    .collect();
                 if dl_config.only_check_folder {
                     if check_chapter_folder_existence(&ch_dir) {
                         console.info(cformat!(
@@ -372,16 +467,16 @@ pub(crate) async fn musq_download(
                             chapter.title(),
                             chapter.id()
                         ));
+                        receipts.downloaded(chapter.id());
                         continue;
                     }
-                } else if let Some(count) = check_downloaded_image_count(&ch_dir, "avif")
-                    && count >= image_blocks.len()
-                {
+                } else if check_downloaded_pages(&ch_dir, &page_stems) {
                     console.warn(cformat!(
                         "   Chapter <m,s>{}</> (<s>{}</>) has been downloaded, skipping",
                         chapter.title(),
                         chapter.id()
                     ));
+                    receipts.downloaded(chapter.id());
                     continue;
                 }
 
@@ -390,7 +485,12 @@ pub(crate) async fn musq_download(
 
                 // download images
                 let total_image_count = image_blocks.len() as u64;
-                for image in image_blocks {
+                for image in image_blocks.iter().copied() {
+                    // stop before the next page when shutting down
+                    if is_shutdown_requested() {
+                        break;
+                    }
+
                     let file_number: u64 = image.file_stem().parse().unwrap();
                     let img_fn = format!("p{:03}.{}", file_number, image.extension());
                     let img_dl_path = ch_dir.join(&img_fn);
@@ -446,6 +546,43 @@ pub(crate) async fn musq_download(
                     }
                 }
                 console.stop_progress(Some("Downloaded".to_string()));
+
+                if is_chapter_interrupted(&ch_dir, &page_stems) {
+                    console.warn(cformat!(
+                        "   Shutdown requested, chapter <m,s>{}</> (<s>{}</>) is left incomplete",
+                        chapter.title(),
+                        chapter.id()
+                    ));
+                    return INTERRUPTED_EXIT_CODE;
+                }
+                record_written_chapter(&ch_dir);
+
+                if let Some(promo_pages) = &promo_pages {
+                    drop_promo_pages(promo_pages, &title_dir, &ch_dir, console).await;
+                }
+
+                if dl_config.embed_metadata {
+                    let page_sources: Vec<PageSource> = image_blocks
+                        .iter()
+                        .filter_map(|image| {
+                            let file_number = image.file_stem().parse::<u64>().ok()?;
+                            Some(PageSource::new(
+                                format!("p{file_number:03}"),
+                                Some(image.url()),
+                            ))
+                        })
+                        .collect();
+                    let provenance = ChapterProvenance {
+                        source: SourceDump::Musq,
+                        title: dump_info.title_name.clone(),
+                        title_id: title_id.into(),
+                        chapter: chapter.title().to_string(),
+                        chapter_id: chapter.id().into(),
+                    };
+                    embed_chapter_metadata(&ch_dir, &provenance, page_sources, console).await;
+                }
+
+                receipts.downloaded(chapter.id());
             }
 
             0
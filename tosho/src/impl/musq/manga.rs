@@ -1,14 +1,39 @@
+use std::collections::BTreeMap;
+
 use color_print::cformat;
 use tosho_musq::{
     MUClient, WeeklyCode,
     constants::BASE_HOST,
-    proto::{ConsumptionType, Tag},
+    proto::{ConsumptionType, MangaResults, Tag},
 };
 
-use crate::{cli::ExitCode, linkify};
+use crate::{cli::ExitCode, config::get_user_path, linkify};
 
 use super::common::do_print_search_information;
 
+fn print_search_results(results: &MangaResults, console: &crate::term::Terminal) -> ExitCode {
+    if results.titles().is_empty() {
+        console.warn("No results found");
+        return 1;
+    }
+
+    // Cut to first 25 results
+    let cutoff_results = if results.titles().len() > 25 {
+        &results.titles()[..25]
+    } else {
+        results.titles()
+    };
+
+    console.info(cformat!(
+        "Search results (<magenta,bold>{}</> results):",
+        cutoff_results.len()
+    ));
+
+    do_print_search_information(cutoff_results, false, None);
+
+    0
+}
+
 pub(crate) async fn musq_search(
     query: &str,
     client: &MUClient,
@@ -18,28 +43,32 @@ pub(crate) async fn musq_search(
 
     let results = client.search(query).await;
     match results {
-        Ok(results) => {
-            if results.titles().is_empty() {
-                console.warn("No results found");
-                return 1;
-            }
-
-            // Cut to first 25 results
-            let cutoff_results = if results.titles().len() > 25 {
-                &results.titles()[..25]
-            } else {
-                results.titles()
-            };
-
-            console.info(cformat!(
-                "Search results (<magenta,bold>{}</> results):",
-                cutoff_results.len()
-            ));
+        Ok(results) => print_search_results(&results, console),
+        Err(e) => {
+            console.error(cformat!("Unable to connect to MU!: {}", e));
+            1
+        }
+    }
+}
 
-            do_print_search_information(cutoff_results, false, None);
+pub(crate) async fn musq_search_tag(
+    tag_id: u64,
+    client: &MUClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let tag_name = load_cached_tags().remove(&tag_id);
+    match tag_name {
+        Some(name) => console.info(cformat!(
+            "Searching for tag <magenta,bold>{}</> ({})...",
+            name,
+            tag_id
+        )),
+        None => console.info(cformat!("Searching for tag <magenta,bold>{}</>...", tag_id)),
+    }
 
-            0
-        }
+    let results = client.search_by_tag(tag_id).await;
+    match results {
+        Ok(results) => print_search_results(&results, console),
         Err(e) => {
             console.error(cformat!("Unable to connect to MU!: {}", e));
             1
@@ -82,6 +111,84 @@ pub(crate) async fn musq_search_weekly(
     }
 }
 
+fn tags_cache_path() -> std::path::PathBuf {
+    get_user_path().join("musq_tags_cache.json")
+}
+
+/// Load the tags that has been discovered before, keyed by the tag ID.
+fn load_cached_tags() -> BTreeMap<u64, String> {
+    std::fs::read(tags_cache_path())
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Merge the tags into the discovered tags cache.
+fn cache_tags(tags: &[Tag]) -> BTreeMap<u64, String> {
+    let mut cached = load_cached_tags();
+    let before = cached.clone();
+    for tag in tags {
+        cached.insert(tag.id(), tag.name().to_string());
+    }
+
+    if cached != before
+        && let Ok(data) = serde_json::to_vec(&cached)
+    {
+        let _ = std::fs::write(tags_cache_path(), data);
+    }
+
+    cached
+}
+
+pub(crate) async fn musq_tags(
+    title_ids: Vec<u64>,
+    client: &MUClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let mut discovered: Vec<Tag> = vec![];
+
+    console.info("Fetching tags from home page...");
+    match client.get_my_home().await {
+        Ok(home) => discovered.extend(home.tags().iter().cloned()),
+        Err(e) => console.error(cformat!("Unable to connect to MU!: {}", e)),
+    }
+
+    for title_id in title_ids {
+        console.info(cformat!(
+            "Fetching tags from title <magenta,bold>{}</>...",
+            title_id
+        ));
+        match client.get_manga(title_id).await {
+            Ok(manga) => discovered.extend(manga.tags().iter().cloned()),
+            Err(e) => console.error(cformat!("Unable to connect to MU!: {}", e)),
+        }
+    }
+
+    let all_tags = cache_tags(&discovered);
+    if all_tags.is_empty() {
+        console.warn("No tags found");
+        return 1;
+    }
+
+    let mut sorted_tags: Vec<(&u64, &String)> = all_tags.iter().collect();
+    sorted_tags.sort_by_key(|(_, name)| name.to_lowercase());
+
+    console.info(cformat!(
+        "Discovered tags (<magenta,bold>{}</> tags):",
+        sorted_tags.len()
+    ));
+    for (tag_id, name) in sorted_tags {
+        let tag_url = format!("https://{}/genre/{}", BASE_HOST, tag_id);
+        let linked = linkify!(&tag_url, name);
+        console.info(cformat!("  {} ({})", linked, tag_id));
+    }
+    console.info(cformat!(
+        "Use <s>search --tag</> with the tag ID to search titles by the tag"
+    ));
+
+    0
+}
+
 fn format_tags(tags: &[Tag]) -> String {
     tags.iter()
         .map(|tag| {
@@ -122,6 +229,8 @@ pub(crate) async fn musq_title_info(
                 linked,
             ));
 
+            cache_tags(result.tags());
+
             console.info(cformat!("  <s>Author</>: {}", result.authors()));
             console.info(cformat!(
                 "  <s>Genre/Tags</>: {}",
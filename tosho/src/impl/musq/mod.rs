@@ -2,7 +2,10 @@ use std::path::PathBuf;
 
 use clap::Subcommand;
 
-use super::parser::{CommaSeparatedNumber, WeeklyCodeCli, parse_comma_number};
+use super::parser::{
+    CommaSeparatedNumber, CommaSeparatedString, WeeklyCodeCli, parse_comma_number,
+    parse_comma_string,
+};
 
 pub(crate) mod accounts;
 pub(super) mod common;
@@ -10,6 +13,8 @@ pub(crate) mod config;
 pub(crate) mod download;
 pub(crate) mod favorites;
 pub(crate) mod manga;
+pub(crate) mod points;
+pub(crate) mod pool;
 pub(crate) mod purchases;
 pub(crate) mod rankings;
 
@@ -18,10 +23,21 @@ pub(crate) enum MUSQCommands {
     /// Authenticate tosho with your MU! account
     Auth {
         /// Session ID
-        session_id: String,
+        ///
+        /// Uses `TOSHO_MU_SESSION` (or `TOSHO_MU_SESSION_FILE`) when omitted.
+        session_id: Option<String>,
+        /// Read the session ID from a file instead
+        #[arg(long = "session-id-file", conflicts_with_all = ["session_id", "session_id_stdin"])]
+        session_id_file: Option<PathBuf>,
+        /// Read the session ID from the first line of stdin instead
+        #[arg(long = "session-id-stdin", conflicts_with = "session_id")]
+        session_id_stdin: bool,
         /// Device kind/type to use
         #[arg(short, long, value_enum, default_value = "android")]
         r#type: crate::r#impl::musq::accounts::DeviceKind,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Get an account information
     Account,
@@ -53,9 +69,20 @@ pub(crate) enum MUSQCommands {
         /// Specify the image quality to download
         #[arg(short = 'q', long = "quality", default_value = "high", value_enum)]
         quality: crate::r#impl::musq::download::DownloadImageQuality,
+        /// Drop the known promotional pages detected by `tools dedupe`
+        #[arg(long = "drop-promo")]
+        drop_promo: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
+        /// Use the accounts in the named pool, see the `pool` command
+        ///
+        /// Each chapter is unlocked by the account that owns it or can pay with free currency first.
+        #[arg(long = "pool", default_value = None)]
+        pool: Option<String>,
     },
     /// Get your account point balance
     Balance,
@@ -75,6 +102,12 @@ pub(crate) enum MUSQCommands {
         /// Specify the image quality to download
         #[arg(short = 'q', long = "quality", default_value = "high", value_enum)]
         quality: crate::r#impl::musq::download::DownloadImageQuality,
+        /// Drop the known promotional pages detected by `tools dedupe`
+        #[arg(long = "drop-promo")]
+        drop_promo: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
@@ -83,6 +116,13 @@ pub(crate) enum MUSQCommands {
     Favorites,
     /// Get your account reading history
     History,
+    /// Get your coin acquisition history and the XP coin expiry forecast
+    #[command(name = "history-points")]
+    HistoryPoints {
+        /// The XP coin validity in days used to estimate the expiry date
+        #[arg(long = "validity", default_value_t = self::points::EVENT_POINT_VALIDITY_DAYS)]
+        validity: i64,
+    },
     /// Get a title information
     Info {
         /// Title ID to use
@@ -94,6 +134,19 @@ pub(crate) enum MUSQCommands {
         #[arg(short = 'r', long = "related")]
         show_related: bool,
     },
+    /// Manage the account pools used by `autodownload --pool`
+    ///
+    /// Show all pools when no name is given, or show the pool when no accounts is given.
+    Pool {
+        /// The pool name
+        name: Option<String>,
+        /// The account IDs in the pool, in order of preference (ex: id1,id2)
+        #[arg(short = 'a', long = "accounts", default_value = None, value_parser = parse_comma_string)]
+        account_ids: Option<CommaSeparatedString>,
+        /// Remove the pool
+        #[arg(short = 'r', long = "remove")]
+        remove: bool,
+    },
     /// Purchases chapters for a title
     Purchase {
         /// Title ID to use
@@ -111,7 +164,17 @@ pub(crate) enum MUSQCommands {
     /// Search for a title
     Search {
         /// Query to search for
-        query: String,
+        #[arg(required_unless_present = "tag")]
+        query: Option<String>,
+        /// Search by the tag ID instead, see `tags` command
+        #[arg(short = 't', long = "tag", conflicts_with = "query")]
+        tag: Option<u64>,
+    },
+    /// List the tags discovered from the home page and title information
+    Tags {
+        /// Also discover the tags from the title ID (ex: 1,2,3)
+        #[arg(short = 't', long = "titles", default_value = None, value_parser = parse_comma_number)]
+        title_ids: Option<CommaSeparatedNumber>,
     },
     /// Get weekly releases
     Weekly {
@@ -0,0 +1,277 @@
+use chrono::TimeZone;
+use color_print::cformat;
+use num_format::{Locale, ToFormattedString};
+use tosho_musq::{MUClient, proto::PointHistory};
+
+use crate::{cli::ExitCode, r#impl::common::unix_timestamp_to_string};
+
+use super::config::Config;
+
+/// The assumed validity of the event/XP coins in days.
+///
+/// The API does not expose the expiry date, so the forecast is estimated
+/// from the acquisition time using this validity period.
+pub(crate) const EVENT_POINT_VALIDITY_DAYS: i64 = 90;
+
+/// The amount of days before the expiry date to start warning in `autodownload`.
+const EXPIRY_WARNING_DAYS: i64 = 7;
+
+/// A batch of event/XP coins that would expire at the same time.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ExpiringPoint {
+    /// The amount of coins left from the acquisition
+    pub(super) amount: u64,
+    /// The unix timestamp of the acquisition
+    pub(super) acquired_at: i64,
+    /// The estimated unix timestamp of the expiry
+    pub(super) expires_at: i64,
+}
+
+/// Estimate when the current event/XP coins would expire.
+///
+/// Coins are assumed to be spent from the oldest acquisition first, so the current
+/// balance is assigned to the most recent acquisitions. The result is sorted by the
+/// nearest expiry first.
+pub(super) fn forecast_event_expiry(
+    current_event: u64,
+    logs: &[PointHistory],
+    validity_days: i64,
+) -> Vec<ExpiringPoint> {
+    let mut acquisitions: Vec<&PointHistory> =
+        logs.iter().filter(|log| log.event_point() > 0).collect();
+    acquisitions.sort_by_key(|log| std::cmp::Reverse(log.created_at()));
+
+    let validity = validity_days * 24 * 60 * 60;
+    let mut remaining = current_event;
+    let mut forecast = vec![];
+    for log in acquisitions {
+        if remaining == 0 {
+            break;
+        }
+
+        let amount = log.event_point().min(remaining);
+        remaining -= amount;
+        forecast.push(ExpiringPoint {
+            amount,
+            acquired_at: log.created_at() as i64,
+            expires_at: log.created_at() as i64 + validity,
+        });
+    }
+
+    forecast.reverse();
+    forecast
+}
+
+fn format_point_changes(log: &PointHistory) -> String {
+    let mut changes = vec![];
+    if log.free_point() > 0 {
+        changes.push(cformat!(
+            "<green,bold>{}</>c free",
+            log.free_point().to_formatted_string(&Locale::en)
+        ));
+    }
+    if log.event_point() > 0 {
+        changes.push(cformat!(
+            "<magenta,bold>{}</>c XP",
+            log.event_point().to_formatted_string(&Locale::en)
+        ));
+    }
+    if log.paid_point() > 0 {
+        changes.push(cformat!(
+            "<yellow!,bold>{}</>c paid",
+            log.paid_point().to_formatted_string(&Locale::en)
+        ));
+    }
+
+    if changes.is_empty() {
+        "0c".to_string()
+    } else {
+        changes.join(", ")
+    }
+}
+
+fn format_expiry(expiring: &ExpiringPoint) -> String {
+    let expires_at = unix_timestamp_to_string(expiring.expires_at).unwrap_or_default();
+    let now = chrono::Utc::now().timestamp();
+    let days_left = (expiring.expires_at - now).div_euclid(24 * 60 * 60);
+
+    if days_left < 0 {
+        cformat!("<red,bold>{}</> (expired?)", expires_at)
+    } else if days_left <= EXPIRY_WARNING_DAYS {
+        cformat!("<red,bold>{}</> ({} days left)", expires_at, days_left)
+    } else {
+        cformat!("<s>{}</> ({} days left)", expires_at, days_left)
+    }
+}
+
+pub(crate) async fn musq_point_history(
+    client: &MUClient,
+    acc_info: &Config,
+    validity_days: i64,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    console.info(cformat!(
+        "Getting point history for <magenta,bold>{}</>...",
+        acc_info.id
+    ));
+
+    let history = match client.get_point_history().await {
+        Ok(history) => history,
+        Err(err) => {
+            console.error(format!("Failed to fetch point history: {err}"));
+            return 1;
+        }
+    };
+
+    let user_point = history.user_point().unwrap_or_default();
+    if history.logs().is_empty() {
+        console.warn("No point history found");
+    } else {
+        console.info(cformat!(
+            "Point history (<m,s>{}</> results):",
+            history.logs().len()
+        ));
+        for log in history.logs() {
+            let created_at = chrono::Utc
+                .timestamp_opt(log.created_at() as i64, 0)
+                .single()
+                .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"))
+                .map(|dt| dt.to_string())
+                .unwrap_or_default();
+            console.info(cformat!(
+                "  [<s>{}</>] {}: {}",
+                created_at,
+                log.displayed_text(),
+                format_point_changes(log)
+            ));
+        }
+    }
+
+    console.info("");
+    console.info(cformat!(
+        "XP coin expiry forecast (estimated from <s>{}</> days validity):",
+        validity_days
+    ));
+    let forecast = forecast_event_expiry(user_point.event(), history.logs(), validity_days);
+    if forecast.is_empty() {
+        console.info("  No XP coin that would expire");
+    }
+    let attributed: u64 = forecast.iter().map(|exp| exp.amount).sum();
+    for expiring in forecast.iter() {
+        console.info(cformat!(
+            "  - <magenta,bold>{}</>c acquired at {}, expires at {}",
+            expiring.amount.to_formatted_string(&Locale::en),
+            unix_timestamp_to_string(expiring.acquired_at).unwrap_or_default(),
+            format_expiry(expiring)
+        ));
+    }
+    if attributed < user_point.event() {
+        console.warn(cformat!(
+            "  <s>{}</>c XP coin is older than the history, unable to forecast the expiry",
+            (user_point.event() - attributed).to_formatted_string(&Locale::en)
+        ));
+    }
+
+    // free coin does not expire, but the recovery stops when it reach the limit
+    if let Ok(shop) = client.get_point_shop().await
+        && let Some(limit) = shop.point_limit()
+    {
+        let free = shop.user_point().unwrap_or_default().free();
+        if limit.free() > 0 && free >= limit.free() {
+            console.warn(cformat!(
+                "Free coin is at the recovery limit (<s>{}</>/<s>{}</>c), daily recovery is wasted until you spend it",
+                free,
+                limit.free()
+            ));
+        }
+    }
+
+    0
+}
+
+/// Warn if there is XP coin that would expire soon while chapters are skipped.
+///
+/// Used by `autodownload` when some chapters are not purchased, `skipped_price`
+/// is the total price of those chapters.
+pub(super) async fn warn_expiring_points(
+    client: &MUClient,
+    skipped_count: usize,
+    skipped_price: u64,
+    console: &crate::term::Terminal,
+) {
+    let Ok(history) = client.get_point_history().await else {
+        return;
+    };
+
+    let user_point = history.user_point().unwrap_or_default();
+    let warn_until = chrono::Utc::now().timestamp() + EXPIRY_WARNING_DAYS * 24 * 60 * 60;
+    let expiring: Vec<ExpiringPoint> = forecast_event_expiry(
+        user_point.event(),
+        history.logs(),
+        EVENT_POINT_VALIDITY_DAYS,
+    )
+    .into_iter()
+    .filter(|exp| exp.expires_at <= warn_until)
+    .collect();
+
+    let Some(nearest) = expiring.first() else {
+        return;
+    };
+
+    let amount: u64 = expiring.iter().map(|exp| exp.amount).sum();
+    console.warn(cformat!(
+        "<magenta,bold>{}</>c XP coin would expire soon (nearest at {}), it could be spent on <m,s>{}</> skipped chapters (<s>{}</>c)",
+        amount.to_formatted_string(&Locale::en),
+        format_expiry(nearest),
+        skipped_count,
+        skipped_price.to_formatted_string(&Locale::en)
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_log(event_point: u64, created_at: u64) -> PointHistory {
+        let mut buf = vec![];
+        prost::encoding::uint64::encode(3, &event_point, &mut buf);
+        prost::encoding::uint64::encode(5, &created_at, &mut buf);
+        <PointHistory as prost::Message>::decode(buf.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_forecast_event_expiry() {
+        let day = 24 * 60 * 60;
+        let logs = vec![
+            make_log(100, 10 * day),
+            make_log(0, 20 * day),
+            make_log(50, 30 * day),
+            make_log(30, 40 * day),
+        ];
+
+        // the oldest acquisition has been partially spent
+        let forecast = forecast_event_expiry(100, &logs, 90);
+        assert_eq!(
+            forecast,
+            vec![
+                ExpiringPoint {
+                    amount: 20,
+                    acquired_at: 10 * day as i64,
+                    expires_at: 100 * day as i64,
+                },
+                ExpiringPoint {
+                    amount: 50,
+                    acquired_at: 30 * day as i64,
+                    expires_at: 120 * day as i64,
+                },
+                ExpiringPoint {
+                    amount: 30,
+                    acquired_at: 40 * day as i64,
+                    expires_at: 130 * day as i64,
+                },
+            ]
+        );
+
+        assert!(forecast_event_expiry(0, &logs, 90).is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+
+use tosho_musq::{
+    ConsumeCoin, MUClient,
+    proto::{MangaDetailV2, UserPoint},
+};
+
+use crate::{
+    cli::ExitCode,
+    config::ConfigImpl,
+    r#impl::{
+        Implementations,
+        client::make_musq_client,
+        pool::{PoolPayment, PoolRange, PoolReceipts, PoolSource, is_chapter_downloaded},
+    },
+};
+
+use super::{
+    config::Config,
+    download::{MUDownloadCliConfig, get_output_directory, musq_download_with_receipts},
+};
+
+/// A pool member with the title detail as seen by the account
+pub(crate) struct MUPoolMember {
+    client: MUClient,
+    detail: MangaDetailV2,
+    purse: UserPoint,
+}
+
+/// Download a title with every MU! account in a pool.
+pub(crate) struct MUPool {
+    pub(crate) title_id: u64,
+    pub(crate) dl_config: MUDownloadCliConfig,
+    pub(crate) output_dir: PathBuf,
+}
+
+impl MUPool {
+    /// The coins needed to unlock the chapter, `None` if it's free
+    fn member_consume(
+        &self,
+        member: &MUPoolMember,
+        chapter_id: u64,
+    ) -> Option<Option<ConsumeCoin>> {
+        let chapter = member
+            .detail
+            .chapters()
+            .iter()
+            .find(|ch| ch.id() == chapter_id)?;
+        if chapter.is_free() {
+            return Some(None);
+        }
+        if !self.dl_config.auto_purchase {
+            return None;
+        }
+
+        let consume = member.client.calculate_coin(&member.purse, chapter).ok()?;
+        consume.is_possible().then_some(Some(consume))
+    }
+}
+
+impl PoolSource for MUPool {
+    type Account = Config;
+    type Member = MUPoolMember;
+
+    const IMPLEMENTATION: Implementations = Implementations::Musq;
+
+    fn title_id(&self) -> u64 {
+        self.title_id
+    }
+
+    fn range(&self) -> PoolRange {
+        PoolRange {
+            chapter_ids: self.dl_config.chapter_ids.clone(),
+            start_from: self.dl_config.start_from,
+            end_at: self.dl_config.end_at,
+        }
+    }
+
+    fn account(config: ConfigImpl) -> Option<Config> {
+        match config {
+            ConfigImpl::Musq(config) => Some(config),
+            _ => None,
+        }
+    }
+
+    fn account_id(account: &Config) -> &str {
+        account.get_id()
+    }
+
+    async fn fetch_member(
+        &self,
+        account: Config,
+        proxy: Option<reqwest::Proxy>,
+    ) -> color_eyre::Result<MUPoolMember> {
+        let client = make_musq_client(&account)?;
+        let client = match proxy {
+            Some(proxy) => client.with_proxy(proxy)?,
+            None => client,
+        };
+
+        let detail = client.get_manga(self.title_id).await?;
+        let mut purse = detail.user_point().unwrap_or_default();
+        if self.dl_config.no_paid_point {
+            purse.set_paid(0);
+        }
+        if self.dl_config.no_xp_point {
+            purse.set_event(0);
+        }
+
+        Ok(MUPoolMember {
+            client,
+            detail,
+            purse,
+        })
+    }
+
+    fn balance(&self, member: &MUPoolMember) -> String {
+        format!(
+            "{}c free, {}c XP, {}c paid",
+            member.purse.free(),
+            member.purse.event(),
+            member.purse.paid()
+        )
+    }
+
+    fn chapters(&self, member: &MUPoolMember) -> Vec<(u64, String)> {
+        member
+            .detail
+            .chapters()
+            .iter()
+            .map(|ch| (ch.id(), ch.title().to_string()))
+            .collect()
+    }
+
+    fn is_downloaded(&self, chapter_id: u64) -> bool {
+        let title_dir = get_output_directory(&self.output_dir, self.title_id, None, false);
+        let ch_dir = get_output_directory(&self.output_dir, self.title_id, Some(chapter_id), false);
+        is_chapter_downloaded(
+            &title_dir,
+            &ch_dir,
+            chapter_id,
+            self.dl_config.only_check_folder,
+        )
+    }
+
+    fn payment(&self, member: &MUPoolMember, chapter_id: u64) -> Option<PoolPayment> {
+        match self.member_consume(member, chapter_id)? {
+            None => Some(PoolPayment::Owned),
+            Some(consume) if consume.get_paid() == 0 => Some(PoolPayment::Free),
+            Some(_) => Some(PoolPayment::Paid),
+        }
+    }
+
+    fn reserve(&self, member: &mut MUPoolMember, chapter_id: u64) {
+        if let Some(Some(consume)) = self.member_consume(member, chapter_id) {
+            member.purse.subtract_free(consume.get_free());
+            member.purse.subtract_event(consume.get_event());
+            member.purse.subtract_paid(consume.get_paid());
+        }
+    }
+
+    async fn download(
+        &self,
+        member: &MUPoolMember,
+        chapter_ids: Vec<usize>,
+        receipts: &mut PoolReceipts,
+        console: &mut crate::term::Terminal,
+    ) -> ExitCode {
+        let member_config = MUDownloadCliConfig {
+            no_input: true,
+            chapter_ids,
+            start_from: None,
+            end_at: None,
+            ..self.dl_config.clone()
+        };
+        musq_download_with_receipts(
+            self.title_id,
+            member_config,
+            self.output_dir.clone(),
+            &member.client,
+            receipts,
+            console,
+        )
+        .await
+    }
+}
@@ -4,6 +4,7 @@ use color_print::cformat;
 use crate::{
     cli::ExitCode,
     config::{get_all_config, save_config, try_remove_config},
+    r#impl::credentials::{CredentialArg, resolve_credential},
 };
 
 use super::config::{Config, DeviceType};
@@ -38,10 +39,22 @@ impl ValueEnum for DeviceKind {
 }
 
 pub(crate) async fn nids_auth_session(
-    session_token: String,
+    session_token: CredentialArg,
     device_kind: DeviceKind,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
+    let Some(session_token) = resolve_credential(
+        session_token,
+        "TOSHO_NI_SESSION",
+        "Session token",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
     let r#type = match device_kind {
         DeviceKind::Web => DeviceType::Web,
     };
@@ -79,7 +92,8 @@ pub(crate) async fn nids_auth_session(
 
                     if let Some(old_config) = old_config {
                         console.warn("Session ID already authenticated!");
-                        let abort_it = console.confirm(Some("Do you want to replace it?"));
+                        let abort_it =
+                            no_input || console.confirm(Some("Do you want to replace it?"));
                         if !abort_it {
                             console.info("Aborting...");
                             return 0;
@@ -116,12 +130,29 @@ pub(crate) async fn nids_auth_session(
 }
 
 pub(crate) async fn nids_auth_email(
-    email: String,
-    password: String,
+    email: Option<String>,
+    password: CredentialArg,
     device_kind: DeviceKind,
     proxy: Option<&reqwest::Proxy>,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
+    let Some(email) =
+        resolve_credential(email, "TOSHO_NI_EMAIL", "Email", false, no_input, console)
+    else {
+        return 1;
+    };
+    let Some(password) = resolve_credential(
+        password,
+        "TOSHO_NI_PASSWORD",
+        "Password",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
     let r#type = match device_kind {
         DeviceKind::Web => DeviceType::Web,
     };
@@ -153,7 +184,7 @@ pub(crate) async fn nids_auth_email(
 
             if let Some(old_config) = old_config {
                 console.warn("Session ID already authenticated!");
-                let abort_it = console.confirm(Some("Do you want to replace it?"));
+                let abort_it = no_input || console.confirm(Some("Do you want to replace it?"));
                 if !abort_it {
                     console.info("Aborting...");
                     return 0;
@@ -0,0 +1,427 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use color_print::cformat;
+use serde::{Deserialize, Serialize};
+use tosho_nids::{
+    NIClient,
+    filters::{FilterType, SortBy, SortOrder},
+    models::{Creator, IssueSummary, PurchasedIssue},
+};
+
+use crate::{
+    cli::ExitCode,
+    config::get_user_path,
+    r#impl::nids::{
+        common::{PaginateAction, pagination_helper},
+        issues::{nids_get_issues, print_issue_summary},
+    },
+};
+
+/// A creator that is being watched for new issues.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct WatchedCreator {
+    /// The creator display name
+    name: String,
+    /// The issues that has been seen before
+    seen_issues: BTreeSet<u32>,
+    /// The purchased issues that has been seen before
+    owned_issues: BTreeSet<u32>,
+}
+
+fn watch_path() -> std::path::PathBuf {
+    get_user_path().join("nids_creator_watch.json")
+}
+
+/// Load the watched creators, keyed by the creator ID.
+fn load_watched() -> BTreeMap<u32, WatchedCreator> {
+    std::fs::read(watch_path())
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_watched(watched: &BTreeMap<u32, WatchedCreator>) -> std::io::Result<()> {
+    let data = serde_json::to_vec_pretty(watched)?;
+    std::fs::write(watch_path(), data)
+}
+
+/// Get the IDs that are not in the seen set, keeping the original order.
+fn unseen_ids(ids: &[u32], seen: &BTreeSet<u32>) -> Vec<u32> {
+    ids.iter()
+        .filter(|id| !seen.contains(id))
+        .copied()
+        .collect()
+}
+
+fn print_creator(creator: &Creator, console: &crate::term::Terminal) {
+    console.info(cformat!(
+        "  <s>{}</s> (<m,s>{}</m,s> / {})",
+        creator.name(),
+        creator.id(),
+        creator.slug()
+    ));
+    if let Some(roles) = creator.roles()
+        && !roles.is_empty()
+    {
+        console.info(cformat!("   <s>Roles</s>: {}", roles.join(", ")));
+    }
+}
+
+async fn fetch_creator(creator_id: u32, client: &NIClient) -> color_eyre::eyre::Result<Creator> {
+    let filters = tosho_nids::Filter::new()
+        .add_filter(FilterType::Id, creator_id)
+        .with_per_page(1);
+    let creators = client.get_creators(Some(&filters)).await?;
+
+    creators
+        .data()
+        .iter()
+        .find(|creator| creator.id() == creator_id)
+        .cloned()
+        .ok_or_else(|| color_eyre::eyre::eyre!("Creator {creator_id} not found"))
+}
+
+/// Fetch the latest issues released by the creator.
+async fn fetch_latest_issues(
+    creator_id: u32,
+    client: &NIClient,
+) -> color_eyre::eyre::Result<Vec<IssueSummary>> {
+    let filters = tosho_nids::Filter::new()
+        .add_filter(FilterType::CreatorId, creator_id)
+        .with_order(SortBy::ReleaseDate, SortOrder::DESC)
+        .with_per_page(50)
+        .with_page(1);
+
+    let issues = client.get_issues(&filters).await?;
+    Ok(issues.data().to_vec())
+}
+
+/// Fetch all the purchased issues by the creator.
+async fn fetch_owned_issues(
+    creator_id: u32,
+    client: &NIClient,
+) -> color_eyre::eyre::Result<Vec<PurchasedIssue>> {
+    let mut filters = tosho_nids::Filter::new()
+        .add_filter(FilterType::CreatorId, creator_id)
+        .with_order(SortBy::FullTitle, SortOrder::ASC)
+        .with_per_page(100)
+        .with_page(1);
+
+    let mut issues = vec![];
+    let mut current_page = 1;
+    loop {
+        let response = client.get_issue_collections(&filters).await?;
+        issues.extend(response.data().iter().cloned());
+        if current_page >= response.pages() {
+            break;
+        }
+        current_page += 1;
+        filters.set_page(current_page);
+    }
+
+    Ok(issues)
+}
+
+pub(crate) async fn nids_get_creators(
+    filters: &mut tosho_nids::Filter,
+    client: &NIClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    console.info("Fetching initial creators with the filter...");
+    let creators = match client.get_creators(Some(filters)).await {
+        Ok(creators) => creators,
+        Err(e) => {
+            console.error(format!("Failed to get creators: {}", e));
+            return 1;
+        }
+    };
+
+    if creators.data().is_empty() {
+        console.info("No creators found with the given filters.");
+        return 0;
+    }
+
+    let mut stop_code = 0;
+    if creators.pages() > 1 {
+        // Do paginated response
+        let mut current_page: u32 = 1;
+        let mut maximum_pages: u32 = creators.pages();
+        let mut collected_creators: HashMap<u32, Vec<Creator>> =
+            HashMap::from([(1, creators.data().to_vec())]);
+        let mut current_data = collected_creators.get(&1).expect("Somehow missing page 1");
+
+        loop {
+            console.info(cformat!(
+                "Showing page <m,s>{}</m,s> of <m,s>{}</m,s>:",
+                current_page,
+                maximum_pages
+            ));
+            for creator in current_data.iter() {
+                print_creator(creator, console);
+            }
+            if current_data.is_empty() {
+                console.info("No creators found on this page.");
+            }
+
+            match pagination_helper(current_page, maximum_pages, console).await {
+                PaginateAction::Next => {
+                    current_page += 1;
+                }
+                PaginateAction::Previous => {
+                    if current_page > 1 {
+                        current_page -= 1;
+                    }
+                }
+                PaginateAction::Exit(code) => {
+                    stop_code = code;
+                    break;
+                }
+            }
+
+            // Fetch new stuff
+            filters.set_page(current_page);
+            if let Some(creators) = collected_creators.get(&current_page) {
+                current_data = creators;
+                console.clear_screen();
+            } else {
+                console.info(cformat!("Loading page <m,s>{}</m,s>...", current_page));
+                let new_creators = match client.get_creators(Some(filters)).await {
+                    Ok(creators) => creators,
+                    Err(e) => {
+                        console.error(format!("Failed to get creators: {}", e));
+                        stop_code = 1;
+                        break;
+                    }
+                };
+
+                console.clear_screen();
+
+                maximum_pages = new_creators.pages();
+                collected_creators.insert(current_page, new_creators.data().to_vec());
+                current_data = collected_creators
+                    .get(&current_page)
+                    .expect("Somehow missing page after insert");
+            }
+        }
+    } else {
+        // Print all creators
+        for creator in creators.data() {
+            print_creator(creator, console);
+        }
+    }
+
+    stop_code
+}
+
+pub(crate) async fn nids_get_creator(
+    creator_id: u32,
+    limit: u32,
+    client: &NIClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    console.info(cformat!("Fetching creator <m,s>{}</m,s>...", creator_id));
+
+    let creator = match fetch_creator(creator_id, client).await {
+        Ok(creator) => creator,
+        Err(e) => {
+            console.error(format!("Failed to get creator: {}", e));
+            return 1;
+        }
+    };
+
+    console.info(cformat!(
+        "Showing information for <m,s>{}</m,s>:",
+        creator.name()
+    ));
+    console.info(cformat!(
+        "  <s>ID</s>: {} / {}",
+        creator.id(),
+        creator.uuid()
+    ));
+    if let Some(roles) = creator.roles()
+        && !roles.is_empty()
+    {
+        console.info(cformat!("  <s>Roles</s>: {}", roles.join(", ")));
+    }
+
+    let mut filters = tosho_nids::Filter::new()
+        .add_filter(FilterType::CreatorId, creator_id)
+        .with_order(SortBy::ReleaseDate, SortOrder::DESC)
+        .with_per_page(limit);
+    nids_get_issues(&mut filters, client, console).await
+}
+
+pub(crate) async fn nids_watch_creator(
+    creator_id: u32,
+    remove: bool,
+    client: &NIClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let mut watched = load_watched();
+
+    if remove {
+        let Some(creator) = watched.remove(&creator_id) else {
+            console.warn(cformat!(
+                "Creator <m,s>{}</m,s> is not being watched",
+                creator_id
+            ));
+            return 1;
+        };
+
+        return match save_watched(&watched) {
+            Ok(_) => {
+                console.info(cformat!(
+                    "Stopped watching <m,s>{}</m,s> ({})",
+                    creator.name,
+                    creator_id
+                ));
+                0
+            }
+            Err(e) => {
+                console.error(format!("Failed to save the watched creators: {}", e));
+                1
+            }
+        };
+    }
+
+    if watched.contains_key(&creator_id) {
+        console.warn(cformat!(
+            "Creator <m,s>{}</m,s> is already being watched",
+            creator_id
+        ));
+        return 0;
+    }
+
+    console.info(cformat!("Fetching creator <m,s>{}</m,s>...", creator_id));
+    let creator = match fetch_creator(creator_id, client).await {
+        Ok(creator) => creator,
+        Err(e) => {
+            console.error(format!("Failed to get creator: {}", e));
+            return 1;
+        }
+    };
+
+    // mark the current and purchased issues as seen so only the new one get reported
+    let issues = match fetch_latest_issues(creator_id, client).await {
+        Ok(issues) => issues,
+        Err(e) => {
+            console.error(format!("Failed to get creator issues: {}", e));
+            return 1;
+        }
+    };
+    let owned = match fetch_owned_issues(creator_id, client).await {
+        Ok(owned) => owned,
+        Err(e) => {
+            console.error(format!("Failed to get purchased issues: {}", e));
+            return 1;
+        }
+    };
+
+    watched.insert(
+        creator_id,
+        WatchedCreator {
+            name: creator.name().to_string(),
+            seen_issues: issues.iter().map(|issue| issue.id()).collect(),
+            owned_issues: owned.iter().map(|issue| issue.id()).collect(),
+        },
+    );
+
+    match save_watched(&watched) {
+        Ok(_) => {
+            console.info(cformat!(
+                "Now watching <m,s>{}</m,s> ({}), use <s>nids watch</s> to check for new issues",
+                creator.name(),
+                creator_id
+            ));
+            0
+        }
+        Err(e) => {
+            console.error(format!("Failed to save the watched creators: {}", e));
+            1
+        }
+    }
+}
+
+pub(crate) async fn nids_check_watched_creators(
+    client: &NIClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let mut watched = load_watched();
+    if watched.is_empty() {
+        console.warn("No creators are being watched, use `nids watch-creator` to add one");
+        return 0;
+    }
+
+    let mut exit_code = 0;
+    for (creator_id, creator) in watched.iter_mut() {
+        console.info(cformat!(
+            "Checking <m,s>{}</m,s> ({})...",
+            creator.name,
+            creator_id
+        ));
+
+        match fetch_latest_issues(*creator_id, client).await {
+            Ok(issues) => {
+                let ids: Vec<u32> = issues.iter().map(|issue| issue.id()).collect();
+                let new_ids = unseen_ids(&ids, &creator.seen_issues);
+                if !new_ids.is_empty() {
+                    console.info(cformat!(
+                        " <g,s>{}</> new purchasable issues:",
+                        new_ids.len()
+                    ));
+                    for issue in issues.iter().filter(|issue| new_ids.contains(&issue.id())) {
+                        print_issue_summary(issue, console);
+                    }
+                }
+                creator.seen_issues.extend(new_ids);
+            }
+            Err(e) => {
+                console.error(format!("   Failed to get creator issues: {}", e));
+                exit_code = 1;
+            }
+        }
+
+        match fetch_owned_issues(*creator_id, client).await {
+            Ok(owned) => {
+                let ids: Vec<u32> = owned.iter().map(|issue| issue.id()).collect();
+                let new_ids = unseen_ids(&ids, &creator.owned_issues);
+                if !new_ids.is_empty() {
+                    console.info(cformat!(" <g,s>{}</> new purchased issues:", new_ids.len()));
+                    for issue in owned.iter().filter(|issue| new_ids.contains(&issue.id())) {
+                        console.info(cformat!(
+                            "  - <s>{}</s> (<m,s>{}</m,s> / {})",
+                            issue.full_title(),
+                            issue.id(),
+                            issue.uuid()
+                        ));
+                    }
+                }
+                creator.owned_issues.extend(new_ids);
+            }
+            Err(e) => {
+                console.error(format!("   Failed to get purchased issues: {}", e));
+                exit_code = 1;
+            }
+        }
+    }
+
+    if let Err(e) = save_watched(&watched) {
+        console.error(format!("Failed to save the watched creators: {}", e));
+        return 1;
+    }
+
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unseen_ids() {
+        let seen = BTreeSet::from([1, 3]);
+
+        assert_eq!(unseen_ids(&[4, 3, 2, 1], &seen), vec![4, 2]);
+        assert_eq!(unseen_ids(&[1, 3], &seen), Vec::<u32>::new());
+    }
+}
@@ -9,7 +9,18 @@ use num_format::{Locale, ToFormattedString};
 use tokio::time::Instant;
 use tosho_nids::NIClient;
 
-use crate::{cli::ExitCode, r#impl::nids::common::timedelta_to_humantime};
+use crate::{
+    cli::ExitCode,
+    r#impl::{
+        common::{
+            INTERRUPTED_EXIT_CODE, check_downloaded_pages, is_chapter_interrupted,
+            is_shutdown_requested, record_written_chapter,
+        },
+        models::SourceDump,
+        nids::common::timedelta_to_humantime,
+        provenance::{ChapterProvenance, PageSource, embed_chapter_metadata},
+    },
+};
 
 #[derive(Debug, Clone, Default)]
 pub(crate) enum DownloadImageQuality {
@@ -87,6 +98,11 @@ async fn nids_actual_downloader(
     console: &crate::term::Terminal,
     progress: Arc<indicatif::ProgressBar>,
 ) -> color_eyre::eyre::Result<()> {
+    // stop before the next page when shutting down
+    if is_shutdown_requested() {
+        return Ok(());
+    }
+
     // determine image extension from page_url
     let extension = match extract_extensions_from_url(&node.page_url) {
         Some(ext) => ext,
@@ -122,10 +138,13 @@ async fn nids_actual_downloader(
     Ok(())
 }
 
-#[derive(serde::Serialize)]
-struct FrameWithPage {
-    frame: tosho_nids::models::reader::ReaderFrame,
-    filename: String,
+/// The file name of the dumped reading frames in the issue folder.
+pub(super) const FRAMES_FILE: &str = "frames.json";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(super) struct FrameWithPage {
+    pub(super) frame: tosho_nids::models::reader::ReaderFrame,
+    pub(super) filename: String,
 }
 
 fn dump_reading_frames(
@@ -151,7 +170,7 @@ fn dump_reading_frames(
     }
 
     if has_any {
-        let output_file = output_dir.join("frames.json");
+        let output_file = output_dir.join(FRAMES_FILE);
         match serde_json::to_string_pretty(&frames_with_page) {
             Ok(json_str) => {
                 if let Err(err) = std::fs::write(&output_file, json_str) {
@@ -179,6 +198,10 @@ pub(crate) struct NIDownloadCliConfig {
     pub(crate) output: Option<PathBuf>,
     /// Report page viewing progress to the server
     pub(crate) report: bool,
+    /// Embed the source metadata into the downloaded pages
+    pub(crate) embed_metadata: bool,
+    /// Report the issue as read once the download completes
+    pub(crate) report_finished: bool,
 }
 
 impl Default for NIDownloadCliConfig {
@@ -189,6 +212,8 @@ impl Default for NIDownloadCliConfig {
             output: None,
             report: false,
             quality: DownloadImageQuality::Desktop,
+            embed_metadata: false,
+            report_finished: false,
         }
     }
 }
@@ -207,6 +232,19 @@ async fn nids_report_progress(
     Ok(())
 }
 
+/// Mark the whole issue as read by reporting the last page.
+async fn nids_report_finished(
+    pages_meta: &tosho_nids::models::reader::StreamedReaderPages,
+    client: &NIClient,
+    console: &crate::term::Terminal,
+) {
+    let issue_uuid = pages_meta.header().issue().uuid();
+    let last_page = pages_meta.pages().len() as u32;
+    if let Err(err) = nids_report_progress(issue_uuid, last_page, client).await {
+        console.warn(format!("   {err}"));
+    }
+}
+
 pub(crate) async fn nids_download(
     issue_id: u32,
     dl_config: NIDownloadCliConfig,
@@ -266,6 +304,21 @@ pub(crate) async fn nids_download(
     );
     let output_dir = dl_config.output.clone().unwrap_or(default_dir);
 
+    let expected_pages: Vec<String> = (0..pages_meta.pages().len())
+        .map(|idx| format!("i_{:04}", idx + 1))
+        .collect();
+    if check_downloaded_pages(&output_dir, &expected_pages) {
+        console.warn(cformat!(
+            "   Issue <m,s>{}</> (<s>{}</>) has been downloaded, skipping",
+            issue_title,
+            issue_id
+        ));
+        if dl_config.report_finished {
+            nids_report_finished(&pages_meta, client, console).await;
+        }
+        return 0;
+    }
+
     if !output_dir.exists()
         && let Err(err) = std::fs::create_dir_all(&output_dir)
     {
@@ -423,11 +476,59 @@ pub(crate) async fn nids_download(
 
     let duration = end_time - current_time;
     progress.finish_with_message("Downloaded");
+
+    if is_chapter_interrupted(&output_dir, &expected_pages) {
+        console.warn(cformat!(
+            "   Shutdown requested, issue <m,s>{}</> (<s>{}</>) is left incomplete",
+            issue_title,
+            issue_id
+        ));
+        return INTERRUPTED_EXIT_CODE;
+    }
+    // don't report or post-process an incomplete issue, the missing pages are retried next time
+    if !check_downloaded_pages(&output_dir, &expected_pages) {
+        console.error(cformat!(
+            "   Issue <m,s>{}</> (<s>{}</>) is incomplete, some pages failed to download",
+            issue_title,
+            issue_id
+        ));
+        return 1;
+    }
+    record_written_chapter(&output_dir);
+
     console.info(cformat!(
         "Downloaded <m,s>{}</m,s> in <m,s>{}</m,s>",
         issue_title,
         timedelta_to_humantime(duration)
     ));
 
+    if dl_config.report_finished {
+        nids_report_finished(&pages_meta, client, console).await;
+    }
+
+    if dl_config.embed_metadata {
+        let issue = edition_issue.issue();
+        let provenance = ChapterProvenance {
+            source: SourceDump::Nids,
+            title: issue.series_run().title().to_string(),
+            title_id: (issue.series_run().id() as u64).into(),
+            chapter: issue.full_title().to_string(),
+            chapter_id: (issue.id() as u64).into(),
+        };
+        let page_sources: Vec<PageSource> = pages_meta
+            .pages()
+            .iter()
+            .enumerate()
+            .map(|(idx, page)| {
+                let page_url = match dl_config.quality {
+                    DownloadImageQuality::Desktop => page.image().url(),
+                    DownloadImageQuality::Mobile => page.image().mobile_url(),
+                };
+                PageSource::new(format!("i_{:04}", idx + 1), Some(page_url))
+            })
+            .collect();
+        embed_chapter_metadata(&output_dir, &provenance, page_sources, console).await;
+    }
+
     0
 }
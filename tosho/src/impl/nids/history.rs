@@ -0,0 +1,235 @@
+use std::path::PathBuf;
+
+use color_print::cformat;
+use tosho_nids::{
+    NIClient,
+    filters::{FilterType, SortBy, SortOrder},
+    models::{PurchasedIssue, others::ReadingHistory},
+};
+
+use crate::{
+    cli::ExitCode,
+    r#impl::{
+        common::INTERRUPTED_EXIT_CODE,
+        nids::{
+            common::fmt_date,
+            download::{NIDownloadCliConfig, nids_download},
+        },
+    },
+};
+
+fn is_finished(history: &ReadingHistory) -> bool {
+    history
+        .bookmark_page()
+        .is_some_and(|page| page >= history.total_pages())
+}
+
+fn format_progress(history: &ReadingHistory) -> String {
+    match history.bookmark_page() {
+        _ if is_finished(history) => cformat!("<g,s>Finished</>"),
+        Some(page) => cformat!("page <m,s>{}</>/<m,s>{}</>", page, history.total_pages()),
+        None => cformat!("<s>Not started</>"),
+    }
+}
+
+/// Get the latest read issue of each series, ordered by the most recent first.
+fn latest_per_series(history: &[ReadingHistory]) -> Vec<&ReadingHistory> {
+    let mut sorted: Vec<&ReadingHistory> = history.iter().collect();
+    sorted.sort_by_key(|item| std::cmp::Reverse(item.last_read()));
+
+    let mut latest: Vec<&ReadingHistory> = vec![];
+    for item in sorted {
+        if !latest
+            .iter()
+            .any(|seen| seen.series_run().id() == item.series_run().id())
+        {
+            latest.push(item);
+        }
+    }
+    latest
+}
+
+/// Find the position of the issues to continue reading from the ordered issues list.
+///
+/// Returns up to `count` issues, starting from the last read issue if it's not
+/// finished yet, or the issue right after it.
+fn next_unread_positions(
+    ordered_uuids: &[&str],
+    last_read_uuid: &str,
+    finished: bool,
+    count: usize,
+) -> Vec<usize> {
+    let Some(position) = ordered_uuids
+        .iter()
+        .position(|&uuid| uuid == last_read_uuid)
+    else {
+        return vec![];
+    };
+
+    let start = if finished { position + 1 } else { position };
+    (start..ordered_uuids.len()).take(count).collect()
+}
+
+async fn fetch_series_issues(
+    series_run_uuid: &str,
+    client: &NIClient,
+) -> color_eyre::eyre::Result<Vec<PurchasedIssue>> {
+    let mut filters = tosho_nids::Filter::new()
+        .add_filter(FilterType::SeriesRunId, series_run_uuid)
+        .with_order(SortBy::BookIndex, SortOrder::ASC)
+        .with_per_page(100)
+        .with_page(1);
+
+    let mut issues = vec![];
+    let mut current_page = 1;
+    loop {
+        let response = client.get_issue_collections(&filters).await?;
+        issues.extend(response.data().iter().cloned());
+        if current_page >= response.pages() {
+            break;
+        }
+        current_page += 1;
+        filters.set_page(current_page);
+    }
+
+    Ok(issues)
+}
+
+pub(crate) async fn nids_reading_history(
+    client: &NIClient,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    console.info("Fetching reading history...");
+
+    let history = match client.get_reading_history().await {
+        Ok(history) => history,
+        Err(err) => {
+            console.error(format!("Failed to fetch reading history: {err}"));
+            return 1;
+        }
+    };
+
+    if history.data().is_empty() {
+        console.warn("No reading history found.");
+        return 0;
+    }
+
+    console.info(cformat!(
+        "Reading history (<m,s>{}</> issues):",
+        history.data().len()
+    ));
+    for item in history.data() {
+        console.info(cformat!(
+            "  <s>{}</> (<m,s>{}</> / {})",
+            item.full_title(),
+            item.id(),
+            item.uuid()
+        ));
+        let last_read = item
+            .last_read()
+            .map(|date| format!(", last read {}", fmt_date(date)))
+            .unwrap_or_default();
+        console.info(format!("    {}{}", format_progress(item), last_read));
+    }
+
+    0
+}
+
+pub(crate) async fn nids_continue_reading(
+    count: usize,
+    dl_config: NIDownloadCliConfig,
+    output_dir: PathBuf,
+    client: &NIClient,
+    console: &mut crate::term::Terminal,
+) -> ExitCode {
+    console.info("Fetching reading history...");
+
+    let history = match client.get_reading_history().await {
+        Ok(history) => history,
+        Err(err) => {
+            console.error(format!("Failed to fetch reading history: {err}"));
+            return 1;
+        }
+    };
+
+    let latest = latest_per_series(history.data());
+    if latest.is_empty() {
+        console.warn("No reading history found.");
+        return 0;
+    }
+
+    let mut exit_code = 0;
+    for last_read in latest {
+        console.info(cformat!(
+            "Checking <m,s>{}</> (last read <s>{}</>, {})...",
+            last_read.series_run().title(),
+            last_read.full_title(),
+            format_progress(last_read)
+        ));
+
+        let issues = match fetch_series_issues(last_read.series_run().id(), client).await {
+            Ok(issues) => issues,
+            Err(err) => {
+                console.error(format!("   Failed to fetch purchased issues: {err}"));
+                exit_code = 1;
+                continue;
+            }
+        };
+
+        let ordered_uuids: Vec<&str> = issues.iter().map(|issue| issue.uuid()).collect();
+        let positions = next_unread_positions(
+            &ordered_uuids,
+            last_read.uuid(),
+            is_finished(last_read),
+            count,
+        );
+        if positions.is_empty() {
+            console.info("   No unread purchased issues, skipping");
+            continue;
+        }
+
+        for position in positions {
+            let issue = &issues[position];
+            let code = nids_download(
+                issue.id(),
+                dl_config.clone(),
+                output_dir.clone(),
+                client,
+                console,
+            )
+            .await;
+            if code == INTERRUPTED_EXIT_CODE {
+                return code;
+            }
+            if code != 0 {
+                // keep the series at the incomplete issue, so it's continued next time
+                exit_code = code;
+                break;
+            }
+        }
+    }
+
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_unread_positions() {
+        let uuids = ["a", "b", "c", "d"];
+
+        // unfinished issue should be continued first
+        assert_eq!(next_unread_positions(&uuids, "b", false, 2), vec![1, 2]);
+        assert_eq!(next_unread_positions(&uuids, "b", true, 2), vec![2, 3]);
+        assert_eq!(
+            next_unread_positions(&uuids, "d", true, 2),
+            Vec::<usize>::new()
+        );
+        assert_eq!(
+            next_unread_positions(&uuids, "z", false, 2),
+            Vec::<usize>::new()
+        );
+    }
+}
@@ -10,7 +10,10 @@ use color_print::cformat;
 use num_format::{Locale, ToFormattedString};
 use tosho_nids::{constants::BASE_HOST, models::SaleStatus};
 
-fn print_issue_summary(issue: &tosho_nids::models::IssueSummary, console: &crate::term::Terminal) {
+pub(super) fn print_issue_summary(
+    issue: &tosho_nids::models::IssueSummary,
+    console: &crate::term::Terminal,
+) {
     let item_url = format!("https://{}/item/{}/{}", BASE_HOST, issue.id(), issue.slug());
     let linked_title = linkify!(&item_url, issue.full_title());
 
@@ -10,9 +10,12 @@ use crate::r#impl::nids::common::{
 pub(crate) mod accounts;
 pub(crate) mod common;
 pub(crate) mod config;
+pub(crate) mod creators;
 pub(crate) mod download;
+pub(crate) mod history;
 pub(crate) mod issues;
 pub(crate) mod marketplace;
+pub(crate) mod panels;
 pub(crate) mod publishers;
 pub(crate) mod purchases;
 pub(crate) mod series;
@@ -22,25 +25,104 @@ pub(crate) enum NIDSCommands {
     /// Authenticate tosho with your NI account.
     Auth {
         /// Email of the NI account
-        email: String,
+        ///
+        /// Uses `TOSHO_NI_EMAIL` (or `TOSHO_NI_EMAIL_FILE`) when omitted.
+        email: Option<String>,
         /// Password of the NI account
-        password: String,
+        ///
+        /// Uses `TOSHO_NI_PASSWORD` (or `TOSHO_NI_PASSWORD_FILE`) when omitted.
+        password: Option<String>,
+        /// Read the password from a file instead
+        #[arg(long = "password-file", conflicts_with_all = ["password", "password_stdin"])]
+        password_file: Option<PathBuf>,
+        /// Read the password from the first line of stdin instead
+        #[arg(long = "password-stdin", conflicts_with = "password")]
+        password_stdin: bool,
         /// Device kind/type to use
         #[arg(short, long, value_enum, default_value = "web")]
         r#type: crate::r#impl::nids::accounts::DeviceKind,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Authenticate tosho with your NI auth token.
     AuthToken {
         /// Session token to use
-        session_token: String,
+        ///
+        /// Uses `TOSHO_NI_SESSION` (or `TOSHO_NI_SESSION_FILE`) when omitted.
+        session_token: Option<String>,
+        /// Read the session token from a file instead
+        #[arg(long = "session-token-file", conflicts_with_all = ["session_token", "session_token_stdin"])]
+        session_token_file: Option<PathBuf>,
+        /// Read the session token from the first line of stdin instead
+        #[arg(long = "session-token-stdin", conflicts_with = "session_token")]
+        session_token_stdin: bool,
         /// Device kind/type to use
         #[arg(short, long, value_enum, default_value = "web")]
         r#type: crate::r#impl::nids::accounts::DeviceKind,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Get an account information
     Account,
     /// See all the accounts you have authenticated with
     Accounts,
+    /// Get single creator information and their issues by the ID
+    Creator {
+        /// Creator ID to get
+        creator_id: u32,
+
+        /// Maximum number of issues to return
+        #[arg(short = 'l', long = "limit", default_value_t = 18)]
+        limit: u32,
+    },
+    /// Get a list of creators depending on the filters
+    Creators {
+        /// The multiple filter pair to use (ex: key=value)
+        #[arg(short = 'f', long = "filter", default_value = None, value_parser = parse_filter_pairs)]
+        filters: Option<Vec<FilterPairInput>>,
+        /// Search the creators by the name
+        #[arg(short = 'q', long = "query", default_value = None)]
+        query: Option<String>,
+        /// Maximum number of creators to return
+        #[arg(short = 'l', long = "limit", default_value_t = 25)]
+        limit: u32,
+        /// What field to use for sorting
+        ///
+        /// Some examples: `id`, `display_name`
+        #[arg(short = 's', long = "sort", default_value = "display_name", value_parser = parse_sort_by)]
+        sort_by: SortByInput,
+        /// The direction of the sort order
+        #[arg(short = 'd', long = "direction", default_value = "asc")]
+        direction: SortOrderInput,
+    },
+    /// Download the next unread issues for each series in your reading history
+    Continue {
+        /// Maximum number of issues to download per series
+        #[arg(short = 'n', long = "count", default_value_t = 1)]
+        count: usize,
+        /// Output directory to use
+        #[arg(short = 'o', long = "output", default_value = None)]
+        output: Option<PathBuf>,
+        /// Enable parallel download
+        #[arg(short = 'p', long = "parallel")]
+        parallel: bool,
+        /// Number of threads to use for parallel download
+        ///
+        /// Needs to be used with `--parallel` flag.
+        #[arg(short = 't', long = "threads", default_value = "4")]
+        threads: usize,
+        /// Report the issues as read once the download completes
+        #[arg(long = "report", default_value_t = false)]
+        report: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
+        /// Quality of images to download
+        #[arg(short = 'q', long = "quality", default_value = "desktop", value_enum)]
+        quality: crate::r#impl::nids::download::DownloadImageQuality,
+    },
     /// Download an issue by the ID
     Download {
         /// Issue ID to download
@@ -62,10 +144,29 @@ pub(crate) enum NIDSCommands {
         /// Report page viewing progress to the server
         #[arg(long = "report", default_value_t = false)]
         report: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
         /// Quality of images to download
         #[arg(short = 'q', long = "quality", default_value = "desktop", value_enum)]
         quality: crate::r#impl::nids::download::DownloadImageQuality,
     },
+    /// Crop a downloaded issue into its panels in reading order for guided view reading
+    ExportPanels {
+        /// The downloaded issue folder containing the `frames.json`
+        issue_dir: PathBuf,
+        /// The export format
+        #[arg(short = 'f', long = "format", default_value = "images", value_enum)]
+        format: crate::r#impl::nids::panels::PanelExportFormat,
+        /// The width of the vertical strip, used with `--format strip`
+        #[arg(short = 'w', long = "width", default_value_t = 1080, value_parser = clap::value_parser!(u32).range(1..))]
+        width: u32,
+        /// Output file or directory to use
+        #[arg(short = 'o', long = "output", default_value = None)]
+        output: Option<PathBuf>,
+    },
+    /// Get your reading history
+    History,
     /// Get single issue information by the ID
     Issue {
         /// Issue ID to get
@@ -166,4 +267,15 @@ pub(crate) enum NIDSCommands {
         #[arg(short = 'd', long = "direction", default_value = "asc")]
         direction: SortOrderInput,
     },
+    /// Check the watched creators for new purchasable or purchased issues
+    Watch,
+    /// Add a creator to the watch list
+    WatchCreator {
+        /// Creator ID to watch
+        creator_id: u32,
+
+        /// Remove the creator from the watch list instead
+        #[arg(short = 'r', long = "remove", default_value_t = false)]
+        remove: bool,
+    },
 }
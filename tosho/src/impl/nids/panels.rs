@@ -0,0 +1,323 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use color_print::cformat;
+use image::{DynamicImage, GenericImage, Rgb, RgbImage, codecs::jpeg::JpegEncoder};
+use tosho_nids::models::reader::ReaderFrame;
+
+use crate::{
+    cli::ExitCode,
+    r#impl::nids::download::{FRAMES_FILE, FrameWithPage},
+};
+
+/// The JPEG quality of the exported panels
+const PANEL_QUALITY: u8 = 90;
+/// The maximum height of a single vertical strip image before it's split
+const STRIP_MAX_HEIGHT: u32 = 16_384;
+/// The gap between panels in the vertical strip
+const STRIP_GAP: u32 = 16;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) enum PanelExportFormat {
+    /// Each panel as a separate image
+    #[default]
+    Images,
+    /// Each panel packed into a CBZ file
+    Cbz,
+    /// All the panels stacked into vertical-scroll strips
+    Strip,
+}
+
+impl ValueEnum for PanelExportFormat {
+    fn from_str(input: &str, ignore_case: bool) -> Result<Self, String> {
+        let input = if ignore_case {
+            input.to_lowercase()
+        } else {
+            input.to_string()
+        };
+        match input.as_str() {
+            "images" | "image" => Ok(Self::Images),
+            "cbz" => Ok(Self::Cbz),
+            "strip" | "webtoon" => Ok(Self::Strip),
+            _ => Err(format!("Invalid panel export format: {input}")),
+        }
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::Images => Some(clap::builder::PossibleValue::new("images")),
+            Self::Cbz => Some(clap::builder::PossibleValue::new("cbz")),
+            Self::Strip => Some(clap::builder::PossibleValue::new("strip").alias("webtoon")),
+        }
+    }
+
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Images, Self::Cbz, Self::Strip]
+    }
+}
+
+/// Convert the relative frame box into a pixel rectangle of `(x, y, width, height)`.
+///
+/// Returns `None` if the frame is outside the page or empty.
+fn frame_rect(frame: &ReaderFrame, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    let x = (frame.x().clamp(0.0, 1.0) * width as f64).round() as u32;
+    let y = (frame.y().clamp(0.0, 1.0) * height as f64).round() as u32;
+    let right = ((frame.x() + frame.width()).clamp(0.0, 1.0) * width as f64).round() as u32;
+    let bottom = ((frame.y() + frame.height()).clamp(0.0, 1.0) * height as f64).round() as u32;
+
+    if right <= x || bottom <= y {
+        None
+    } else {
+        Some((x, y, right - x, bottom - y))
+    }
+}
+
+/// Load the frames in reading order, grouped by the page filename.
+fn load_frames(issue_dir: &Path) -> color_eyre::eyre::Result<Vec<(String, Vec<ReaderFrame>)>> {
+    let content = std::fs::read_to_string(issue_dir.join(FRAMES_FILE))?;
+    let mut frames: Vec<FrameWithPage> = serde_json::from_str(&content)?;
+    frames.sort_by(|a, b| {
+        a.filename
+            .cmp(&b.filename)
+            .then(a.frame.index().cmp(&b.frame.index()))
+    });
+
+    let mut grouped: Vec<(String, Vec<ReaderFrame>)> = vec![];
+    for item in frames {
+        match grouped.last_mut() {
+            Some((filename, page_frames)) if *filename == item.filename => {
+                page_frames.push(item.frame)
+            }
+            _ => grouped.push((item.filename, vec![item.frame])),
+        }
+    }
+
+    Ok(grouped)
+}
+
+fn encode_jpeg(image: &RgbImage) -> color_eyre::eyre::Result<Vec<u8>> {
+    let mut data = vec![];
+    JpegEncoder::new_with_quality(&mut data, PANEL_QUALITY).encode_image(image)?;
+    Ok(data)
+}
+
+/// Stack the panels vertically into strips with the same width.
+fn make_strips(panels: &[RgbImage], width: u32) -> color_eyre::eyre::Result<Vec<RgbImage>> {
+    let resized: Vec<RgbImage> = panels
+        .iter()
+        .map(|panel| {
+            if panel.width() == width {
+                return panel.clone();
+            }
+            let height = (panel.height() as f64 * width as f64 / panel.width() as f64)
+                .round()
+                .max(1.0) as u32;
+            image::imageops::resize(panel, width, height, image::imageops::FilterType::Lanczos3)
+        })
+        .collect();
+
+    // group the panels so each strip stays under the maximum height
+    let mut groups: Vec<Vec<&RgbImage>> = vec![];
+    let mut current_height = 0;
+    for panel in resized.iter() {
+        let needed = panel.height() + STRIP_GAP;
+        match groups.last_mut() {
+            Some(group) if current_height + needed <= STRIP_MAX_HEIGHT => {
+                group.push(panel);
+                current_height += needed;
+            }
+            _ => {
+                groups.push(vec![panel]);
+                current_height = needed;
+            }
+        }
+    }
+
+    let mut strips = vec![];
+    for group in groups {
+        let height: u32 = group.iter().map(|panel| panel.height() + STRIP_GAP).sum();
+        let mut strip = RgbImage::from_pixel(width, height - STRIP_GAP, Rgb([255, 255, 255]));
+        let mut offset = 0;
+        for panel in group {
+            strip.copy_from(panel, 0, offset)?;
+            offset += panel.height() + STRIP_GAP;
+        }
+        strips.push(strip);
+    }
+
+    Ok(strips)
+}
+
+fn write_cbz(output: &Path, images: &[(String, Vec<u8>)]) -> color_eyre::eyre::Result<()> {
+    let file = std::fs::File::create(output)?;
+    let mut writer = zip::ZipWriter::new(file);
+    // the panels are already compressed
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, data) in images {
+        writer.start_file(name.as_str(), options)?;
+        writer.write_all(data)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+pub(crate) fn nids_export_panels(
+    issue_dir: &Path,
+    format: PanelExportFormat,
+    strip_width: u32,
+    output: Option<PathBuf>,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let pages = match load_frames(issue_dir) {
+        Ok(pages) => pages,
+        Err(err) => {
+            console.error(cformat!(
+                "Failed to read <s>{}</> in <s>{}</>: {}",
+                FRAMES_FILE,
+                issue_dir.display(),
+                err
+            ));
+            return 1;
+        }
+    };
+
+    if pages.is_empty() {
+        console.warn("No panels found in the reading frames");
+        return 0;
+    }
+
+    let total_frames: usize = pages.iter().map(|(_, frames)| frames.len()).sum();
+    console.info(cformat!(
+        "Cropping <m,s>{}</> panels from <m,s>{}</> pages...",
+        total_frames,
+        pages.len()
+    ));
+
+    let progress = console.make_progress(total_frames as u64, Some("Cropping"));
+    let mut panels: Vec<RgbImage> = vec![];
+    for (filename, frames) in pages.iter() {
+        let page = match image::open(issue_dir.join(filename)) {
+            Ok(page) => page,
+            Err(err) => {
+                console.warn(format!("   Failed to open page {filename}: {err}"));
+                progress.inc(frames.len() as u64);
+                continue;
+            }
+        };
+
+        for frame in frames {
+            if let Some((x, y, width, height)) = frame_rect(frame, page.width(), page.height()) {
+                let panel: DynamicImage = page.crop_imm(x, y, width, height);
+                panels.push(panel.to_rgb8());
+            }
+            progress.inc(1);
+        }
+    }
+    progress.finish_with_message("Cropped");
+
+    let result = match format {
+        PanelExportFormat::Images | PanelExportFormat::Cbz => panels
+            .iter()
+            .enumerate()
+            .map(|(idx, panel)| Ok((format!("panel_{:04}.jpg", idx + 1), encode_jpeg(panel)?)))
+            .collect::<color_eyre::eyre::Result<Vec<(String, Vec<u8>)>>>(),
+        PanelExportFormat::Strip => make_strips(&panels, strip_width).and_then(|strips| {
+            strips
+                .iter()
+                .enumerate()
+                .map(|(idx, strip)| Ok((format!("strip_{:03}.jpg", idx + 1), encode_jpeg(strip)?)))
+                .collect()
+        }),
+    };
+
+    let images = match result {
+        Ok(images) => images,
+        Err(err) => {
+            console.error(format!("Failed to encode the panels: {err}"));
+            return 1;
+        }
+    };
+
+    let written = match format {
+        PanelExportFormat::Cbz => {
+            let output = output.unwrap_or_else(|| issue_dir.join("panels.cbz"));
+            write_cbz(&output, &images).map(|_| output)
+        }
+        _ => {
+            let output = output.unwrap_or_else(|| issue_dir.join("panels"));
+            std::fs::create_dir_all(&output)
+                .and_then(|_| {
+                    images
+                        .iter()
+                        .try_for_each(|(name, data)| std::fs::write(output.join(name), data))
+                })
+                .map(|_| output)
+                .map_err(Into::into)
+        }
+    };
+
+    match written {
+        Ok(output) => {
+            console.info(cformat!(
+                "Exported <m,s>{}</> panels to <s>{}</>",
+                panels.len(),
+                output.display()
+            ));
+            0
+        }
+        Err(err) => {
+            console.error(format!("Failed to write the panels: {err}"));
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_frame(x: f64, y: f64, width: f64, height: f64) -> ReaderFrame {
+        serde_json::from_value(serde_json::json!({
+            "uuid": "frame",
+            "index": 0,
+            "x": x,
+            "y": y,
+            "width": width,
+            "height": height,
+            "opacity": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_frame_rect() {
+        let frame = make_frame(0.5, 0.25, 0.5, 0.5);
+        assert_eq!(frame_rect(&frame, 200, 400), Some((100, 100, 100, 200)));
+
+        // clamped to the page
+        let frame = make_frame(0.75, 0.75, 0.5, 0.5);
+        assert_eq!(frame_rect(&frame, 100, 100), Some((75, 75, 25, 25)));
+
+        let frame = make_frame(1.0, 0.0, 0.5, 0.5);
+        assert_eq!(frame_rect(&frame, 100, 100), None);
+    }
+
+    #[test]
+    fn test_make_strips() {
+        let panels = vec![
+            RgbImage::new(100, 50),
+            RgbImage::new(200, 200),
+            RgbImage::new(100, 17_000),
+        ];
+
+        let strips = make_strips(&panels, 100).unwrap();
+        // the last panel is too tall to be stacked with the others
+        assert_eq!(strips.len(), 2);
+        assert_eq!(strips[0].width(), 100);
+        assert_eq!(strips[0].height(), 50 + STRIP_GAP + 100);
+    }
+}
@@ -0,0 +1,621 @@
+//! Named groups of accounts (pools) that can be used together when downloading.
+//!
+//! The pools are saved per source in `account_pools.json`, the download side
+//! decides which account unlocks each chapter and keeps a ledger of it.
+
+use std::{collections::BTreeMap, path::Path};
+
+use color_print::cformat;
+
+use crate::{
+    cli::ExitCode,
+    config::{ConfigImpl, get_config, get_user_path},
+};
+
+use super::{
+    Implementations,
+    common::{INTERRUPTED_EXIT_CODE, check_chapter_folder_existence, check_downloaded_pages},
+    models::{IdDump, MangaDetailDump},
+};
+
+/// The saved pools, keyed by the source prefix then the pool name
+type PoolMap = BTreeMap<String, BTreeMap<String, Vec<String>>>;
+
+fn pools_path() -> std::path::PathBuf {
+    get_user_path().join("account_pools.json")
+}
+
+fn load_pools() -> PoolMap {
+    std::fs::read(pools_path())
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_pools(pools: &PoolMap) -> std::io::Result<()> {
+    let user_path = get_user_path();
+    if !user_path.exists() {
+        std::fs::create_dir_all(&user_path)?;
+    }
+
+    let data = serde_json::to_vec_pretty(pools)?;
+    std::fs::write(pools_path(), data)
+}
+
+fn pool_key(implementation: &Implementations) -> &'static str {
+    match implementation {
+        Implementations::Kmkc => super::kmkc::config::PREFIX,
+        Implementations::Musq => super::musq::config::PREFIX,
+        Implementations::Amap => super::amap::config::PREFIX,
+        Implementations::Sjv => super::sjv::config::PREFIX,
+        Implementations::Rbean => super::rbean::config::PREFIX,
+        Implementations::Mplus => super::mplus::config::PREFIX,
+        Implementations::Nids => super::nids::config::PREFIX,
+    }
+}
+
+/// Load every account in the pool, in the saved order.
+///
+/// Accounts that no longer exist are skipped with a warning.
+pub(crate) fn load_pool_accounts(
+    implementation: Implementations,
+    name: &str,
+    console: &crate::term::Terminal,
+) -> Vec<ConfigImpl> {
+    let pools = load_pools();
+    let Some(account_ids) = pools
+        .get(pool_key(&implementation))
+        .and_then(|source| source.get(name))
+    else {
+        console.error(cformat!("Account pool <m,s>{}</> not found!", name));
+        return vec![];
+    };
+
+    account_ids
+        .iter()
+        .filter_map(|account_id| {
+            let config = get_config(account_id, &implementation, None);
+            if config.is_none() {
+                console.warn(cformat!(
+                    "Account <m,s>{}</> in pool <m,s>{}</> not found, skipping",
+                    account_id,
+                    name
+                ));
+            }
+            config
+        })
+        .collect()
+}
+
+/// Show, create/update, or remove an account pool.
+pub(crate) fn account_pool_manage(
+    implementation: Implementations,
+    name: Option<String>,
+    account_ids: Option<Vec<String>>,
+    remove: bool,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    let mut pools = load_pools();
+    let key = pool_key(&implementation);
+
+    let Some(name) = name else {
+        let source_pools = pools.get(key).cloned().unwrap_or_default();
+        if source_pools.is_empty() {
+            console.warn("No account pools found!");
+            return 0;
+        }
+
+        console.info(cformat!(
+            "Account pools (<m,s>{}</> pools):",
+            source_pools.len()
+        ));
+        for (name, account_ids) in source_pools {
+            console.info(cformat!("  <s>{}</>: {}", name, account_ids.join(", ")));
+        }
+        return 0;
+    };
+
+    let source_pools = pools.entry(key.to_string()).or_default();
+    if remove {
+        if source_pools.remove(&name).is_none() {
+            console.warn(cformat!("Account pool <m,s>{}</> not found!", name));
+            return 1;
+        }
+    } else if let Some(account_ids) = account_ids {
+        let missing: Vec<&String> = account_ids
+            .iter()
+            .filter(|account_id| get_config(account_id, &implementation, None).is_none())
+            .collect();
+        if !missing.is_empty() {
+            console.error(format!(
+                "Unknown account IDs: {}",
+                missing
+                    .iter()
+                    .map(|id| id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            return 1;
+        }
+
+        let mut deduped: Vec<String> = vec![];
+        for account_id in account_ids {
+            if !deduped.contains(&account_id) {
+                deduped.push(account_id);
+            }
+        }
+        source_pools.insert(name.clone(), deduped);
+    } else {
+        return match source_pools.get(&name) {
+            Some(account_ids) => {
+                console.info(cformat!(
+                    "Account pool <m,s>{}</>: {}",
+                    name,
+                    account_ids.join(", ")
+                ));
+                0
+            }
+            None => {
+                console.warn(cformat!("Account pool <m,s>{}</> not found!", name));
+                1
+            }
+        };
+    }
+
+    match save_pools(&pools) {
+        Ok(_) => {
+            if remove {
+                console.info(cformat!("Removed account pool <m,s>{}</>", name));
+            } else {
+                console.info(cformat!("Saved account pool <m,s>{}</>", name));
+            }
+            0
+        }
+        Err(err) => {
+            console.error(format!("Failed to save the account pools: {err}"));
+            1
+        }
+    }
+}
+
+/// How an account is able to unlock a chapter, ordered by preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PoolPayment {
+    /// The chapter is free or already owned by the account
+    Owned,
+    /// The chapter can be unlocked with free/event currency or a ticket
+    Free,
+    /// The chapter need paid currency to be unlocked
+    Paid,
+}
+
+/// Pick the account that should unlock a chapter.
+///
+/// The account with the most preferred [`PoolPayment`] is used, ties are broken by
+/// the order of the accounts in the pool.
+pub(crate) fn pick_pool_account(payments: &[Option<PoolPayment>]) -> Option<(usize, PoolPayment)> {
+    payments
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, payment)| payment.map(|payment| (idx, payment)))
+        .min_by_key(|(idx, payment)| (*payment, *idx))
+}
+
+/// Which chapters the user selected to download, used to filter the title chapters.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PoolRange {
+    pub(crate) chapter_ids: Vec<usize>,
+    pub(crate) start_from: Option<u64>,
+    pub(crate) end_at: Option<u64>,
+}
+
+impl PoolRange {
+    pub(crate) fn contains(&self, chapter_id: u64) -> bool {
+        let selected =
+            self.chapter_ids.is_empty() || self.chapter_ids.contains(&(chapter_id as usize));
+        let after_start = self.start_from.is_none_or(|start| chapter_id >= start);
+        let before_end = self.end_at.is_none_or(|end| chapter_id <= end);
+
+        selected && after_start && before_end
+    }
+}
+
+/// A chapter that is actually unlocked by the downloader.
+#[derive(Debug, Clone)]
+struct PoolReceipt {
+    chapter: String,
+    chapter_id: u64,
+    payment: PoolPayment,
+    cost: String,
+    downloaded: bool,
+}
+
+/// The chapters that the downloader unlocked and saved, reported back to the pool.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PoolReceipts {
+    entries: Vec<PoolReceipt>,
+}
+
+impl PoolReceipts {
+    /// Record a chapter that is owned or has been purchased.
+    pub(crate) fn unlocked(
+        &mut self,
+        chapter_id: u64,
+        chapter: &str,
+        payment: PoolPayment,
+        cost: impl Into<String>,
+    ) {
+        self.entries.push(PoolReceipt {
+            chapter: chapter.to_string(),
+            chapter_id,
+            payment,
+            cost: cost.into(),
+            downloaded: false,
+        });
+    }
+
+    /// Mark an unlocked chapter as saved to the disk.
+    pub(crate) fn downloaded(&mut self, chapter_id: u64) {
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.chapter_id == chapter_id)
+        {
+            entry.downloaded = true;
+        }
+    }
+
+    fn is_downloaded(&self, chapter_id: u64) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.chapter_id == chapter_id && entry.downloaded)
+    }
+}
+
+/// A summary of which account unlocked which chapters.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PoolLedger {
+    accounts: BTreeMap<String, Vec<PoolReceipt>>,
+    skipped: Vec<(String, u64)>,
+    failed: Vec<(String, u64)>,
+}
+
+impl PoolLedger {
+    /// Record what the downloader reported for the chapters assigned to the account.
+    pub(crate) fn record(
+        &mut self,
+        account_id: &str,
+        assigned: &[(u64, String)],
+        receipts: PoolReceipts,
+    ) {
+        for (chapter_id, chapter) in assigned {
+            if !receipts.is_downloaded(*chapter_id) {
+                self.failed.push((chapter.clone(), *chapter_id));
+            }
+        }
+        if !receipts.entries.is_empty() {
+            self.accounts
+                .entry(account_id.to_string())
+                .or_default()
+                .extend(receipts.entries);
+        }
+    }
+
+    pub(crate) fn skip(&mut self, chapter: &str, chapter_id: u64) {
+        self.skipped.push((chapter.to_string(), chapter_id));
+    }
+
+    pub(crate) fn print(&self, console: &crate::term::Terminal) {
+        console.info("Account pool summary:");
+        if self.accounts.is_empty() {
+            console.info("  No chapters were unlocked by any account");
+        }
+        for (account_id, entries) in self.accounts.iter() {
+            let owned = entries
+                .iter()
+                .filter(|entry| entry.payment == PoolPayment::Owned)
+                .count();
+            console.info(cformat!(
+                "  <m,s>{}</>: <s>{}</> owned, <s>{}</> purchased",
+                account_id,
+                owned,
+                entries.len() - owned
+            ));
+            for entry in entries
+                .iter()
+                .filter(|entry| entry.payment != PoolPayment::Owned)
+            {
+                let status = if entry.downloaded {
+                    ""
+                } else {
+                    ", not downloaded"
+                };
+                console.info(cformat!(
+                    "   - <s>{}</> ({}) for {}{}",
+                    entry.chapter,
+                    entry.chapter_id,
+                    entry.cost,
+                    status
+                ));
+            }
+        }
+        if !self.failed.is_empty() {
+            console.warn(format!(
+                "  {} chapters failed to be unlocked or downloaded:",
+                self.failed.len()
+            ));
+            for (chapter, chapter_id) in self.failed.iter() {
+                console.warn(format!("   - {chapter} ({chapter_id})"));
+            }
+        }
+        if !self.skipped.is_empty() {
+            console.warn(format!(
+                "  {} chapters can't be unlocked by any account:",
+                self.skipped.len()
+            ));
+            for (chapter, chapter_id) in self.skipped.iter() {
+                console.warn(format!("   - {chapter} ({chapter_id})"));
+            }
+        }
+    }
+}
+
+/// A source that can download a title with every account in a pool.
+/// Check if the chapter has been downloaded before assigning it to an account.
+///
+/// The pages are checked against the page count recorded in the title `_info.json`,
+/// so incomplete chapters are downloaded again. A chapter without a recorded page
+/// count has never been downloaded completely.
+pub(super) fn is_chapter_downloaded(
+    title_dir: &Path,
+    chapter_dir: &Path,
+    chapter_id: u64,
+    only_check_folder: bool,
+) -> bool {
+    if only_check_folder {
+        return check_chapter_folder_existence(chapter_dir);
+    }
+
+    let expected = std::fs::read_to_string(title_dir.join("_info.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<MangaDetailDump>(&content).ok())
+        .and_then(|info| {
+            let chapter_id = IdDump::from(chapter_id);
+            info.chapters
+                .iter()
+                .find(|ch| ch.id == chapter_id)
+                .and_then(|ch| ch.pages)
+        });
+
+    match expected {
+        Some(pages) => {
+            let page_stems: Vec<String> = (0..pages).map(|idx| format!("p{idx:03}")).collect();
+            check_downloaded_pages(chapter_dir, &page_stems)
+        }
+        None => false,
+    }
+}
+
+pub(crate) trait PoolSource {
+    /// The account config of the source
+    type Account;
+    /// An account with the title information and balance as seen by it
+    type Member;
+
+    const IMPLEMENTATION: Implementations;
+
+    fn title_id(&self) -> u64;
+
+    /// The chapters selected by the user
+    fn range(&self) -> PoolRange;
+
+    fn account(config: ConfigImpl) -> Option<Self::Account>;
+
+    fn account_id(account: &Self::Account) -> &str;
+
+    /// Fetch the title and balance with the account.
+    async fn fetch_member(
+        &self,
+        account: Self::Account,
+        proxy: Option<reqwest::Proxy>,
+    ) -> color_eyre::Result<Self::Member>;
+
+    /// The balance of the member, shown after fetching it
+    fn balance(&self, member: &Self::Member) -> String;
+
+    /// Every chapter of the title as `(ID, title)`
+    fn chapters(&self, member: &Self::Member) -> Vec<(u64, String)>;
+
+    fn is_downloaded(&self, chapter_id: u64) -> bool;
+
+    /// How the member is able to unlock the chapter with the remaining balance
+    fn payment(&self, member: &Self::Member, chapter_id: u64) -> Option<PoolPayment>;
+
+    /// Set aside the balance needed to unlock the chapter.
+    fn reserve(&self, member: &mut Self::Member, chapter_id: u64);
+
+    /// Download the assigned chapters, reporting what is actually unlocked.
+    async fn download(
+        &self,
+        member: &Self::Member,
+        chapter_ids: Vec<usize>,
+        receipts: &mut PoolReceipts,
+        console: &mut crate::term::Terminal,
+    ) -> ExitCode;
+
+    /// Called for every member after all downloads are done.
+    fn finish(&self, _member: &Self::Member) {}
+}
+
+/// Automatically download the title using every account in the pool.
+///
+/// Each chapter is unlocked by the account that already owns it, then the one that
+/// can use free currency or a ticket, then the one that can pay with paid currency.
+pub(crate) async fn pool_download<S: PoolSource>(
+    source: &S,
+    pool: &str,
+    proxy: Option<reqwest::Proxy>,
+    console: &mut crate::term::Terminal,
+) -> ExitCode {
+    let accounts = load_pool_accounts(S::IMPLEMENTATION, pool, console)
+        .into_iter()
+        .filter_map(S::account);
+
+    let mut members: Vec<(String, S::Member)> = vec![];
+    for account in accounts {
+        let account_id = S::account_id(&account).to_string();
+        console.info(cformat!(
+            "Fetching title <m,s>{}</> with account <m,s>{}</>...",
+            source.title_id(),
+            account_id
+        ));
+
+        match source.fetch_member(account, proxy.clone()).await {
+            Ok(member) => {
+                console.info(format!("  Balance: {}", source.balance(&member)));
+                members.push((account_id, member));
+            }
+            Err(err) => console.error(format!("  Failed to fetch title: {err}")),
+        }
+    }
+
+    let Some((_, first)) = members.first() else {
+        console.error("No accounts in the pool are usable, aborting");
+        return 1;
+    };
+
+    let range = source.range();
+    let chapters: Vec<(u64, String)> = source
+        .chapters(first)
+        .into_iter()
+        .filter(|(chapter_id, _)| range.contains(*chapter_id))
+        .filter(|(chapter_id, _)| !source.is_downloaded(*chapter_id))
+        .collect();
+
+    if chapters.is_empty() {
+        console.info("No new chapters to download");
+        return 0;
+    }
+
+    let mut ledger = PoolLedger::default();
+    let mut assigned: Vec<Vec<(u64, String)>> = vec![vec![]; members.len()];
+    for (chapter_id, chapter) in chapters {
+        let payments: Vec<Option<PoolPayment>> = members
+            .iter()
+            .map(|(_, member)| source.payment(member, chapter_id))
+            .collect();
+
+        let Some((idx, _)) = pick_pool_account(&payments) else {
+            ledger.skip(&chapter, chapter_id);
+            continue;
+        };
+
+        source.reserve(&mut members[idx].1, chapter_id);
+        assigned[idx].push((chapter_id, chapter));
+    }
+
+    let mut exit_code = 0;
+    for ((account_id, member), assigned) in members.iter().zip(assigned.iter()) {
+        if assigned.is_empty() {
+            continue;
+        }
+
+        console.info(cformat!(
+            "Downloading <s>{}</> chapters with account <m,s>{}</>...",
+            assigned.len(),
+            account_id
+        ));
+        let chapter_ids = assigned
+            .iter()
+            .map(|(chapter_id, _)| *chapter_id as usize)
+            .collect();
+        let mut receipts = PoolReceipts::default();
+        let code = source
+            .download(member, chapter_ids, &mut receipts, console)
+            .await;
+        if code != 0 {
+            exit_code = code;
+        }
+        ledger.record(account_id, assigned, receipts);
+        if code == INTERRUPTED_EXIT_CODE {
+            break;
+        }
+    }
+
+    for (_, member) in members.iter() {
+        source.finish(member);
+    }
+
+    ledger.print(console);
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_pool_account() {
+        use PoolPayment::*;
+
+        assert_eq!(pick_pool_account(&[None, None]), None);
+        assert_eq!(
+            pick_pool_account(&[Some(Paid), Some(Free), Some(Free)]),
+            Some((1, Free))
+        );
+        assert_eq!(
+            pick_pool_account(&[Some(Paid), None, Some(Owned)]),
+            Some((2, Owned))
+        );
+        assert_eq!(pick_pool_account(&[None, Some(Paid)]), Some((1, Paid)));
+    }
+
+    #[test]
+    fn test_pool_range() {
+        let range = PoolRange {
+            start_from: Some(10),
+            end_at: Some(20),
+            ..Default::default()
+        };
+        assert!(range.contains(10));
+        assert!(range.contains(20));
+        assert!(!range.contains(21));
+
+        let range = PoolRange {
+            chapter_ids: vec![5, 15],
+            start_from: Some(10),
+            ..Default::default()
+        };
+        assert!(range.contains(15));
+        assert!(!range.contains(5));
+    }
+
+    #[test]
+    fn test_is_chapter_downloaded() {
+        let dir = std::env::temp_dir().join("tosho-pool-downloaded-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let chapter_dir = dir.join("10");
+        std::fs::create_dir_all(&chapter_dir).unwrap();
+        std::fs::write(chapter_dir.join("p000.avif"), b"page").unwrap();
+        std::fs::write(
+            dir.join("_info.json"),
+            serde_json::json!({
+                "titleName": "Title",
+                "authorName": "Author",
+                "chapters": [{"id": 10, "mainName": "Chapter 10", "pages": 2}],
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        // the chapter is incomplete, unless only the folder is checked
+        assert!(!is_chapter_downloaded(&dir, &chapter_dir, 10, false));
+        assert!(is_chapter_downloaded(&dir, &chapter_dir, 10, true));
+
+        std::fs::write(chapter_dir.join("p001.avif"), b"page").unwrap();
+        assert!(is_chapter_downloaded(&dir, &chapter_dir, 10, false));
+        // no recorded page count
+        assert!(!is_chapter_downloaded(&dir, &dir.join("11"), 11, false));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
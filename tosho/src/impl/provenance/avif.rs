@@ -0,0 +1,747 @@
+//! Embed the metadata into AVIF/HEIF as `Exif` and `mime` (XMP) items.
+//!
+//! The `meta` box is rebuilt with the new items added to `iinf`, `iloc` and `iref`,
+//! while the metadata payload is appended into a new `mdat` box at the end of the file.
+//! Since the `meta` box is growing, the file offsets in `iloc` that point after it are shifted.
+
+use color_eyre::eyre::{Result, bail, eyre};
+
+/// A parsed ISO-BMFF box
+struct BmffBox {
+    kind: [u8; 4],
+    /// Start of the box, including the header
+    start: usize,
+    /// Start of the content
+    content: usize,
+    /// End of the box
+    end: usize,
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16> {
+    match data.get(pos..pos + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes(bytes.try_into()?)),
+        None => bail!("unexpected end of data at {pos}"),
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    match data.get(pos..pos + 4) {
+        Some(bytes) => Ok(u32::from_be_bytes(bytes.try_into()?)),
+        None => bail!("unexpected end of data at {pos}"),
+    }
+}
+
+/// Read the version of a full box content
+fn read_version(data: &[u8]) -> Result<u8> {
+    data.first()
+        .copied()
+        .ok_or_else(|| eyre!("empty full box content"))
+}
+
+/// Read a big-endian number with variable size (0, 4 or 8 bytes)
+fn read_sized(data: &[u8], pos: usize, size: u8) -> Result<u64> {
+    match size {
+        0 => Ok(0),
+        4 => Ok(read_u32(data, pos)? as u64),
+        8 => match data.get(pos..pos + 8) {
+            Some(bytes) => Ok(u64::from_be_bytes(bytes.try_into()?)),
+            None => bail!("unexpected end of data at {pos}"),
+        },
+        _ => bail!("unsupported field size {size}"),
+    }
+}
+
+fn write_sized(output: &mut Vec<u8>, value: u64, size: u8) -> Result<()> {
+    match size {
+        0 if value == 0 => {}
+        4 if value <= u32::MAX as u64 => output.extend_from_slice(&(value as u32).to_be_bytes()),
+        8 => output.extend_from_slice(&value.to_be_bytes()),
+        _ => bail!("value {value} does not fit into {size} bytes"),
+    }
+    Ok(())
+}
+
+fn parse_boxes(data: &[u8], start: usize, end: usize) -> Result<Vec<BmffBox>> {
+    let mut boxes = vec![];
+    let mut pos = start;
+    while pos < end {
+        let size = read_u32(data, pos)? as usize;
+        let kind: [u8; 4] = match data.get(pos + 4..pos + 8) {
+            Some(kind) => kind.try_into()?,
+            None => bail!("unexpected end of box header at {pos}"),
+        };
+
+        let (content, box_end) = match size {
+            // extends to the end
+            0 => (pos + 8, end),
+            1 => {
+                let large_size = read_sized(data, pos + 8, 8)? as usize;
+                match pos.checked_add(large_size) {
+                    Some(box_end) => (pos + 16, box_end),
+                    None => bail!("invalid box size at {pos}"),
+                }
+            }
+            _ => (pos + 8, pos + size),
+        };
+
+        if box_end > end || box_end < content {
+            bail!("invalid box size at {pos}");
+        }
+
+        boxes.push(BmffBox {
+            kind,
+            start: pos,
+            content,
+            end: box_end,
+        });
+        pos = box_end;
+    }
+
+    Ok(boxes)
+}
+
+fn make_box(kind: &[u8; 4], payload: &[u8]) -> Result<Vec<u8>> {
+    let size = payload.len() + 8;
+    if size > u32::MAX as usize {
+        bail!("box is too large");
+    }
+
+    let mut output = Vec::with_capacity(size);
+    output.extend_from_slice(&(size as u32).to_be_bytes());
+    output.extend_from_slice(kind);
+    output.extend_from_slice(payload);
+    Ok(output)
+}
+
+fn make_full_box(kind: &[u8; 4], version: u8, flags: u32, payload: &[u8]) -> Result<Vec<u8>> {
+    let mut content = Vec::with_capacity(payload.len() + 4);
+    content.push(version);
+    content.extend_from_slice(&flags.to_be_bytes()[1..]);
+    content.extend_from_slice(payload);
+    make_box(kind, &content)
+}
+
+struct IlocExtent {
+    index: u64,
+    offset: u64,
+    length: u64,
+}
+
+struct IlocItem {
+    id: u32,
+    construction_method: u8,
+    data_reference_index: u16,
+    base_offset: u64,
+    extents: Vec<IlocExtent>,
+}
+
+struct Iloc {
+    version: u8,
+    offset_size: u8,
+    length_size: u8,
+    base_offset_size: u8,
+    index_size: u8,
+    items: Vec<IlocItem>,
+}
+
+impl Iloc {
+    fn parse(data: &[u8]) -> Result<Self> {
+        let version = read_version(data)?;
+        if version > 2 {
+            bail!("unsupported iloc version {version}");
+        }
+
+        let mut pos = 4;
+        let sizes = read_u16(data, pos)?;
+        pos += 2;
+        let offset_size = (sizes >> 12) as u8;
+        let length_size = ((sizes >> 8) & 0xF) as u8;
+        let base_offset_size = ((sizes >> 4) & 0xF) as u8;
+        let index_size = if version > 0 { (sizes & 0xF) as u8 } else { 0 };
+
+        let item_count = if version < 2 {
+            let count = read_u16(data, pos)? as u32;
+            pos += 2;
+            count
+        } else {
+            let count = read_u32(data, pos)?;
+            pos += 4;
+            count
+        };
+
+        let mut items = vec![];
+        for _ in 0..item_count {
+            let id = if version < 2 {
+                let id = read_u16(data, pos)? as u32;
+                pos += 2;
+                id
+            } else {
+                let id = read_u32(data, pos)?;
+                pos += 4;
+                id
+            };
+
+            let construction_method = if version > 0 {
+                let method = (read_u16(data, pos)? & 0xF) as u8;
+                pos += 2;
+                method
+            } else {
+                0
+            };
+
+            let data_reference_index = read_u16(data, pos)?;
+            pos += 2;
+            let base_offset = read_sized(data, pos, base_offset_size)?;
+            pos += base_offset_size as usize;
+
+            let extent_count = read_u16(data, pos)?;
+            pos += 2;
+            let mut extents = vec![];
+            for _ in 0..extent_count {
+                let index = read_sized(data, pos, index_size)?;
+                pos += index_size as usize;
+                let offset = read_sized(data, pos, offset_size)?;
+                pos += offset_size as usize;
+                let length = read_sized(data, pos, length_size)?;
+                pos += length_size as usize;
+
+                extents.push(IlocExtent {
+                    index,
+                    offset,
+                    length,
+                });
+            }
+
+            items.push(IlocItem {
+                id,
+                construction_method,
+                data_reference_index,
+                base_offset,
+                extents,
+            });
+        }
+
+        Ok(Self {
+            version,
+            offset_size,
+            length_size,
+            base_offset_size,
+            index_size,
+            items,
+        })
+    }
+
+    fn to_box(&self) -> Result<Vec<u8>> {
+        let mut payload = vec![];
+        let sizes = ((self.offset_size as u16) << 12)
+            | ((self.length_size as u16) << 8)
+            | ((self.base_offset_size as u16) << 4)
+            | (self.index_size as u16);
+        payload.extend_from_slice(&sizes.to_be_bytes());
+
+        if self.version < 2 {
+            payload.extend_from_slice(&(self.items.len() as u16).to_be_bytes());
+        } else {
+            payload.extend_from_slice(&(self.items.len() as u32).to_be_bytes());
+        }
+
+        for item in self.items.iter() {
+            if self.version < 2 {
+                payload.extend_from_slice(&(item.id as u16).to_be_bytes());
+            } else {
+                payload.extend_from_slice(&item.id.to_be_bytes());
+            }
+            if self.version > 0 {
+                payload.extend_from_slice(&(item.construction_method as u16).to_be_bytes());
+            }
+            payload.extend_from_slice(&item.data_reference_index.to_be_bytes());
+            write_sized(&mut payload, item.base_offset, self.base_offset_size)?;
+            payload.extend_from_slice(&(item.extents.len() as u16).to_be_bytes());
+            for extent in item.extents.iter() {
+                write_sized(&mut payload, extent.index, self.index_size)?;
+                write_sized(&mut payload, extent.offset, self.offset_size)?;
+                write_sized(&mut payload, extent.length, self.length_size)?;
+            }
+        }
+
+        make_full_box(b"iloc", self.version, 0, &payload)
+    }
+
+    /// Shift every file offset that is located after `after` by `delta` bytes.
+    fn shift_offsets(&mut self, after: u64, delta: u64) -> Result<()> {
+        let uses_base_offset = self.base_offset_size > 0;
+        for item in self.items.iter_mut() {
+            // only file offsets in the same file
+            if item.construction_method != 0 || item.data_reference_index != 0 {
+                continue;
+            }
+
+            if uses_base_offset {
+                let first_offset = item.extents.first().map(|ext| ext.offset).unwrap_or(0);
+                if item.base_offset.saturating_add(first_offset) >= after {
+                    item.base_offset = item
+                        .base_offset
+                        .checked_add(delta)
+                        .ok_or_else(|| eyre!("item offset overflow for item {}", item.id))?;
+                }
+            } else {
+                for extent in item.extents.iter_mut() {
+                    if extent.offset >= after {
+                        extent.offset = extent
+                            .offset
+                            .checked_add(delta)
+                            .ok_or_else(|| eyre!("item offset overflow for item {}", item.id))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the item ID and type of an `infe` box content
+fn parse_infe(data: &[u8]) -> Result<(u32, [u8; 4])> {
+    let version = read_version(data)?;
+    match version {
+        2 => {
+            let id = read_u16(data, 4)? as u32;
+            let kind: [u8; 4] = data.get(8..12).unwrap_or_default().try_into()?;
+            Ok((id, kind))
+        }
+        3 => {
+            let id = read_u32(data, 4)?;
+            let kind: [u8; 4] = data.get(10..14).unwrap_or_default().try_into()?;
+            Ok((id, kind))
+        }
+        _ => Ok((read_u16(data, 4)? as u32, [0; 4])),
+    }
+}
+
+fn make_infe(id: u32, kind: &[u8; 4], content_type: Option<&str>) -> Result<Vec<u8>> {
+    let mut payload = vec![];
+    payload.extend_from_slice(&(id as u16).to_be_bytes());
+    // item_protection_index
+    payload.extend_from_slice(&0u16.to_be_bytes());
+    payload.extend_from_slice(kind);
+    // empty item_name
+    payload.push(0);
+    if let Some(content_type) = content_type {
+        payload.extend_from_slice(content_type.as_bytes());
+        payload.push(0);
+    }
+
+    make_full_box(b"infe", 2, 0, &payload)
+}
+
+/// Rebuild the `meta` box with the new metadata items
+struct MetaBuilder<'a> {
+    data: &'a [u8],
+    meta: &'a BmffBox,
+    children: Vec<BmffBox>,
+    iloc: Iloc,
+    primary_id: u32,
+    exif_id: u32,
+    xmp_id: u32,
+}
+
+impl<'a> MetaBuilder<'a> {
+    fn new(data: &'a [u8], meta: &'a BmffBox) -> Result<Self> {
+        // meta is a full box
+        let children = parse_boxes(data, meta.content + 4, meta.end)?;
+
+        let mut iloc = None;
+        let mut primary_id = None;
+        let mut max_id = 0;
+        for child in children.iter() {
+            let content = &data[child.content..child.end];
+            match &child.kind {
+                b"iloc" => {
+                    let parsed = Iloc::parse(content)?;
+                    max_id = parsed
+                        .items
+                        .iter()
+                        .map(|item| item.id)
+                        .fold(max_id, u32::max);
+                    iloc = Some(parsed);
+                }
+                b"pitm" => {
+                    primary_id = Some(if read_version(content)? == 0 {
+                        read_u16(content, 4)? as u32
+                    } else {
+                        read_u32(content, 4)?
+                    });
+                }
+                b"iinf" => {
+                    let header = if read_version(content)? == 0 { 6 } else { 8 };
+                    for infe in parse_boxes(data, child.content + header, child.end)? {
+                        let (id, kind) = parse_infe(&data[infe.content..infe.end])?;
+                        if &kind == b"Exif" {
+                            bail!("image already has EXIF metadata");
+                        }
+                        max_id = max_id.max(id);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let (Some(iloc), Some(primary_id)) = (iloc, primary_id) else {
+            bail!("missing iloc or pitm box");
+        };
+        if max_id + 2 > u16::MAX as u32 || primary_id > u16::MAX as u32 {
+            bail!("item ID is too large");
+        }
+
+        Ok(Self {
+            data,
+            meta,
+            children,
+            iloc,
+            primary_id,
+            exif_id: max_id + 1,
+            xmp_id: max_id + 2,
+        })
+    }
+
+    fn build_iinf(&self, iinf: &BmffBox) -> Result<Vec<u8>> {
+        let content = &self.data[iinf.content..iinf.end];
+        let version = read_version(content)?;
+        let (count, header) = if version == 0 {
+            (read_u16(content, 4)? as u32, 6)
+        } else {
+            (read_u32(content, 4)?, 8)
+        };
+
+        let mut payload = vec![];
+        if version == 0 {
+            payload.extend_from_slice(&((count + 2) as u16).to_be_bytes());
+        } else {
+            payload.extend_from_slice(&(count + 2).to_be_bytes());
+        }
+        payload.extend_from_slice(&content[header..]);
+        payload.extend(make_infe(self.exif_id, b"Exif", None)?);
+        payload.extend(make_infe(
+            self.xmp_id,
+            b"mime",
+            Some("application/rdf+xml"),
+        )?);
+
+        make_full_box(b"iinf", version, 0, &payload)
+    }
+
+    /// Make the `cdsc` (content describes) reference from the metadata to the primary item
+    fn make_references(&self, large_id: bool) -> Vec<u8> {
+        let mut references = vec![];
+        for id in [self.exif_id, self.xmp_id] {
+            let mut payload = vec![];
+            if large_id {
+                payload.extend_from_slice(&id.to_be_bytes());
+                payload.extend_from_slice(&1u16.to_be_bytes());
+                payload.extend_from_slice(&self.primary_id.to_be_bytes());
+            } else {
+                payload.extend_from_slice(&(id as u16).to_be_bytes());
+                payload.extend_from_slice(&1u16.to_be_bytes());
+                payload.extend_from_slice(&(self.primary_id as u16).to_be_bytes());
+            }
+            // the reference box size is always small
+            references.extend(make_box(b"cdsc", &payload).unwrap_or_default());
+        }
+        references
+    }
+
+    fn build_iref(&self, iref: Option<&BmffBox>) -> Result<Vec<u8>> {
+        match iref {
+            Some(iref) => {
+                let content = &self.data[iref.content..iref.end];
+                let version = read_version(content)?;
+                let Some(references) = content.get(4..) else {
+                    bail!("invalid iref box");
+                };
+                let mut payload = references.to_vec();
+                payload.extend(self.make_references(version > 0));
+                make_full_box(b"iref", version, 0, &payload)
+            }
+            None => make_full_box(b"iref", 0, 0, &self.make_references(false)),
+        }
+    }
+
+    /// Build the new `meta` box, with the metadata located at `exif_offset` and `xmp_offset`.
+    fn build(&mut self, exif_offset: (u64, u64), xmp_offset: (u64, u64)) -> Result<Vec<u8>> {
+        for (id, (offset, length)) in [(self.exif_id, exif_offset), (self.xmp_id, xmp_offset)] {
+            let (base_offset, offset) = if self.iloc.base_offset_size > 0 {
+                (offset, 0)
+            } else {
+                (0, offset)
+            };
+
+            self.iloc.items.retain(|item| item.id != id);
+            self.iloc.items.push(IlocItem {
+                id,
+                construction_method: 0,
+                data_reference_index: 0,
+                base_offset,
+                extents: vec![IlocExtent {
+                    index: 0,
+                    offset,
+                    length,
+                }],
+            });
+        }
+
+        let mut payload = self.data[self.meta.content..self.meta.content + 4].to_vec();
+        let mut has_iref = false;
+        for child in self.children.iter() {
+            match &child.kind {
+                b"iloc" => payload.extend(self.iloc.to_box()?),
+                b"iinf" => payload.extend(self.build_iinf(child)?),
+                b"iref" => {
+                    has_iref = true;
+                    payload.extend(self.build_iref(Some(child))?);
+                }
+                _ => payload.extend_from_slice(&self.data[child.start..child.end]),
+            }
+        }
+        if !has_iref {
+            payload.extend(self.build_iref(None)?);
+        }
+
+        make_box(b"meta", &payload)
+    }
+}
+
+/// Embed the EXIF and XMP into an AVIF/HEIF image.
+///
+/// Image that already has EXIF metadata is not supported.
+pub(super) fn embed(data: &[u8], exif: &[u8], xmp: &str) -> Result<Vec<u8>> {
+    let boxes = parse_boxes(data, 0, data.len())?;
+    let Some(meta) = boxes.iter().find(|bmff| &bmff.kind == b"meta") else {
+        bail!("missing meta box");
+    };
+
+    let mut builder = MetaBuilder::new(data, meta)?;
+    // make sure the offsets can fit, the size of the new meta box depends on it
+    if builder.iloc.offset_size == 0 && builder.iloc.base_offset_size == 0 {
+        builder.iloc.offset_size = 4;
+    }
+    if builder.iloc.length_size == 0 {
+        builder.iloc.length_size = 4;
+    }
+    if data.len() + exif.len() + xmp.len() + 4096 > u32::MAX as usize {
+        builder.iloc.offset_size = builder.iloc.offset_size.max(8);
+        builder.iloc.base_offset_size = if builder.iloc.base_offset_size > 0 {
+            8
+        } else {
+            0
+        };
+    }
+
+    // first pass to get the new meta size
+    let new_meta_size = builder.build((0, 0), (0, 0))?.len();
+    let old_meta_size = meta.end - meta.start;
+    let delta = new_meta_size as i64 - old_meta_size as i64;
+    if delta < 0 {
+        bail!("unexpected meta box size");
+    }
+
+    // HEIF Exif item starts with the offset to the TIFF header
+    let mut exif_payload = Vec::with_capacity(exif.len() + 4);
+    exif_payload.extend_from_slice(&0u32.to_be_bytes());
+    exif_payload.extend_from_slice(exif);
+
+    let mut mdat_payload = exif_payload.clone();
+    mdat_payload.extend_from_slice(xmp.as_bytes());
+
+    // the new mdat is appended at the end of the file
+    let mdat_start = (data.len() as i64 + delta) as u64 + 8;
+    let exif_location = (mdat_start, exif_payload.len() as u64);
+    let xmp_location = (mdat_start + exif_payload.len() as u64, xmp.len() as u64);
+
+    builder.iloc.shift_offsets(meta.end as u64, delta as u64)?;
+    let new_meta = builder.build(exif_location, xmp_location)?;
+    if new_meta.len() != new_meta_size {
+        bail!("unexpected meta box size");
+    }
+
+    let mut output = Vec::with_capacity(data.len() + new_meta.len() + mdat_payload.len());
+    for bmff in boxes.iter() {
+        if bmff.start == meta.start {
+            output.extend_from_slice(&new_meta);
+        } else if read_u32(data, bmff.start)? == 0 {
+            // box that extends to the end need an explicit size now
+            let size = (bmff.end - bmff.start) as u64;
+            if size > u32::MAX as u64 {
+                bail!("box is too large");
+            }
+            output.extend_from_slice(&(size as u32).to_be_bytes());
+            output.extend_from_slice(&data[bmff.start + 4..bmff.end]);
+        } else {
+            output.extend_from_slice(&data[bmff.start..bmff.end]);
+        }
+    }
+    output.extend(make_box(b"mdat", &mdat_payload)?);
+
+    Ok(output)
+}
+
+/// Collect the data of all non-metadata items, in the `iloc` order.
+///
+/// Used to compare AVIF/HEIF images regardless of the embedded metadata.
+pub(super) fn image_data(data: &[u8]) -> Result<Vec<u8>> {
+    let boxes = parse_boxes(data, 0, data.len())?;
+    let Some(meta) = boxes.iter().find(|bmff| &bmff.kind == b"meta") else {
+        bail!("missing meta box");
+    };
+
+    let mut iloc = None;
+    let mut metadata_ids = vec![];
+    for child in parse_boxes(data, meta.content + 4, meta.end)? {
+        let content = &data[child.content..child.end];
+        match &child.kind {
+            b"iloc" => iloc = Some(Iloc::parse(content)?),
+            b"iinf" => {
+                let header = if read_version(content)? == 0 { 6 } else { 8 };
+                for infe in parse_boxes(data, child.content + header, child.end)? {
+                    let (id, kind) = parse_infe(&data[infe.content..infe.end])?;
+                    if matches!(&kind, b"Exif" | b"mime") {
+                        metadata_ids.push(id);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(iloc) = iloc else {
+        bail!("missing iloc box");
+    };
+
+    let mut output = vec![];
+    for item in iloc.items.iter() {
+        // only care about the data stored in the file itself
+        if metadata_ids.contains(&item.id) || item.construction_method != 0 {
+            continue;
+        }
+
+        for extent in item.extents.iter() {
+            let start = item
+                .base_offset
+                .checked_add(extent.offset)
+                .and_then(|start| usize::try_from(start).ok());
+            // zero length means the rest of the file
+            let end = match extent.length {
+                0 => Some(data.len()),
+                length => start.and_then(|start| start.checked_add(usize::try_from(length).ok()?)),
+            };
+            let (Some(start), Some(end)) = (start, end) else {
+                bail!("invalid item extent for item {}", item.id);
+            };
+            let Some(extent_data) = data.get(start..end) else {
+                bail!("invalid item extent for item {}", item.id);
+            };
+            output.extend_from_slice(extent_data);
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Make a minimal HEIF structure with a single item in `mdat`
+    fn make_heif(payload: &[u8]) -> Vec<u8> {
+        let ftyp = make_box(b"ftyp", b"avif\0\0\0\0avifmif1").unwrap();
+        let hdlr = make_full_box(b"hdlr", 0, 0, b"\0\0\0\0pict\0\0\0\0\0\0\0\0\0\0\0\0\0").unwrap();
+        let pitm = make_full_box(b"pitm", 0, 0, &1u16.to_be_bytes()).unwrap();
+        let mut iinf_payload = 1u16.to_be_bytes().to_vec();
+        iinf_payload.extend(make_infe(1, b"av01", None).unwrap());
+        let iinf = make_full_box(b"iinf", 0, 0, &iinf_payload).unwrap();
+
+        let build = |offset: u64| {
+            let iloc = Iloc {
+                version: 0,
+                offset_size: 4,
+                length_size: 4,
+                base_offset_size: 0,
+                index_size: 0,
+                items: vec![IlocItem {
+                    id: 1,
+                    construction_method: 0,
+                    data_reference_index: 0,
+                    base_offset: 0,
+                    extents: vec![IlocExtent {
+                        index: 0,
+                        offset,
+                        length: payload.len() as u64,
+                    }],
+                }],
+            };
+            let mut meta_payload = vec![0, 0, 0, 0];
+            meta_payload.extend_from_slice(&hdlr);
+            meta_payload.extend_from_slice(&pitm);
+            meta_payload.extend(iloc.to_box().unwrap());
+            meta_payload.extend_from_slice(&iinf);
+            make_box(b"meta", &meta_payload).unwrap()
+        };
+
+        let meta_size = build(0).len();
+        let offset = (ftyp.len() + meta_size + 8) as u64;
+        let mut output = ftyp.clone();
+        output.extend(build(offset));
+        output.extend(make_box(b"mdat", payload).unwrap());
+        output
+    }
+
+    /// Read all item data using the `iloc` box
+    fn read_items(data: &[u8]) -> Vec<(u32, Vec<u8>)> {
+        let boxes = parse_boxes(data, 0, data.len()).unwrap();
+        let meta = boxes.iter().find(|b| &b.kind == b"meta").unwrap();
+        let children = parse_boxes(data, meta.content + 4, meta.end).unwrap();
+        let iloc = children.iter().find(|b| &b.kind == b"iloc").unwrap();
+        let iloc = Iloc::parse(&data[iloc.content..iloc.end]).unwrap();
+
+        iloc.items
+            .iter()
+            .map(|item| {
+                let extent = &item.extents[0];
+                let start = (item.base_offset + extent.offset) as usize;
+                let end = start + extent.length as usize;
+                (item.id, data[start..end].to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_embed_avif() {
+        let heif = make_heif(b"image-data");
+        assert_eq!(read_items(&heif), vec![(1, b"image-data".to_vec())]);
+
+        let embedded = embed(&heif, b"MM\0*exif", "<xmp/>").unwrap();
+        let items = read_items(&embedded);
+        assert_eq!(items.len(), 3);
+        // the image data is still pointing to the correct location
+        assert_eq!(items[0], (1, b"image-data".to_vec()));
+        assert_eq!(items[1], (2, b"\0\0\0\0MM\0*exif".to_vec()));
+        assert_eq!(items[2], (3, b"<xmp/>".to_vec()));
+
+        // embedding twice is not supported
+        assert!(embed(&embedded, b"MM\0*exif", "<xmp/>").is_err());
+
+        // the image data is the same regardless of the metadata
+        assert_eq!(image_data(&embedded).unwrap(), b"image-data".to_vec());
+        assert_eq!(image_data(&heif).unwrap(), image_data(&embedded).unwrap());
+
+        // empty or truncated boxes are errors instead of panics
+        let mut empty_meta = make_box(b"ftyp", b"avif\0\0\0\0avifmif1").unwrap();
+        let empty_pitm = make_box(b"pitm", &[]).unwrap();
+        empty_meta.extend(make_full_box(b"meta", 0, 0, &empty_pitm).unwrap());
+        assert!(embed(&empty_meta, b"MM\0*exif", "<xmp/>").is_err());
+        assert!(image_data(&heif[..heif.len() - 4]).is_err());
+        let mut huge_box = 1u32.to_be_bytes().to_vec();
+        huge_box.extend_from_slice(b"mdat");
+        huge_box.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert!(parse_boxes(&huge_box, 0, huge_box.len()).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Embed the metadata into JPEG `APP1` segments.
+
+use color_eyre::eyre::{Result, bail};
+
+const EXIF_SIGNATURE: &[u8] = b"Exif\0\0";
+const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+const MARKER_APP0: u8 = 0xE0;
+const MARKER_APP1: u8 = 0xE1;
+const MARKER_SOS: u8 = 0xDA;
+
+fn make_segment(marker: u8, signature: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+    // the length includes itself
+    let length = signature.len() + payload.len() + 2;
+    if length > u16::MAX as usize {
+        bail!("metadata is too large for a JPEG segment");
+    }
+
+    let mut segment = Vec::with_capacity(length + 2);
+    segment.extend_from_slice(&[0xFF, marker]);
+    segment.extend_from_slice(&(length as u16).to_be_bytes());
+    segment.extend_from_slice(signature);
+    segment.extend_from_slice(payload);
+    Ok(segment)
+}
+
+/// Embed the EXIF and XMP into a JPEG image, replacing the existing one.
+pub(super) fn embed(data: &[u8], exif: &[u8], xmp: &str) -> Result<Vec<u8>> {
+    let mut leading = vec![];
+    let mut segments = vec![];
+
+    let mut pos = 2;
+    loop {
+        // skip fill bytes
+        while data.get(pos) == Some(&0xFF) && data.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+
+        if pos + 4 > data.len() || data[pos] != 0xFF {
+            bail!("invalid JPEG segment at {pos}");
+        }
+
+        let marker = data[pos + 1];
+        if marker == MARKER_SOS {
+            break;
+        }
+
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > data.len() {
+            bail!("invalid JPEG segment length at {pos}");
+        }
+
+        let segment = &data[pos..end];
+        let payload = &segment[4..];
+        let is_old_metadata = marker == MARKER_APP1
+            && (payload.starts_with(EXIF_SIGNATURE) || payload.starts_with(XMP_SIGNATURE));
+
+        if is_old_metadata {
+            // drop, will be replaced
+        } else if marker == MARKER_APP0 && segments.is_empty() {
+            // JFIF must be the first segment
+            leading.push(segment);
+        } else {
+            segments.push(segment);
+        }
+
+        pos = end;
+    }
+
+    let mut output = Vec::with_capacity(data.len() + exif.len() + xmp.len() + 64);
+    output.extend_from_slice(&data[..2]);
+    for segment in leading {
+        output.extend_from_slice(segment);
+    }
+    output.extend(make_segment(MARKER_APP1, EXIF_SIGNATURE, exif)?);
+    output.extend(make_segment(MARKER_APP1, XMP_SIGNATURE, xmp.as_bytes())?);
+    for segment in segments {
+        output.extend_from_slice(segment);
+    }
+    output.extend_from_slice(&data[pos..]);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_jpeg() {
+        let image = image::DynamicImage::new_rgb8(8, 8);
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Jpeg).unwrap();
+        let data = data.into_inner();
+
+        let first = embed(&data, b"MM\0*old", "<old/>").unwrap();
+        let second = embed(&first, b"MM\0*new", "<new/>").unwrap();
+
+        // the old metadata should be replaced
+        assert!(!second.windows(7).any(|w| w == b"MM\0*old"));
+        assert!(second.windows(7).any(|w| w == b"MM\0*new"));
+        assert!(second.windows(6).any(|w| w == b"<new/>"));
+
+        let decoded = image::load_from_memory(&second).unwrap();
+        assert_eq!(decoded.width(), 8);
+    }
+}
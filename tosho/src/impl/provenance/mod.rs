@@ -0,0 +1,454 @@
+//! Embed the source metadata (provenance) into downloaded pages.
+//!
+//! The metadata is written as both EXIF and XMP where the format supports it:
+//! - JPEG: `APP1` segments
+//! - PNG: `eXIf` and `iTXt` chunks
+//! - WebP: `EXIF` and `XMP ` chunks
+//! - AVIF/HEIF: `Exif` and `mime` items
+
+use std::{
+    borrow::Cow,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{Result, bail};
+
+use super::models::{IdDump, SourceDump};
+
+mod avif;
+mod jpeg;
+mod png;
+mod webp;
+
+/// The XMP namespace used for our own properties
+const TOSHO_XMP_NS: &str = "https://github.com/noaione/tosho-mango/ns/1.0/";
+
+/// The chapter information shared by every page
+#[derive(Debug, Clone)]
+pub(crate) struct ChapterProvenance {
+    /// The source where the chapter is downloaded from
+    pub(crate) source: SourceDump,
+    /// The title name
+    pub(crate) title: String,
+    /// The title ID in the source
+    pub(crate) title_id: IdDump,
+    /// The chapter name
+    pub(crate) chapter: String,
+    /// The chapter ID in the source
+    pub(crate) chapter_id: IdDump,
+}
+
+/// A page to embed the metadata into
+#[derive(Debug, Clone)]
+pub(crate) struct PageSource {
+    /// The file stem of the page (e.g. `p000`)
+    pub(crate) stem: String,
+    /// The original URL of the page, if known
+    pub(crate) url: Option<String>,
+}
+
+impl PageSource {
+    pub(crate) fn new(stem: impl Into<String>, url: Option<&str>) -> Self {
+        Self {
+            stem: stem.into(),
+            url: url.map(|url| url.to_string()),
+        }
+    }
+}
+
+/// The full provenance of a single page
+struct PageProvenance<'a> {
+    chapter: &'a ChapterProvenance,
+    /// The page number (1-indexed)
+    page: usize,
+    /// The total page in the chapter
+    total_pages: usize,
+    /// The original URL without any query parameters
+    url: Option<String>,
+    downloaded_at: chrono::DateTime<chrono::Local>,
+}
+
+fn source_service_name(source: SourceDump) -> &'static str {
+    match source {
+        SourceDump::Kmkc => "KM by KC",
+        SourceDump::Musq => "MU! by SQ",
+        SourceDump::Amap => "AM by AP",
+        SourceDump::Sjv => "SJ/M by V",
+        SourceDump::Rbean => "小豆 by KRKR",
+        SourceDump::Mplus => "M+ by S",
+        SourceDump::Nids => "NI by DS",
+    }
+}
+
+fn source_key(source: SourceDump) -> &'static str {
+    match source {
+        SourceDump::Kmkc => "kmkc",
+        SourceDump::Musq => "musq",
+        SourceDump::Amap => "amap",
+        SourceDump::Sjv => "sjv",
+        SourceDump::Rbean => "rbean",
+        SourceDump::Mplus => "mplus",
+        SourceDump::Nids => "nids",
+    }
+}
+
+/// Strip the query parameters and fragment since it might contains a signed token.
+fn strip_url_query(url: &str) -> String {
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    url[..end].to_string()
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Create the EXIF data in TIFF format (without the `Exif\0\0` prefix)
+///
+/// The EXIF ASCII tags only allow 7-bit text, so the title and chapter name are
+/// only written there when they are ASCII. Otherwise they are kept in the XMP and
+/// the UTF-16 `XPTitle` tag.
+fn make_exif(prov: &PageProvenance) -> Result<Vec<u8>> {
+    let ascii = |tag: exif::Tag, text: String| exif::Field {
+        tag,
+        ifd_num: exif::In::PRIMARY,
+        value: exif::Value::Ascii(vec![text.into_bytes()]),
+    };
+
+    let description = format!(
+        "{} - {} ({}/{})",
+        prov.chapter.title, prov.chapter.chapter, prov.page, prov.total_pages
+    );
+    // XPTitle is a null-terminated UTF-16LE string stored as bytes
+    let xp_title = description
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect::<Vec<u8>>();
+
+    let mut fields = vec![
+        // XPTitle
+        exif::Field {
+            tag: exif::Tag(exif::Context::Tiff, 0x9C9B),
+            ifd_num: exif::In::PRIMARY,
+            value: exif::Value::Byte(xp_title),
+        },
+        // PageNumber, the first value is 0-indexed
+        exif::Field {
+            tag: exif::Tag(exif::Context::Tiff, 0x0129),
+            ifd_num: exif::In::PRIMARY,
+            value: exif::Value::Short(vec![
+                (prov.page - 1).min(u16::MAX as usize) as u16,
+                prov.total_pages.min(u16::MAX as usize) as u16,
+            ]),
+        },
+        ascii(
+            exif::Tag::Software,
+            format!("tosho v{}", env!("CARGO_PKG_VERSION")),
+        ),
+        ascii(
+            exif::Tag::DateTime,
+            prov.downloaded_at.format("%Y:%m:%d %H:%M:%S").to_string(),
+        ),
+    ];
+    if description.is_ascii() {
+        fields.push(ascii(exif::Tag::ImageDescription, description.clone()));
+    }
+    if prov.chapter.title.is_ascii() {
+        // DocumentName
+        fields.push(ascii(
+            exif::Tag(exif::Context::Tiff, 0x010D),
+            prov.chapter.title.clone(),
+        ));
+    }
+    if prov.chapter.chapter.is_ascii() {
+        // PageName
+        fields.push(ascii(
+            exif::Tag(exif::Context::Tiff, 0x011D),
+            prov.chapter.chapter.clone(),
+        ));
+    }
+
+    let mut writer = exif::experimental::Writer::new();
+    for field in fields.iter() {
+        writer.push_field(field);
+    }
+
+    let mut buffer = Cursor::new(Vec::new());
+    writer.write(&mut buffer, false)?;
+    Ok(buffer.into_inner())
+}
+
+/// Create the XMP packet
+fn make_xmp(prov: &PageProvenance) -> String {
+    let timestamp = prov.downloaded_at.to_rfc3339();
+    let source_url = prov
+        .url
+        .as_ref()
+        .map(|url| format!("\n   <dc:source>{}</dc:source>", escape_xml(url)))
+        .unwrap_or_default();
+
+    format!(
+        r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:tosho="{ns}">
+   <dc:title><rdf:Alt><rdf:li xml:lang="x-default">{title}</rdf:li></rdf:Alt></dc:title>
+   <dc:publisher><rdf:Bag><rdf:li>{service}</rdf:li></rdf:Bag></dc:publisher>{source_url}
+   <xmp:CreatorTool>tosho v{version}</xmp:CreatorTool>
+   <xmp:MetadataDate>{timestamp}</xmp:MetadataDate>
+   <tosho:Source>{source}</tosho:Source>
+   <tosho:TitleId>{title_id}</tosho:TitleId>
+   <tosho:Chapter>{chapter}</tosho:Chapter>
+   <tosho:ChapterId>{chapter_id}</tosho:ChapterId>
+   <tosho:Page>{page}</tosho:Page>
+   <tosho:TotalPages>{total_pages}</tosho:TotalPages>
+   <tosho:DownloadedAt>{timestamp}</tosho:DownloadedAt>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        bom = '\u{feff}',
+        ns = TOSHO_XMP_NS,
+        title = escape_xml(&prov.chapter.title),
+        service = escape_xml(source_service_name(prov.chapter.source)),
+        source_url = source_url,
+        version = env!("CARGO_PKG_VERSION"),
+        timestamp = timestamp,
+        source = source_key(prov.chapter.source),
+        title_id = escape_xml(&prov.chapter.title_id.to_string()),
+        chapter = escape_xml(&prov.chapter.chapter),
+        chapter_id = escape_xml(&prov.chapter.chapter_id.to_string()),
+        page = prov.page,
+        total_pages = prov.total_pages,
+    )
+}
+
+/// Embed the metadata into the image data, returns `None` if the format is not supported.
+fn embed_metadata(data: &[u8], exif: &[u8], xmp: &str) -> Result<Option<Vec<u8>>> {
+    let embedded = if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg::embed(data, exif, xmp)?
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png::embed(data, exif, xmp)?
+    } else if data.len() > 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        webp::embed(data, exif, xmp)?
+    } else if data.len() > 12 && &data[4..8] == b"ftyp" {
+        avif::embed(data, exif, xmp)?
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(embedded))
+}
+
+/// Get the image content of a page without the embedded metadata.
+///
+/// Only AVIF/HEIF need this, since the other formats can be compared by
+/// the decoded image instead.
+pub(crate) fn image_content(data: &[u8]) -> Cow<'_, [u8]> {
+    if data.len() > 12 && &data[4..8] == b"ftyp" {
+        match avif::image_data(data) {
+            Ok(content) if !content.is_empty() => return Cow::Owned(content),
+            _ => {}
+        }
+    }
+
+    Cow::Borrowed(data)
+}
+
+fn find_page_file(chapter_dir: &Path, stem: &str) -> Option<PathBuf> {
+    std::fs::read_dir(chapter_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.is_file()
+                && !matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("json" | "tmp")
+                )
+                && path.file_stem().and_then(|s| s.to_str()) == Some(stem)
+        })
+}
+
+fn embed_page(path: &Path, prov: &PageProvenance) -> Result<bool> {
+    let data = std::fs::read(path)?;
+    if data.is_empty() {
+        bail!("zero-byte file");
+    }
+
+    let exif = make_exif(prov)?;
+    let xmp = make_xmp(prov);
+
+    match embed_metadata(&data, &exif, &xmp)? {
+        Some(embedded) => {
+            // write to a temporary file first so we don't leave a broken page behind,
+            // keep the original extension so it never has the same stem as the page
+            let mut temp_path = path.as_os_str().to_owned();
+            temp_path.push(".tmp");
+            let temp_path = PathBuf::from(temp_path);
+            std::fs::write(&temp_path, embedded)?;
+            std::fs::rename(&temp_path, path)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Embed the metadata into every downloaded pages of a chapter.
+pub(crate) async fn embed_chapter_metadata(
+    chapter_dir: &Path,
+    chapter: &ChapterProvenance,
+    pages: Vec<PageSource>,
+    console: &crate::term::Terminal,
+) {
+    let chapter_dir = chapter_dir.to_path_buf();
+    let chapter = chapter.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let downloaded_at = chrono::Local::now();
+        let total_pages = pages.len();
+
+        let mut embedded = 0;
+        let mut errors = vec![];
+        for (idx, page) in pages.iter().enumerate() {
+            let Some(path) = find_page_file(&chapter_dir, &page.stem) else {
+                // not downloaded or dropped
+                continue;
+            };
+
+            let prov = PageProvenance {
+                chapter: &chapter,
+                page: idx + 1,
+                total_pages,
+                url: page.url.as_deref().map(strip_url_query),
+                downloaded_at,
+            };
+
+            match embed_page(&path, &prov) {
+                Ok(true) => embedded += 1,
+                Ok(false) => {}
+                Err(err) => errors.push(format!("{}: {err}", page.stem)),
+            }
+        }
+
+        (embedded, errors)
+    })
+    .await;
+
+    match result {
+        Ok((embedded, errors)) => {
+            for error in errors {
+                console.warn(format!("    Failed to embed metadata to {error}"));
+            }
+            if console.is_debug() {
+                console.log(format!("   Embedded metadata to {embedded} pages"));
+            }
+        }
+        Err(err) => console.error(format!("    Failed to embed metadata: {err}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_provenance(chapter: &ChapterProvenance) -> PageProvenance<'_> {
+        PageProvenance {
+            chapter,
+            page: 2,
+            total_pages: 10,
+            url: Some(strip_url_query(
+                "https://example.com/page/2.jpg?token=secret",
+            )),
+            downloaded_at: chrono::Local::now(),
+        }
+    }
+
+    fn make_chapter() -> ChapterProvenance {
+        ChapterProvenance {
+            source: SourceDump::Mplus,
+            title: "Title & <Friends>".to_string(),
+            title_id: IdDump::Number(100),
+            chapter: "Chapter 1".to_string(),
+            chapter_id: IdDump::Number(1000),
+        }
+    }
+
+    #[test]
+    fn test_strip_url_query() {
+        assert_eq!(
+            strip_url_query("https://example.com/a.jpg?token=1#frag"),
+            "https://example.com/a.jpg"
+        );
+        assert_eq!(
+            strip_url_query("https://example.com/a.jpg"),
+            "https://example.com/a.jpg"
+        );
+    }
+
+    #[test]
+    fn test_make_xmp() {
+        let chapter = make_chapter();
+        let xmp = make_xmp(&make_provenance(&chapter));
+
+        assert!(xmp.contains("Title &amp; &lt;Friends&gt;"));
+        assert!(xmp.contains("<dc:source>https://example.com/page/2.jpg</dc:source>"));
+        assert!(xmp.contains("<tosho:Page>2</tosho:Page>"));
+        assert!(!xmp.contains("secret"));
+    }
+
+    #[test]
+    fn test_make_exif() {
+        let chapter = make_chapter();
+        let exif = make_exif(&make_provenance(&chapter)).unwrap();
+
+        let (fields, _) = exif::parse_exif(&exif).unwrap();
+        let software = fields
+            .iter()
+            .find(|field| field.tag == exif::Tag::Software)
+            .unwrap();
+        assert!(
+            software
+                .display_value()
+                .to_string()
+                .contains(env!("CARGO_PKG_VERSION"))
+        );
+
+        let page_number = fields
+            .iter()
+            .find(|field| field.tag == exif::Tag(exif::Context::Tiff, 0x0129))
+            .unwrap();
+        assert!(matches!(&page_number.value, exif::Value::Short(value) if value == &[1, 10]));
+
+        // non-ASCII text is not written into the ASCII tags
+        let chapter = ChapterProvenance {
+            title: "作品名".to_string(),
+            ..make_chapter()
+        };
+        let exif = make_exif(&make_provenance(&chapter)).unwrap();
+        let (fields, _) = exif::parse_exif(&exif).unwrap();
+        assert!(fields.iter().all(|field| match &field.value {
+            exif::Value::Ascii(values) => values.iter().all(|value| value.is_ascii()),
+            _ => true,
+        }));
+        assert!(
+            fields
+                .iter()
+                .any(|field| field.tag == exif::Tag(exif::Context::Tiff, 0x011D))
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Embed the metadata into PNG `eXIf` and `iTXt` chunks.
+
+use color_eyre::eyre::{Result, bail};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+
+fn make_chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(payload);
+
+    let mut chunk = Vec::with_capacity(payload.len() + 12);
+    chunk.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(payload);
+    chunk.extend_from_slice(&hasher.finalize().to_be_bytes());
+    chunk
+}
+
+fn make_xmp_chunk(xmp: &str) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(xmp.len() + XMP_KEYWORD.len() + 5);
+    payload.extend_from_slice(XMP_KEYWORD);
+    // null separator, compression flag, compression method
+    payload.extend_from_slice(&[0, 0, 0]);
+    // empty language tag and translated keyword
+    payload.extend_from_slice(&[0, 0]);
+    payload.extend_from_slice(xmp.as_bytes());
+
+    make_chunk(b"iTXt", &payload)
+}
+
+/// Embed the EXIF and XMP into a PNG image, replacing the existing one.
+pub(super) fn embed(data: &[u8], exif: &[u8], xmp: &str) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len() + exif.len() + xmp.len() + 64);
+    output.extend_from_slice(PNG_SIGNATURE);
+
+    let mut pos = PNG_SIGNATURE.len();
+    let mut written = false;
+    while pos < data.len() {
+        if pos + 12 > data.len() {
+            bail!("invalid PNG chunk at {pos}");
+        }
+
+        let length = u32::from_be_bytes(data[pos..pos + 4].try_into()?) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let end = pos + 12 + length;
+        if end > data.len() {
+            bail!("invalid PNG chunk length at {pos}");
+        }
+
+        let payload = &data[pos + 8..pos + 8 + length];
+        let is_old_metadata = kind == b"eXIf"
+            || (kind == b"iTXt"
+                && payload.starts_with(XMP_KEYWORD)
+                && payload.get(XMP_KEYWORD.len()) == Some(&0));
+
+        if !is_old_metadata {
+            output.extend_from_slice(&data[pos..end]);
+        }
+
+        // eXIf must be placed before IDAT, so we put it right after IHDR
+        if kind == b"IHDR" {
+            output.extend(make_chunk(b"eXIf", exif));
+            output.extend(make_xmp_chunk(xmp));
+            written = true;
+        }
+
+        pos = end;
+    }
+
+    if !written {
+        bail!("missing IHDR chunk");
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_png() {
+        let image = image::DynamicImage::new_rgb8(8, 8);
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        let data = data.into_inner();
+
+        let first = embed(&data, b"MM\0*old", "<old/>").unwrap();
+        let second = embed(&first, b"MM\0*new", "<new/>").unwrap();
+
+        assert!(!second.windows(7).any(|w| w == b"MM\0*old"));
+        assert!(!second.windows(6).any(|w| w == b"<old/>"));
+        assert!(second.windows(6).any(|w| w == b"<new/>"));
+
+        // make sure the image is still readable
+        let decoded = image::load_from_memory(&second).unwrap();
+        assert_eq!(decoded.width(), 8);
+    }
+}
@@ -0,0 +1,156 @@
+//! Embed the metadata into WebP `EXIF` and `XMP ` chunks.
+//!
+//! Simple WebP (lossy `VP8 ` or lossless `VP8L`) would be converted into
+//! the extended format (`VP8X`) since the metadata chunks require it.
+
+use color_eyre::eyre::{Result, bail};
+
+const FLAG_ALPHA: u8 = 0x10;
+const FLAG_EXIF: u8 = 0x08;
+const FLAG_XMP: u8 = 0x04;
+
+struct Chunk<'a> {
+    kind: [u8; 4],
+    payload: &'a [u8],
+}
+
+fn parse_chunks(data: &[u8]) -> Result<Vec<Chunk<'_>>> {
+    let mut chunks = vec![];
+    let mut pos = 12;
+    while pos < data.len() {
+        if pos + 8 > data.len() {
+            bail!("invalid WebP chunk at {pos}");
+        }
+
+        let kind: [u8; 4] = data[pos..pos + 4].try_into()?;
+        let length = u32::from_le_bytes(data[pos + 4..pos + 8].try_into()?) as usize;
+        let end = pos + 8 + length;
+        if end > data.len() {
+            bail!("invalid WebP chunk length at {pos}");
+        }
+
+        chunks.push(Chunk {
+            kind,
+            payload: &data[pos + 8..end],
+        });
+        // chunks are padded to even size
+        pos = end + (length % 2);
+    }
+
+    Ok(chunks)
+}
+
+fn write_chunk(output: &mut Vec<u8>, kind: &[u8; 4], payload: &[u8]) {
+    output.extend_from_slice(kind);
+    output.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    output.extend_from_slice(payload);
+    if payload.len() % 2 == 1 {
+        output.push(0);
+    }
+}
+
+/// Get the canvas size and alpha usage of a simple WebP image
+fn simple_image_info(chunk: &Chunk) -> Result<(u32, u32, bool)> {
+    let payload = chunk.payload;
+    match &chunk.kind {
+        b"VP8 " => {
+            if payload.len() < 10 || payload[3..6] != [0x9D, 0x01, 0x2A] {
+                bail!("invalid VP8 frame header");
+            }
+            let width = u16::from_le_bytes([payload[6], payload[7]]) & 0x3FFF;
+            let height = u16::from_le_bytes([payload[8], payload[9]]) & 0x3FFF;
+            Ok((width as u32, height as u32, false))
+        }
+        b"VP8L" => {
+            if payload.len() < 5 || payload[0] != 0x2F {
+                bail!("invalid VP8L header");
+            }
+            let bits = u32::from_le_bytes(payload[1..5].try_into()?);
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            let alpha = (bits >> 28) & 1 == 1;
+            Ok((width, height, alpha))
+        }
+        _ => bail!("unknown WebP image chunk"),
+    }
+}
+
+fn make_vp8x(flags: u8, width: u32, height: u32) -> [u8; 10] {
+    let width = (width - 1).to_le_bytes();
+    let height = (height - 1).to_le_bytes();
+    [
+        flags, 0, 0, 0, width[0], width[1], width[2], height[0], height[1], height[2],
+    ]
+}
+
+/// Embed the EXIF and XMP into a WebP image, replacing the existing one.
+pub(super) fn embed(data: &[u8], exif: &[u8], xmp: &str) -> Result<Vec<u8>> {
+    let chunks = parse_chunks(data)?;
+    let Some(first) = chunks.first() else {
+        bail!("empty WebP image");
+    };
+
+    let vp8x = match &first.kind {
+        b"VP8X" => {
+            if first.payload.len() < 10 {
+                bail!("invalid VP8X chunk");
+            }
+            let mut vp8x: [u8; 10] = first.payload[..10].try_into()?;
+            vp8x[0] |= FLAG_EXIF | FLAG_XMP;
+            vp8x
+        }
+        _ => {
+            let (width, height, alpha) = simple_image_info(first)?;
+            let mut flags = FLAG_EXIF | FLAG_XMP;
+            if alpha {
+                flags |= FLAG_ALPHA;
+            }
+            make_vp8x(flags, width, height)
+        }
+    };
+
+    let mut body = Vec::with_capacity(data.len() + exif.len() + xmp.len() + 64);
+    body.extend_from_slice(b"WEBP");
+    write_chunk(&mut body, b"VP8X", &vp8x);
+    for chunk in chunks.iter() {
+        if matches!(&chunk.kind, b"VP8X" | b"EXIF" | b"XMP ") {
+            continue;
+        }
+        write_chunk(&mut body, &chunk.kind, chunk.payload);
+    }
+    // metadata chunks are placed at the end
+    write_chunk(&mut body, b"EXIF", exif);
+    write_chunk(&mut body, b"XMP ", xmp.as_bytes());
+
+    let mut output = Vec::with_capacity(body.len() + 8);
+    output.extend_from_slice(b"RIFF");
+    output.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    output.extend(body);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_webp() {
+        let image = image::DynamicImage::new_rgba8(12, 7);
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::WebP).unwrap();
+        let data = data.into_inner();
+
+        let first = embed(&data, b"MM\0*old", "<old/>").unwrap();
+        let second = embed(&first, b"MM\0*new", "<new/>").unwrap();
+
+        let chunks = parse_chunks(&second).unwrap();
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|chunk| &chunk.kind).collect();
+        assert_eq!(kinds, vec![b"VP8X", b"VP8L", b"EXIF", b"XMP "]);
+        assert_eq!(chunks[0].payload, &make_vp8x(0x1C, 12, 7));
+        assert_eq!(chunks[2].payload, b"MM\0*new");
+
+        let decoded = image::load_from_memory(&second).unwrap();
+        assert_eq!(decoded.width(), 12);
+        assert_eq!(decoded.height(), 7);
+    }
+}
@@ -4,6 +4,7 @@ use tosho_rbean::{RBClient, RBPlatform};
 use crate::{
     cli::ExitCode,
     config::{get_all_config, save_config, try_remove_config},
+    r#impl::credentials::{CredentialArg, resolve_credential},
 };
 
 use super::{
@@ -12,16 +13,29 @@ use super::{
 };
 
 pub async fn rbean_account_login(
-    email: String,
-    password: String,
+    email: Option<String>,
+    password: CredentialArg,
     platform: DeviceType,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
-    console.info(cformat!(
-        "Authenticating with email <m,s>{}</> and password <m,s>{}</>...",
-        email,
-        password
-    ));
+    let Some(email) =
+        resolve_credential(email, "TOSHO_RB_EMAIL", "Email", false, no_input, console)
+    else {
+        return 1;
+    };
+    let Some(password) = resolve_credential(
+        password,
+        "TOSHO_RB_PASSWORD",
+        "Password",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
+    console.info(cformat!("Authenticating with email <m,s>{}</>...", email));
 
     let rb_platform = match platform {
         DeviceType::Android => RBPlatform::Android,
@@ -39,7 +53,7 @@ pub async fn rbean_account_login(
     let mut old_id: Option<String> = None;
     if let Some(old_config) = old_config {
         console.warn("Email already authenticated!");
-        let abort_it = console.confirm(Some("Do you want to replace it?"));
+        let abort_it = no_input || console.confirm(Some("Do you want to replace it?"));
         if !abort_it {
             console.info("Aborting...");
             return 0;
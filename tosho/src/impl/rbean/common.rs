@@ -61,7 +61,7 @@ pub(super) fn do_print_search_information(
     }
 }
 
-pub(super) fn save_session_config(client: &RBClient, config: &Config) {
+pub(crate) fn save_session_config(client: &RBClient, config: &Config) {
     let mut config = config.clone();
     config.access_token = client.get_token().to_string();
     if let Some(expiry_at) = client.get_expiry_at() {
@@ -15,8 +15,13 @@ use crate::{
     cli::ExitCode,
     r#impl::{
         clean_filename,
-        common::{check_chapter_folder_existence, check_downloaded_image_count},
-        models::{ChapterDetailDump, MangaDetailDump},
+        common::{
+            INTERRUPTED_EXIT_CODE, check_chapter_folder_existence, check_downloaded_pages,
+            is_chapter_interrupted, is_shutdown_requested, record_chapter_pages,
+            record_written_chapter,
+        },
+        models::{ChapterDetailDump, MangaDetailDump, SourceDump},
+        provenance::{ChapterProvenance, PageSource, embed_chapter_metadata},
     },
     term::{ConsoleChoice, Terminal},
 };
@@ -126,6 +131,8 @@ pub(crate) struct RBDownloadConfigCli {
 
     /// Auto download ignore any images checking but just check for folder existence
     pub(crate) only_check_folder: bool,
+    /// Embed the source metadata into the downloaded pages
+    pub(crate) embed_metadata: bool,
 }
 
 fn create_chapters_info(title: &Manga, chapters: &[Chapter]) -> MangaDetailDump {
@@ -292,6 +299,23 @@ fn select_quality_url(
     }
 }
 
+/// Select the image URL of a page based on the format and quality.
+fn select_page_url(
+    page: &ChapterPage,
+    dl_config: &RBDownloadConfigCli,
+    hires_available: bool,
+) -> color_eyre::eyre::Result<String> {
+    let mut img_source = match dl_config.format {
+        CLIDownloadFormat::Jpeg => page.image().jpg().to_vec(),
+        CLIDownloadFormat::Webp => page.image().webp().to_vec(),
+    };
+
+    img_source.sort();
+    img_source.reverse();
+
+    select_quality_url(&img_source, dl_config.quality, hires_available)
+}
+
 async fn rbean_actual_downloader(
     node: DownloadNode,
     image_dir: PathBuf,
@@ -300,18 +324,15 @@ async fn rbean_actual_downloader(
     console: Terminal,
     progress: Arc<indicatif::ProgressBar>,
 ) -> color_eyre::eyre::Result<()> {
+    // stop before the next page when shutting down
+    if is_shutdown_requested() {
+        return Ok(());
+    }
+
     let image_fn = format!("p{:03}.{}", node.idx, node.extension);
     let img_dl_path = image_dir.join(image_fn.clone());
 
-    let mut img_source = match dl_config.format {
-        CLIDownloadFormat::Jpeg => node.page.image().jpg().to_vec(),
-        CLIDownloadFormat::Webp => node.page.image().webp().to_vec(),
-    };
-
-    img_source.sort();
-    img_source.reverse();
-
-    let download_url = select_quality_url(&img_source, dl_config.quality, hires_available)?;
+    let download_url = select_page_url(&node.page, &dl_config, hires_available)?;
     let writer = tokio::fs::File::create(&img_dl_path).await?;
 
     if console.is_debug() {
@@ -424,14 +445,23 @@ pub(crate) async fn rbean_download(
     }
 
     let title_dir = get_output_directory(&output_dir, result.uuid().to_string(), None, true);
-    let dump_info = create_chapters_info(&result, chapter_meta.chapters());
+    let mut dump_info = create_chapters_info(&result, chapter_meta.chapters())
+        .with_source(SourceDump::Rbean, result.uuid().to_string());
 
     let title_dump_path = title_dir.join("_info.json");
+    // keep the chapter information from the previous download
+    if let Some(older_info) = MangaDetailDump::load(&title_dump_path) {
+        dump_info.inherit_chapter_meta(&older_info);
+    }
     dump_info
         .dump(&title_dump_path)
         .expect("Failed to dump title info");
 
     for chapter in download_chapters {
+        if is_shutdown_requested() {
+            console.warn("Shutdown requested, stopping the download");
+            return INTERRUPTED_EXIT_CODE;
+        }
         console.info(cformat!(
             "  Downloading chapter <m,s>{}</> ({})...",
             chapter.formatted_title(),
@@ -464,6 +494,17 @@ pub(crate) async fn rbean_download(
         let view_req = view_req.unwrap();
         save_session_config(client, account);
 
+        record_chapter_pages(
+            &mut dump_info,
+            &title_dump_path,
+            chapter.uuid().to_string(),
+            view_req.data().pages().len(),
+            console,
+        );
+
+        let page_stems: Vec<String> = (0..view_req.data().pages().len())
+            .map(|idx| format!("p{idx:03}"))
+            .collect();
         if dl_config.only_check_folder {
             if check_chapter_folder_existence(&image_dir) {
                 console.info(cformat!(
@@ -473,9 +514,7 @@ pub(crate) async fn rbean_download(
                 ));
                 continue;
             }
-        } else if let Some(count) = check_downloaded_image_count(&image_dir, image_ext)
-            && count >= view_req.data().pages().len()
-        {
+        } else if check_downloaded_pages(&image_dir, &page_stems) {
             console.warn(cformat!(
                 "   Chapter <m,s>{}</> (<s>{}</>) has been downloaded, skipping",
                 chapter.formatted_title(),
@@ -592,6 +631,35 @@ pub(crate) async fn rbean_download(
             }
         }
         progress.finish_with_message("Downloaded");
+
+        if is_chapter_interrupted(&image_dir, &page_stems) {
+            console.warn(cformat!(
+                "   Shutdown requested, chapter <m,s>{}</> (<s>{}</>) is left incomplete",
+                chapter.formatted_title(),
+                chapter.uuid()
+            ));
+            return INTERRUPTED_EXIT_CODE;
+        }
+        record_written_chapter(&image_dir);
+
+        if dl_config.embed_metadata {
+            let provenance = ChapterProvenance {
+                source: SourceDump::Rbean,
+                title: dump_info.title_name.clone(),
+                title_id: result.uuid().to_string().into(),
+                chapter: chapter.formatted_title(),
+                chapter_id: chapter.uuid().to_string().into(),
+            };
+            let page_sources: Vec<PageSource> = pages_data
+                .iter()
+                .enumerate()
+                .map(|(idx, page)| {
+                    let url = select_page_url(page, &dl_config, hires_available).ok();
+                    PageSource::new(format!("p{idx:03}"), url.as_deref())
+                })
+                .collect();
+            embed_chapter_metadata(&image_dir, &provenance, page_sources, console).await;
+        }
     }
 
     0
@@ -10,6 +10,7 @@ pub(crate) mod config;
 pub(crate) mod download;
 pub(crate) mod favorites;
 pub(crate) mod manga;
+pub(crate) mod publishers;
 pub(crate) mod rankings;
 
 #[derive(Subcommand, Clone)]
@@ -17,12 +18,25 @@ pub(crate) enum RBeanCommands {
     /// Authenticate tosho with your 小豆 (Red Bean) account.
     Auth {
         /// Email to use
-        email: String,
+        ///
+        /// Uses `TOSHO_RB_EMAIL` (or `TOSHO_RB_EMAIL_FILE`) when omitted.
+        email: Option<String>,
         /// Password to use
-        password: String,
+        ///
+        /// Uses `TOSHO_RB_PASSWORD` (or `TOSHO_RB_PASSWORD_FILE`) when omitted.
+        password: Option<String>,
+        /// Read the password from a file instead
+        #[arg(long = "password-file", conflicts_with_all = ["password", "password_stdin"])]
+        password_file: Option<PathBuf>,
+        /// Read the password from the first line of stdin instead
+        #[arg(long = "password-stdin", conflicts_with = "password")]
+        password_stdin: bool,
         /// Platform to use
         #[arg(short, long, value_enum, default_value = "android")]
         platform: crate::r#impl::rbean::config::DeviceType,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Get an account information
     Account,
@@ -36,6 +50,9 @@ pub(crate) enum RBeanCommands {
         /// Skip folder contents checking and ONLY check for folder existence
         #[arg(short = 'f', long)]
         only_check_folder: bool,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
@@ -64,6 +81,9 @@ pub(crate) enum RBeanCommands {
         /// Specify the chapter UUID to purchase (ex: uuid-1,uuid-2,uuid-3)
         #[arg(short = 'c', long = "chapters", default_value = None, value_parser = parse_comma_string)]
         chapters: Option<CommaSeparatedString>,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
@@ -95,6 +115,19 @@ pub(crate) enum RBeanCommands {
         #[arg(short = 'c', long = "chapters")]
         show_chapters: bool,
     },
+    /// Get a publisher information and their titles catalogue
+    Publisher {
+        /// Slug of the publisher
+        slug: String,
+        /// Limit the number of titles
+        #[arg(short, long, default_value = "25")]
+        limit: Option<u32>,
+        /// Sort the titles
+        #[arg(short, long, value_enum, default_value = "alphabetical")]
+        sort: Option<crate::r#impl::rbean::manga::CLISortOption>,
+    },
+    /// Get the list of available publishers
+    Publishers,
     /// Get the read list of your account
     #[command(name = "readlist")]
     ReadList,
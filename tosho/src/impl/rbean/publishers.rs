@@ -0,0 +1,180 @@
+use color_print::cformat;
+use tosho_rbean::{
+    RBClient,
+    models::{Publisher, SortOption},
+};
+
+use crate::cli::ExitCode;
+
+use super::{
+    common::{do_print_search_information, save_session_config},
+    config::Config,
+    manga::CLISortOption,
+};
+
+/// Find the publishers that looks similar to the given slug.
+fn similar_publishers<'a>(publishers: &'a [Publisher], slug: &str) -> Vec<&'a Publisher> {
+    let slug = slug.to_lowercase();
+    publishers
+        .iter()
+        .filter(|publisher| {
+            let pub_slug = publisher.slug().to_lowercase();
+            pub_slug.contains(&slug)
+                || slug.contains(&pub_slug)
+                || publisher.name().to_lowercase().contains(&slug)
+        })
+        .collect()
+}
+
+fn print_publisher(publisher: &Publisher, console: &crate::term::Terminal) {
+    console.info(cformat!(
+        "  <s>{}</> (<m,s>{}</> / {})",
+        publisher.name(),
+        publisher.slug(),
+        publisher.uuid()
+    ));
+}
+
+pub(crate) async fn rbean_publishers(
+    client: &mut RBClient,
+    account: &Config,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    console.info("Fetching available publishers...");
+
+    let filters = match client.get_manga_filters().await {
+        Ok(filters) => filters,
+        Err(e) => {
+            console.error(format!("Failed to get manga filters: {e}"));
+            return 1;
+        }
+    };
+
+    save_session_config(client, account);
+
+    if filters.publishers().is_empty() {
+        console.warn("No publishers found!");
+        return 0;
+    }
+
+    console.info(cformat!(
+        "Publishers (<m,s>{}</> publishers):",
+        filters.publishers().len()
+    ));
+    for publisher in filters.publishers() {
+        print_publisher(publisher, console);
+    }
+
+    if !filters.sort_options().is_empty() {
+        console.info("");
+        console.info("Available sort options:");
+        for sort in filters.sort_options() {
+            console.info(cformat!("  - <s>{}</> ({})", sort.name(), sort.r#type()));
+        }
+    }
+
+    0
+}
+
+pub(crate) async fn rbean_publisher(
+    slug: &str,
+    limit: Option<u32>,
+    sort_options: Option<CLISortOption>,
+    client: &mut RBClient,
+    account: &Config,
+    console: &crate::term::Terminal,
+) -> ExitCode {
+    console.info(cformat!("Fetching publisher <m,s>{}</>...", slug));
+
+    let publisher = match client.get_publisher(slug).await {
+        Ok(publisher) => publisher,
+        Err(e) => {
+            console.error(format!("Failed to get publisher: {e}"));
+
+            if let Ok(filters) = client.get_manga_filters().await {
+                let similar = similar_publishers(filters.publishers(), slug);
+                if !similar.is_empty() {
+                    console.info("Did you mean:");
+                    for publisher in similar {
+                        print_publisher(publisher, console);
+                    }
+                }
+            }
+
+            return 1;
+        }
+    };
+
+    save_session_config(client, account);
+
+    console.info(cformat!(
+        "Showing information for <m,s>{}</>:",
+        publisher.name()
+    ));
+    console.info(cformat!("  <s>Slug</>: {}", publisher.slug()));
+    console.info(cformat!("  <s>UUID</>: {}", publisher.uuid()));
+
+    let results = match client
+        .get_publisher_mangas(
+            publisher.slug(),
+            Some(0),
+            limit,
+            sort_options.map(SortOption::from),
+        )
+        .await
+    {
+        Ok(results) => results,
+        Err(e) => {
+            console.error(format!("Failed to get publisher catalogue: {e}"));
+            return 1;
+        }
+    };
+
+    save_session_config(client, account);
+
+    if results.results().is_empty() {
+        console.warn("No titles found for this publisher!");
+        return 0;
+    }
+
+    console.info(cformat!(
+        "Catalogue (<m,s>{}</> of <m,s>{}</> titles):",
+        results.results().len(),
+        results.total()
+    ));
+    do_print_search_information(results.results(), false, None);
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_publisher(name: &str, slug: &str) -> Publisher {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "uuid": "uuid",
+            "slug": slug,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_similar_publishers() {
+        let publishers = vec![
+            make_publisher("Kodansha", "kodansha"),
+            make_publisher("Square Enix", "square-enix"),
+        ];
+
+        let similar = similar_publishers(&publishers, "Square");
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].slug(), "square-enix");
+
+        let similar = similar_publishers(&publishers, "kodansha-us");
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].slug(), "kodansha");
+
+        assert!(similar_publishers(&publishers, "shueisha").is_empty());
+    }
+}
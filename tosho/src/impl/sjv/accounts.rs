@@ -4,23 +4,37 @@ use tosho_sjv::{SJClient, SJConfig, SJMode, SJPlatform};
 use crate::{
     cli::ExitCode,
     config::{get_all_config, save_config, try_remove_config},
+    r#impl::credentials::{CredentialArg, resolve_credential},
 };
 
 use super::config::{Config, DeviceType, SJDeviceMode};
 use crate::r#impl::common::unix_timestamp_to_string;
 
 pub async fn sjv_account_login(
-    email: String,
-    password: String,
+    email: Option<String>,
+    password: CredentialArg,
     mode: SJDeviceMode,
     platform: DeviceType,
+    no_input: bool,
     console: &crate::term::Terminal,
 ) -> ExitCode {
-    console.info(cformat!(
-        "Authenticating with email <m,s>{}</> and password <m,s>{}</>...",
-        email,
-        password
-    ));
+    let Some(email) =
+        resolve_credential(email, "TOSHO_SJ_EMAIL", "Email", false, no_input, console)
+    else {
+        return 1;
+    };
+    let Some(password) = resolve_credential(
+        password,
+        "TOSHO_SJ_PASSWORD",
+        "Password",
+        true,
+        no_input,
+        console,
+    ) else {
+        return 1;
+    };
+
+    console.info(cformat!("Authenticating with email <m,s>{}</>...", email));
 
     let sj_platform = match platform {
         DeviceType::Android => SJPlatform::Android,
@@ -40,7 +54,7 @@ pub async fn sjv_account_login(
     let mut old_id: Option<String> = None;
     if let Some(old_config) = old_config {
         console.warn("Email already authenticated!");
-        let abort_it = console.confirm(Some("Do you want to replace it?"));
+        let abort_it = no_input || console.confirm(Some("Do you want to replace it?"));
         if !abort_it {
             console.info("Aborting...");
             return 0;
@@ -42,7 +42,7 @@ pub(super) fn do_print_search_information(
 }
 
 /// Search the big cache JSON for specific title
-pub(super) fn search_manga_by_text(contents: &[MangaDetail], target: &str) -> Vec<MangaDetail> {
+pub(crate) fn search_manga_by_text(contents: &[MangaDetail], target: &str) -> Vec<MangaDetail> {
     // Remove diacritics and lower case the target string
     let clean_target = secular::lower_lay_string(target);
     // Split target by spaces and collect patterns
@@ -96,8 +96,8 @@ pub(super) fn search_manga_by_text(contents: &[MangaDetail], target: &str) -> Ve
 const CACHE_EXPIRY: i64 = 12 * 60 * 60;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub(super) struct WrappedStoreCache {
-    pub(super) series: Vec<MangaDetail>,
+pub(crate) struct WrappedStoreCache {
+    pub(crate) series: Vec<MangaDetail>,
     pub(super) chapters: Vec<MangaChapterDetail>,
     #[serde(rename = "_last_updated")]
     pub(super) last_updated: i64,
@@ -131,7 +131,7 @@ impl From<MangaStoreResponse> for WrappedStoreCache {
     }
 }
 
-pub(super) async fn get_cached_store_data(
+pub(crate) async fn get_cached_store_data(
     client: &SJClient,
 ) -> color_eyre::eyre::Result<WrappedStoreCache> {
     let term = get_console(0);
@@ -1,18 +1,26 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+
 use color_print::cformat;
 use tosho_sjv::{
     SJClient, SJPlatform,
-    models::{AccountSubscription, MangaChapterDetail, MangaDetail, SubscriptionType},
+    models::{
+        AccountArchive, AccountSubscription, MangaChapterDetail, MangaDetail, SubscriptionType,
+    },
 };
 
-use crate::r#impl::common::{check_chapter_folder_existence, check_downloaded_image_count};
+use crate::r#impl::common::{
+    INTERRUPTED_EXIT_CODE, check_chapter_folder_existence, check_downloaded_pages,
+    is_chapter_interrupted, is_shutdown_requested, record_chapter_pages, record_written_chapter,
+};
 use crate::{
     cli::ExitCode,
     r#impl::{
-        models::{ChapterDetailDump, MangaDetailDump},
+        models::{ChapterDetailDump, MangaDetailDump, SourceDump},
         parser::NumberOrString,
+        provenance::{ChapterProvenance, PageSource, embed_chapter_metadata},
     },
     term::ConsoleChoice,
 };
@@ -42,6 +50,118 @@ pub(crate) struct SJDownloadCliConfig {
 
     /// Auto download ignore any images checking but just check for folder existence
     pub(crate) only_check_folder: bool,
+    /// Embed the source metadata into the downloaded pages
+    pub(crate) embed_metadata: bool,
+    /// Persist the chapters over the quota and resume after the quota reset
+    pub(crate) wait_for_reset: bool,
+}
+
+/// The file name of the persisted download queue in the title folder.
+const QUEUE_FILE: &str = "_queue.json";
+
+/// The chapters that are left over from the archive quota.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SJQueueDump {
+    /// The chapter IDs that are still need to be downloaded
+    chapter_ids: Vec<u32>,
+    /// The next quota reset in UNIX timestamp
+    next_reset: i64,
+}
+
+impl SJQueueDump {
+    fn load(path: &Path) -> Option<Self> {
+        std::fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+    }
+
+    fn dump(&self, path: &Path) -> color_eyre::eyre::Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Get the number of chapters that can still be read, `None` if there is no limit.
+///
+/// The download limit is also respected when the account has one.
+fn remaining_quota(archive: &AccountArchive) -> Option<usize> {
+    let read_quota = (archive.read_limit() > 0).then(|| archive.remaining().max(0) as usize);
+    let download_quota = (archive.download_limit() > 0).then(|| archive.download_limit() as usize);
+
+    match (read_quota, download_quota) {
+        (Some(read), Some(download)) => Some(read.min(download)),
+        (read, download) => read.or(download),
+    }
+}
+
+/// Split the chapters into what fits the quota and the remainder.
+fn split_by_quota<T>(mut items: Vec<T>, quota: Option<usize>) -> (Vec<T>, Vec<T>) {
+    match quota {
+        Some(quota) if quota < items.len() => {
+            let remainder = items.split_off(quota);
+            (items, remainder)
+        }
+        _ => (items, vec![]),
+    }
+}
+
+fn format_reset_time(next_reset: i64) -> String {
+    match chrono::DateTime::from_timestamp(next_reset, 0) {
+        Some(dt) => dt
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string(),
+        None => next_reset.to_string(),
+    }
+}
+
+fn report_quota(
+    archive: &AccountArchive,
+    allowed: usize,
+    remainder: &[&MangaChapterDetail],
+    console: &crate::term::Terminal,
+) {
+    console.warn(cformat!(
+        "Read quota only allows <m,s>{}</> of <m,s>{}</> chapters (<s>{}</>/<s>{}</> remaining)",
+        allowed,
+        allowed + remainder.len(),
+        archive.remaining().max(0),
+        archive.read_limit()
+    ));
+    for chapter in remainder {
+        console.warn(cformat!(
+            "  - <s>{}</> ({})",
+            chapter.pretty_title(),
+            chapter.id()
+        ));
+    }
+    console.warn(cformat!(
+        "The quota resets at <s>{}</>",
+        format_reset_time(archive.next_reset())
+    ));
+    if archive.download_expire() > 0 {
+        console.warn(cformat!(
+            "Downloaded chapters expire after <s>{}</> hours",
+            archive.download_expire() / 3600
+        ));
+    }
+}
+
+async fn wait_for_reset(next_reset: i64, console: &crate::term::Terminal) {
+    console.info(cformat!(
+        "Waiting until <s>{}</> to resume the download...",
+        format_reset_time(next_reset)
+    ));
+
+    let now = chrono::Utc::now().timestamp();
+    // add a bit of leeway so the quota is actually refreshed
+    let wait_until = tokio::time::Instant::now()
+        + std::time::Duration::from_secs((next_reset - now).max(0) as u64 + 60);
+    // wake up periodically so a shutdown request is not blocked by the wait
+    while !is_shutdown_requested() && tokio::time::Instant::now() < wait_until {
+        let step = wait_until.saturating_duration_since(tokio::time::Instant::now());
+        tokio::time::sleep(step.min(std::time::Duration::from_secs(1))).await;
+    }
 }
 
 fn create_chapters_info(title: &MangaDetail, chapters: &[MangaChapterDetail]) -> MangaDetailDump {
@@ -161,6 +281,11 @@ async fn sjv_actual_downloader(
     console: crate::term::Terminal,
     progress: Arc<indicatif::ProgressBar>,
 ) -> color_eyre::eyre::Result<()> {
+    // stop before the next page when shutting down
+    if is_shutdown_requested() {
+        return Ok(());
+    }
+
     let download_url = node
         .client
         .get_manga_url(node.id, false, Some(node.page))
@@ -194,6 +319,139 @@ async fn sjv_actual_downloader(
     Ok(())
 }
 
+/// Download a single chapter into the chapter folder.
+/// Download a single chapter, returns `false` if it's left incomplete by a shutdown request.
+async fn sjv_download_chapter(
+    title: &MangaDetail,
+    title_name: &str,
+    chapter: &MangaChapterDetail,
+    image_dir: PathBuf,
+    dl_config: &SJDownloadCliConfig,
+    client: &SJClient,
+    console: &crate::term::Terminal,
+) -> bool {
+    let image_ext = match client.get_platform() {
+        SJPlatform::Web => "png",
+        _ => "jpg",
+    };
+
+    let view_req = client.verify_chapter(chapter.id()).await;
+    if let Err(e) = view_req {
+        console.error(format!("Failed to verify chapter: {e}"));
+        return true;
+    }
+
+    let ch_metadata = client.get_chapter_metadata(chapter.id()).await;
+    if let Err(e) = ch_metadata {
+        console.error(format!("Failed to fetch chapter metadata: {e}"));
+        return true;
+    }
+
+    // create chapter dir
+    std::fs::create_dir_all(&image_dir).unwrap();
+
+    // Determine total image count, if we start at 0
+    // then the total image count is the same as the chapter.pages
+    // If above 0, then we need to add that amount to the total image count
+    let start_page = chapter.start_page().unwrap_or(0);
+    let total_image_count = chapter.pages() + start_page;
+
+    let progress = console.make_progress_arc(total_image_count as u64, Some("Downloading"));
+
+    if dl_config.parallel {
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(dl_config.threads));
+
+        let tasks: Vec<_> = (0..total_image_count)
+            .map(|page| {
+                // wrap function in async block
+                let wrap_client = client.clone();
+                let image_dir = image_dir.clone();
+                let cnsl = console.clone();
+                let progress = Arc::clone(&progress);
+                let chapter_id = chapter.id();
+                let semaphore = Arc::clone(&semaphore);
+
+                tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await.unwrap();
+
+                    match sjv_actual_downloader(
+                        DownloadNode {
+                            client: wrap_client,
+                            id: chapter_id,
+                            page,
+                            extension: image_ext.to_string(),
+                        },
+                        image_dir,
+                        cnsl.clone(),
+                        progress,
+                    )
+                    .await
+                    {
+                        Ok(_) => {}
+                        Err(e) => {
+                            cnsl.error(format!("    Failed to download chapter: {e}"));
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        futures_util::future::join_all(tasks).await;
+    } else {
+        for page in 0..total_image_count {
+            match sjv_actual_downloader(
+                DownloadNode {
+                    client: client.clone(),
+                    id: chapter.id(),
+                    page,
+                    extension: image_ext.to_string(),
+                },
+                image_dir.clone(),
+                console.clone(),
+                Arc::clone(&progress),
+            )
+            .await
+            {
+                Ok(_) => {}
+                Err(e) => {
+                    console.error(format!("    Failed to download chapter: {e}"));
+                }
+            }
+        }
+    }
+    progress.finish_with_message("Downloaded");
+
+    let page_stems: Vec<String> = (0..total_image_count)
+        .map(|page| format!("p{page:03}"))
+        .collect();
+    if is_chapter_interrupted(&image_dir, &page_stems) {
+        console.warn(cformat!(
+            "   Shutdown requested, chapter <m,s>{}</> (<s>{}</>) is left incomplete",
+            chapter.pretty_title(),
+            chapter.id()
+        ));
+        return false;
+    }
+    record_written_chapter(&image_dir);
+
+    if dl_config.embed_metadata {
+        let provenance = ChapterProvenance {
+            source: SourceDump::Sjv,
+            title: title_name.to_string(),
+            title_id: (title.id() as u64).into(),
+            chapter: chapter.pretty_title(),
+            chapter_id: (chapter.id() as u64).into(),
+        };
+        // the page URL is signed per request, so nothing to record here
+        let page_sources: Vec<PageSource> = (0..total_image_count)
+            .map(|page| PageSource::new(format!("p{page:03}"), None))
+            .collect();
+        embed_chapter_metadata(&image_dir, &provenance, page_sources, console).await;
+    }
+
+    true
+}
+
 pub(crate) async fn sjv_download(
     title_or_slug: NumberOrString,
     dl_config: SJDownloadCliConfig,
@@ -269,11 +527,41 @@ pub(crate) async fn sjv_download(
                 return 1;
             }
 
-            let select_chapters = if dl_config.no_input {
+            let queue_path =
+                get_output_directory(&output_dir, title.id(), None, false).join(QUEUE_FILE);
+            let queued = if dl_config.wait_for_reset && dl_config.chapter_ids.is_empty() {
+                SJQueueDump::load(&queue_path)
+            } else {
+                None
+            };
+
+            let mut select_chapters = if dl_config.no_input {
                 chapters.clone()
             } else {
                 do_chapter_select(chapters.clone(), title, subs_resp.subscriptions(), console)
             };
+            // the queued chapters from the previous run are added to the selection
+            let queued_reset = match &queued {
+                Some(queued) => {
+                    console.info(cformat!(
+                        "Resuming <m,s>{}</> queued chapters from the previous run...",
+                        queued.chapter_ids.len()
+                    ));
+                    select_chapters.extend(
+                        chapters
+                            .iter()
+                            .filter(|ch| {
+                                queued.chapter_ids.contains(&ch.id())
+                                    && !select_chapters.iter().any(|sel| sel.id() == ch.id())
+                            })
+                            .cloned()
+                            .collect::<Vec<MangaChapterDetail>>(),
+                    );
+                    Some(queued.next_reset)
+                }
+                None => None,
+            };
+            let queued_ids: Vec<u32> = queued.map(|queued| queued.chapter_ids).unwrap_or_default();
 
             let has_subs = match title.subscription_type() {
                 None => false,
@@ -286,24 +574,28 @@ pub(crate) async fn sjv_download(
             let mut download_chapters: Vec<&MangaChapterDetail> = select_chapters
                 .iter()
                 .filter(|&ch| {
+                    // allow if chapter_ids is empty or chapter id is in chapter_ids
+                    let selected = dl_config.chapter_ids.is_empty()
+                        || dl_config.chapter_ids.contains(&(ch.id() as usize));
+
                     if dl_config.no_input {
                         // check if chapter id is in range
-                        match (dl_config.start_from, dl_config.end_at) {
-                            (Some(start), Some(end)) => {
-                                // between start and end
-                                ch.id() >= start && ch.id() <= end
-                            }
-                            (Some(start), None) => {
-                                ch.id() >= start // start to end
-                            }
-                            (None, Some(end)) => {
-                                ch.id() <= end // 0 to end
+                        selected
+                            && match (dl_config.start_from, dl_config.end_at) {
+                                (Some(start), Some(end)) => {
+                                    // between start and end
+                                    ch.id() >= start && ch.id() <= end
+                                }
+                                (Some(start), None) => {
+                                    ch.id() >= start // start to end
+                                }
+                                (None, Some(end)) => {
+                                    ch.id() <= end // 0 to end
+                                }
+                                _ => true,
                             }
-                            _ => true,
-                        }
                     } else {
-                        dl_config.chapter_ids.is_empty()
-                            || dl_config.chapter_ids.contains(&(ch.id() as usize))
+                        selected
                     }
                 })
                 .filter(|&ch| ch.is_available() || has_subs)
@@ -325,26 +617,34 @@ pub(crate) async fn sjv_download(
             download_chapters.sort_by_key(|&a| a.id());
 
             let title_dir = get_output_directory(&output_dir, title.id(), None, true);
-            let dump_info = create_chapters_info(title, &chapters);
+            let mut dump_info = create_chapters_info(title, &chapters)
+                .with_source(SourceDump::Sjv, title.id() as u64);
 
             let title_dump_path = title_dir.join("_info.json");
+            // keep the chapter information from the previous download
+            if let Some(older_info) = MangaDetailDump::load(&title_dump_path) {
+                dump_info.inherit_chapter_meta(&older_info);
+            }
             dump_info
                 .dump(&title_dump_path)
                 .expect("Failed to dump title info");
 
+            let mut pending: Vec<&MangaChapterDetail> = vec![];
             for chapter in download_chapters {
-                console.info(cformat!(
-                    "  Downloading chapter <m,s>{}</> ({})...",
-                    chapter.pretty_title(),
-                    chapter.id()
-                ));
-
+                if is_shutdown_requested() {
+                    console.warn("Shutdown requested, stopping the download");
+                    return INTERRUPTED_EXIT_CODE;
+                }
                 let image_dir =
                     get_output_directory(&output_dir, title.id(), Some(chapter.id()), false);
-                let image_ext = match client.get_platform() {
-                    SJPlatform::Web => "png",
-                    _ => "jpg",
-                };
+
+                record_chapter_pages(
+                    &mut dump_info,
+                    &title_dump_path,
+                    chapter.id() as u64,
+                    (chapter.pages() + chapter.start_page().unwrap_or(0)) as usize,
+                    console,
+                );
 
                 if dl_config.only_check_folder {
                     if check_chapter_folder_existence(&image_dir) {
@@ -355,9 +655,12 @@ pub(crate) async fn sjv_download(
                         ));
                         continue;
                     }
-                } else if let Some(count) = check_downloaded_image_count(&image_dir, image_ext)
-                    && count >= chapter.pages() as usize
-                {
+                } else if check_downloaded_pages(
+                    &image_dir,
+                    &(0..chapter.pages() + chapter.start_page().unwrap_or(0))
+                        .map(|page| format!("p{page:03}"))
+                        .collect::<Vec<String>>(),
+                ) {
                     console.warn(cformat!(
                         "   Chapter <m,s>{}</> (<s>{}</>) has been downloaded, skipping",
                         chapter.pretty_title(),
@@ -366,92 +669,99 @@ pub(crate) async fn sjv_download(
                     continue;
                 }
 
-                let view_req = client.verify_chapter(chapter.id()).await;
-                if let Err(e) = view_req {
-                    console.error(format!("Failed to verify chapter: {e}"));
-                    continue;
-                }
+                pending.push(chapter);
+            }
 
-                let ch_metadata = client.get_chapter_metadata(chapter.id()).await;
-                if let Err(e) = ch_metadata {
-                    console.error(format!("Failed to fetch chapter metadata: {e}"));
-                    continue;
+            // the queued chapters can't be read until the quota they are waiting for resets
+            let mut deferred: Vec<&MangaChapterDetail> = vec![];
+            if let Some(next_reset) = queued_reset
+                && next_reset > chrono::Utc::now().timestamp()
+            {
+                (deferred, pending) = pending
+                    .into_iter()
+                    .partition(|ch| queued_ids.contains(&ch.id()));
+                if !deferred.is_empty() {
+                    console.warn(cformat!(
+                        "<m,s>{}</> queued chapters are waiting for the quota reset at <s>{}</>",
+                        deferred.len(),
+                        format_reset_time(next_reset)
+                    ));
                 }
+            }
 
-                // create chapter dir
-                std::fs::create_dir_all(&image_dir).unwrap();
-
-                // Determine total image count, if we start at 0
-                // then the total image count is the same as the chapter.pages
-                // If above 0, then we need to add that amount to the total image count
-                let start_page = chapter.start_page().unwrap_or(0);
-                let total_image_count = chapter.pages() + start_page;
-
-                let progress =
-                    console.make_progress_arc(total_image_count as u64, Some("Downloading"));
-
-                if dl_config.parallel {
-                    let semaphore = Arc::new(tokio::sync::Semaphore::new(dl_config.threads));
-
-                    let tasks: Vec<_> = (0..total_image_count)
-                        .map(|page| {
-                            // wrap function in async block
-                            let wrap_client = client.clone();
-                            let image_dir = image_dir.clone();
-                            let cnsl = console.clone();
-                            let progress = Arc::clone(&progress);
-                            let chapter_id = chapter.id();
-                            let semaphore = Arc::clone(&semaphore);
-
-                            tokio::spawn(async move {
-                                let _permit = semaphore.acquire().await.unwrap();
-
-                                match sjv_actual_downloader(
-                                    DownloadNode {
-                                        client: wrap_client,
-                                        id: chapter_id,
-                                        page,
-                                        extension: image_ext.to_string(),
-                                    },
-                                    image_dir,
-                                    cnsl.clone(),
-                                    progress,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        cnsl.error(format!("    Failed to download chapter: {e}"));
-                                    }
-                                }
-                            })
-                        })
-                        .collect();
-
-                    futures_util::future::join_all(tasks).await;
-                } else {
-                    for page in 0..total_image_count {
-                        match sjv_actual_downloader(
-                            DownloadNode {
-                                client: client.clone(),
-                                id: chapter.id(),
-                                page,
-                                extension: image_ext.to_string(),
-                            },
-                            image_dir.clone(),
-                            console.clone(),
-                            Arc::clone(&progress),
-                        )
-                        .await
-                        {
-                            Ok(_) => {}
-                            Err(e) => {
-                                console.error(format!("    Failed to download chapter: {e}"));
-                            }
+            let mut archive = subs_resp.archive();
+            loop {
+                let (current, mut remainder) = split_by_quota(pending, remaining_quota(&archive));
+                remainder.append(&mut deferred);
+
+                if !remainder.is_empty() {
+                    report_quota(&archive, current.len(), &remainder, console);
+                    if dl_config.wait_for_reset {
+                        let queue = SJQueueDump {
+                            chapter_ids: remainder.iter().map(|ch| ch.id()).collect(),
+                            next_reset: archive.next_reset(),
+                        };
+                        if let Err(e) = queue.dump(&queue_path) {
+                            console.warn(format!("Failed to save the download queue: {e}"));
                         }
                     }
                 }
-                progress.finish_with_message("Downloaded");
+
+                for chapter in current {
+                    if is_shutdown_requested() {
+                        console.warn("Shutdown requested, stopping the download");
+                        return INTERRUPTED_EXIT_CODE;
+                    }
+                    console.info(cformat!(
+                        "  Downloading chapter <m,s>{}</> ({})...",
+                        chapter.pretty_title(),
+                        chapter.id()
+                    ));
+
+                    let image_dir =
+                        get_output_directory(&output_dir, title.id(), Some(chapter.id()), false);
+                    let completed = sjv_download_chapter(
+                        title,
+                        &dump_info.title_name,
+                        chapter,
+                        image_dir,
+                        &dl_config,
+                        client,
+                        console,
+                    )
+                    .await;
+                    if !completed {
+                        return INTERRUPTED_EXIT_CODE;
+                    }
+                }
+
+                if remainder.is_empty() {
+                    if dl_config.wait_for_reset && queue_path.exists() {
+                        let _ = std::fs::remove_file(&queue_path);
+                    }
+                    break;
+                }
+                if !dl_config.wait_for_reset {
+                    console.info(
+                        "Use `--wait-reset` to resume the remaining chapters after the quota reset",
+                    );
+                    return 1;
+                }
+
+                wait_for_reset(archive.next_reset(), console).await;
+                if is_shutdown_requested() {
+                    console
+                        .warn("Shutdown requested, the remaining chapters are kept in the queue");
+                    return INTERRUPTED_EXIT_CODE;
+                }
+                archive = match client.get_entitlements().await {
+                    Ok(resp) => resp.archive(),
+                    Err(e) => {
+                        console.error(format!("Failed to fetch subscription info: {e}"));
+                        return 1;
+                    }
+                };
+                pending = remainder;
             }
 
             0
@@ -462,3 +772,33 @@ pub(crate) async fn sjv_download(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_archive(read_limit: i32, remaining: i32) -> AccountArchive {
+        serde_json::from_value(serde_json::json!({
+            "ok": 1,
+            "subscription_type": "vm",
+            "archive_limit": read_limit,
+            "archive_reset_seconds": 0,
+            "download_limit": 0,
+            "download_expire_seconds": 0,
+            "next_reset_epoch": 0,
+            "num_remaining": remaining,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_split_by_quota() {
+        assert_eq!(remaining_quota(&make_archive(0, 0)), None);
+        assert_eq!(remaining_quota(&make_archive(20, -1)), Some(0));
+
+        let quota = remaining_quota(&make_archive(20, 2));
+        assert_eq!(split_by_quota(vec![1, 2, 3], quota), (vec![1, 2], vec![3]));
+        assert_eq!(split_by_quota(vec![1, 2], quota), (vec![1, 2], vec![]));
+        assert_eq!(split_by_quota(vec![1, 2], None), (vec![1, 2], vec![]));
+    }
+}
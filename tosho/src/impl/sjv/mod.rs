@@ -15,14 +15,27 @@ pub(crate) enum SJVCommands {
     /// Authenticate tosho with your SJ/M account.
     Auth {
         /// Email to use
-        email: String,
+        ///
+        /// Uses `TOSHO_SJ_EMAIL` (or `TOSHO_SJ_EMAIL_FILE`) when omitted.
+        email: Option<String>,
         /// Password to use
-        password: String,
+        ///
+        /// Uses `TOSHO_SJ_PASSWORD` (or `TOSHO_SJ_PASSWORD_FILE`) when omitted.
+        password: Option<String>,
+        /// Read the password from a file instead
+        #[arg(long = "password-file", conflicts_with_all = ["password", "password_stdin"])]
+        password_file: Option<PathBuf>,
+        /// Read the password from the first line of stdin instead
+        #[arg(long = "password-stdin", conflicts_with = "password")]
+        password_stdin: bool,
         /// Mode to use
         #[arg(short, long, value_enum, default_value = "sj")]
         mode: crate::r#impl::sjv::config::SJDeviceMode,
         #[arg(short, long, value_enum, default_value = "web")]
         platform: crate::r#impl::sjv::config::DeviceType,
+        /// Never prompt, fail if a credential is missing and replace the existing account
+        #[arg(long = "no-input")]
+        no_input: bool,
     },
     /// Get an account information
     Account,
@@ -42,6 +55,12 @@ pub(crate) enum SJVCommands {
         /// Specify the end chapter ID to download
         #[arg(short = 'e', long, default_value = None)]
         end_until: Option<u32>,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
+        /// Persist the chapters over the read quota and resume the download after the quota reset
+        #[arg(long = "wait-reset")]
+        wait_reset: bool,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
@@ -64,6 +83,12 @@ pub(crate) enum SJVCommands {
         /// Specify the chapter ID to purchase (ex: 1,2,3,4,5)
         #[arg(short = 'c', long = "chapters", default_value = None, value_parser = parse_comma_number)]
         chapters: Option<CommaSeparatedNumber>,
+        /// Embed the source metadata (XMP/EXIF) into the downloaded pages
+        #[arg(long = "embed-metadata")]
+        embed_metadata: bool,
+        /// Persist the chapters over the read quota and resume the download after the quota reset
+        #[arg(long = "wait-reset")]
+        wait_reset: bool,
         /// Output directory to use
         #[arg(short = 'o', long = "output", default_value = None)]
         output: Option<PathBuf>,
@@ -0,0 +1,545 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::{Path, PathBuf},
+};
+
+use color_print::cformat;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    cli::ExitCode,
+    r#impl::{models::DroppedPagesDump, provenance::image_content},
+};
+
+use super::{read_title_folders, verify::parse_page_number};
+
+/// The known promotional pages of a title, generated by `tools dedupe`
+pub(crate) const PROMO_PAGES_FILE: &str = "_promo_pages.json";
+/// The report of every removed pages in a title
+const DEDUPE_REPORT_FILE: &str = "_dedupe_report.json";
+
+#[derive(Clone, Debug)]
+pub(crate) struct ToolsDedupeConfig {
+    /// Minimum amount of chapters a page need to appear in
+    pub(crate) min_chapters: usize,
+    /// Maximum hamming distance between two perceptual hashes
+    pub(crate) threshold: u32,
+    /// Remove the detected pages
+    pub(crate) remove: bool,
+}
+
+impl Default for ToolsDedupeConfig {
+    fn default() -> Self {
+        Self {
+            min_chapters: 3,
+            threshold: 4,
+            remove: false,
+        }
+    }
+}
+
+/// The hash of a single page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub(crate) enum PageHash {
+    /// A 64-bit difference hash, used for formats that can be decoded
+    Perceptual(u64),
+    /// A SHA-256 digest, used for formats that can't be decoded (e.g. AVIF)
+    Exact(String),
+}
+
+impl PageHash {
+    /// Check if two hashes are similar enough to be the same page
+    fn is_similar(&self, other: &PageHash, threshold: u32) -> bool {
+        match (self, other) {
+            (PageHash::Perceptual(a), PageHash::Perceptual(b)) => (a ^ b).count_ones() <= threshold,
+            (PageHash::Exact(a), PageHash::Exact(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Pages without any detail (e.g. blank pages) will be hashed to zero,
+    /// we never consider them as promotional pages.
+    fn is_blank(&self) -> bool {
+        matches!(self, PageHash::Perceptual(0))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PromoPage {
+    /// The hash of the page
+    hash: PageHash,
+    /// An example of where the page is found (`chapter/file`)
+    example: String,
+    /// The amount of chapters the page is found in
+    chapters: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PromoPages {
+    /// Maximum hamming distance used when comparing perceptual hashes
+    threshold: u32,
+    pages: Vec<PromoPage>,
+}
+
+impl PromoPages {
+    /// Load the known promotional pages from the title folder
+    pub(crate) fn load(title_dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(title_dir.join(PROMO_PAGES_FILE)).ok()?;
+
+        serde_json::from_str(&content).ok()
+    }
+
+    fn matches(&self, hash: &PageHash) -> bool {
+        !hash.is_blank()
+            && self
+                .pages
+                .iter()
+                .any(|page| page.hash.is_similar(hash, self.threshold))
+    }
+}
+
+/// Merge the removed pages into the title report
+fn write_dedupe_report(
+    title_dir: &Path,
+    removed: &BTreeMap<String, Vec<String>>,
+) -> std::io::Result<()> {
+    let report_path = title_dir.join(DEDUPE_REPORT_FILE);
+    let mut report: BTreeMap<String, Vec<String>> = std::fs::read_to_string(&report_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    for (chapter, pages) in removed {
+        let entry = report.entry(chapter.clone()).or_default();
+        for page in pages {
+            if !entry.contains(page) {
+                entry.push(page.clone());
+            }
+        }
+        entry.sort();
+    }
+
+    let content = serde_json::to_string_pretty(&report)?;
+    std::fs::write(report_path, content)
+}
+
+/// Compute the difference hash of an image.
+///
+/// The image is shrunk into 9x8 grayscale, then each bit is set
+/// when a pixel is brighter than the pixel on the right.
+fn difference_hash(image: &image::DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn hash_page(path: &Path) -> std::io::Result<PageHash> {
+    let data = std::fs::read(path)?;
+
+    match image::load_from_memory(&data) {
+        Ok(image) => Ok(PageHash::Perceptual(difference_hash(&image))),
+        Err(_) => {
+            // ignore the embedded metadata, so tagged pages still match
+            let digest = <Sha256 as Digest>::digest(image_content(&data));
+            let hex = digest
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>();
+            Ok(PageHash::Exact(hex))
+        }
+    }
+}
+
+/// Collect all the pages of a chapter folder
+fn collect_pages(chapter_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut pages: Vec<PathBuf> = std::fs::read_dir(chapter_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(parse_page_number)
+                    .is_some()
+        })
+        .collect();
+    pages.sort();
+
+    Ok(pages)
+}
+
+fn hash_chapter(chapter_dir: &Path) -> std::io::Result<Vec<(PathBuf, PageHash)>> {
+    let mut hashes = vec![];
+    for page in collect_pages(chapter_dir)? {
+        let hash = hash_page(&page)?;
+        hashes.push((page, hash));
+    }
+
+    Ok(hashes)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// A group of similar pages
+struct PageGroup {
+    hash: PageHash,
+    /// The chapter folder and page path
+    members: Vec<(String, PathBuf)>,
+}
+
+impl PageGroup {
+    fn chapter_count(&self) -> usize {
+        self.members
+            .iter()
+            .map(|(chapter, _)| chapter)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+fn group_pages(pages: Vec<(String, PathBuf, PageHash)>, threshold: u32) -> Vec<PageGroup> {
+    let mut groups: Vec<PageGroup> = vec![];
+    for (chapter, path, hash) in pages {
+        if hash.is_blank() {
+            continue;
+        }
+
+        match groups
+            .iter_mut()
+            .find(|group| group.hash.is_similar(&hash, threshold))
+        {
+            Some(group) => group.members.push((chapter, path)),
+            None => groups.push(PageGroup {
+                hash,
+                members: vec![(chapter, path)],
+            }),
+        }
+    }
+
+    groups
+}
+
+pub(crate) async fn tools_dedupe(
+    input_folder: &Path,
+    config: ToolsDedupeConfig,
+    console: &mut crate::term::Terminal,
+) -> ExitCode {
+    if !input_folder.is_dir() {
+        console.error(format!(
+            "The input folder is not a directory: {}",
+            input_folder.display()
+        ));
+        return 1;
+    }
+
+    let (chapter_dirs, edition_dirs) = match read_title_folders(input_folder) {
+        Ok(folders) => folders,
+        Err(err) => {
+            console.error(format!("Failed to read input folder: {err}"));
+            return 1;
+        }
+    };
+
+    let mut exit_code = 0;
+    if !chapter_dirs.is_empty() || edition_dirs.is_empty() {
+        exit_code = dedupe_title(input_folder, chapter_dirs, config.clone(), console).await;
+    }
+    // the language editions have their own promotional pages
+    for edition_dir in edition_dirs {
+        console.info(cformat!(
+            "Deduping edition <m,s>{}</>...",
+            edition_dir.display()
+        ));
+        let code = Box::pin(tools_dedupe(&edition_dir, config.clone(), console)).await;
+        exit_code = exit_code.max(code);
+    }
+
+    exit_code
+}
+
+/// Detect the repeated pages in the chapter folders of a single title (or language edition).
+async fn dedupe_title(
+    input_folder: &Path,
+    chapter_dirs: Vec<PathBuf>,
+    config: ToolsDedupeConfig,
+    console: &mut crate::term::Terminal,
+) -> ExitCode {
+    if chapter_dirs.len() < config.min_chapters {
+        console.warn(cformat!(
+            "Need at least <s>{}</> chapter folders to detect repeated pages, found <s>{}</>",
+            config.min_chapters,
+            chapter_dirs.len()
+        ));
+        return 1;
+    }
+
+    let progress = console.make_progress(chapter_dirs.len() as u64, Some("Hashing"));
+    let mut all_pages = vec![];
+    for chapter_dir in chapter_dirs {
+        let folder = file_name(&chapter_dir);
+        let result = tokio::task::spawn_blocking(move || hash_chapter(&chapter_dir)).await;
+        progress.inc(1);
+
+        match result {
+            Ok(Ok(hashes)) => {
+                all_pages.extend(
+                    hashes
+                        .into_iter()
+                        .map(|(path, hash)| (folder.clone(), path, hash)),
+                );
+            }
+            Ok(Err(err)) => console.error(format!("Failed to hash chapter {folder}: {err}")),
+            Err(err) => console.error(format!("Failed to hash chapter {folder}: {err}")),
+        }
+    }
+    progress.finish_with_message("Hashed");
+
+    // the `image` crate can't decode AVIF, so near-duplicates can't be detected for them
+    let exact_pages: Vec<&PathBuf> = all_pages
+        .iter()
+        .filter(|(_, _, hash)| matches!(hash, PageHash::Exact(_)))
+        .map(|(_, path, _)| path)
+        .collect();
+    if !exact_pages.is_empty() {
+        let formats = exact_pages
+            .iter()
+            .filter_map(|path| path.extension()?.to_str())
+            .map(|ext| ext.to_uppercase())
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>()
+            .join(", ");
+        console.warn(cformat!(
+            "<s>{}</> pages can't be decoded ({}), only exact duplicates are detected for them",
+            exact_pages.len(),
+            formats
+        ));
+    }
+
+    let promo_groups: Vec<PageGroup> = group_pages(all_pages, config.threshold)
+        .into_iter()
+        .filter(|group| group.chapter_count() >= config.min_chapters)
+        .collect();
+
+    if promo_groups.is_empty() {
+        console.info(cformat!("<g,s>No repeated pages found!</>"));
+        return 0;
+    }
+
+    console.info(cformat!(
+        "Found <m,s>{}</> repeated pages:",
+        promo_groups.len()
+    ));
+    for group in promo_groups.iter() {
+        let (chapter, path) = &group.members[0];
+        console.info(cformat!(
+            "  <s>{}/{}</> found in <s>{}</> chapters",
+            chapter,
+            file_name(path),
+            group.chapter_count()
+        ));
+    }
+
+    let promo_pages = PromoPages {
+        threshold: config.threshold,
+        pages: promo_groups
+            .iter()
+            .map(|group| {
+                let (chapter, path) = &group.members[0];
+                PromoPage {
+                    hash: group.hash.clone(),
+                    example: format!("{}/{}", chapter, file_name(path)),
+                    chapters: group.chapter_count(),
+                }
+            })
+            .collect(),
+    };
+
+    let promo_content = serde_json::to_string_pretty(&promo_pages).unwrap();
+    if let Err(err) = std::fs::write(input_folder.join(PROMO_PAGES_FILE), promo_content) {
+        console.error(format!("Failed to write {PROMO_PAGES_FILE}: {err}"));
+    }
+
+    if !config.remove {
+        console.info(cformat!(
+            "Use <s>--remove</> to remove the repeated pages, or <s>--drop-promo</> when downloading"
+        ));
+        return 0;
+    }
+
+    let mut removed: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for group in promo_groups {
+        for (chapter, path) in group.members {
+            match std::fs::remove_file(&path) {
+                Ok(_) => removed.entry(chapter).or_default().push(file_name(&path)),
+                Err(err) => {
+                    console.error(format!("Failed to remove page {}: {err}", path.display()))
+                }
+            }
+        }
+    }
+
+    let mut removed_count = 0;
+    for (chapter, pages) in removed.iter() {
+        removed_count += pages.len();
+        console.info(cformat!(
+            "  Removed <s>{}</> from <m,s>{}</>",
+            pages.join(", "),
+            chapter
+        ));
+        if let Err(err) = DroppedPagesDump::append(&input_folder.join(chapter), pages) {
+            console.error(format!(
+                "Failed to write {} for {chapter}: {err}",
+                DroppedPagesDump::FILE_NAME
+            ));
+        }
+    }
+
+    if let Err(err) = write_dedupe_report(input_folder, &removed) {
+        console.error(format!("Failed to write {DEDUPE_REPORT_FILE}: {err}"));
+    }
+
+    console.info(cformat!(
+        "Removed <s>{}</> pages from <s>{}</> chapters, see <s>{}</> for the full report",
+        removed_count,
+        removed.len(),
+        DEDUPE_REPORT_FILE
+    ));
+
+    0
+}
+
+/// Drop the known promotional pages from a freshly downloaded chapter.
+pub(crate) async fn drop_promo_pages(
+    promo_pages: &PromoPages,
+    title_dir: &Path,
+    chapter_dir: &Path,
+    console: &crate::term::Terminal,
+) {
+    let promo = promo_pages.clone();
+    let ch_dir = chapter_dir.to_path_buf();
+    let result = tokio::task::spawn_blocking(move || -> std::io::Result<Vec<String>> {
+        let mut dropped = vec![];
+        for (path, hash) in hash_chapter(&ch_dir)? {
+            if promo.matches(&hash) {
+                std::fs::remove_file(&path)?;
+                dropped.push(file_name(&path));
+            }
+        }
+
+        if !dropped.is_empty() {
+            DroppedPagesDump::append(&ch_dir, &dropped)?;
+        }
+        Ok(dropped)
+    })
+    .await;
+
+    let dropped = match result {
+        Ok(Ok(dropped)) => dropped,
+        Ok(Err(err)) => {
+            console.error(format!("    Failed to drop promotional pages: {err}"));
+            return;
+        }
+        Err(err) => {
+            console.error(format!("    Failed to drop promotional pages: {err}"));
+            return;
+        }
+    };
+
+    if dropped.is_empty() {
+        return;
+    }
+
+    console.info(cformat!(
+        "   Dropped <s>{}</> promotional pages: {}",
+        dropped.len(),
+        dropped.join(", ")
+    ));
+
+    let removed = BTreeMap::from([(file_name(chapter_dir), dropped)]);
+    if let Err(err) = write_dedupe_report(title_dir, &removed) {
+        console.error(format!("    Failed to write {DEDUPE_REPORT_FILE}: {err}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_hash_similarity() {
+        let base = PageHash::Perceptual(0b1011_0110);
+        assert!(base.is_similar(&PageHash::Perceptual(0b1011_0111), 1));
+        assert!(!base.is_similar(&PageHash::Perceptual(0b0100_1001), 4));
+
+        let exact = PageHash::Exact("abcdef".to_string());
+        assert!(exact.is_similar(&PageHash::Exact("abcdef".to_string()), 0));
+        assert!(!exact.is_similar(&base, 64));
+    }
+
+    #[test]
+    fn test_difference_hash() {
+        let gradient =
+            image::DynamicImage::ImageLuma8(image::GrayImage::from_fn(90, 80, |x, _| {
+                image::Luma([255 - (x as u8 * 2)])
+            }));
+        let blank = image::DynamicImage::ImageLuma8(image::GrayImage::from_pixel(
+            90,
+            80,
+            image::Luma([255]),
+        ));
+
+        // brightness decreases to the right, so every bit is set
+        assert_eq!(difference_hash(&gradient), u64::MAX);
+        assert!(PageHash::Perceptual(difference_hash(&blank)).is_blank());
+    }
+
+    #[test]
+    fn test_group_pages() {
+        let pages = vec![
+            (
+                "1".to_string(),
+                PathBuf::from("1/p000.png"),
+                PageHash::Perceptual(0xF0F0),
+            ),
+            (
+                "1".to_string(),
+                PathBuf::from("1/p001.png"),
+                PageHash::Perceptual(0xFFFF_0000),
+            ),
+            (
+                "2".to_string(),
+                PathBuf::from("2/p000.png"),
+                PageHash::Perceptual(0xF0F1),
+            ),
+            (
+                "3".to_string(),
+                PathBuf::from("3/p000.png"),
+                PageHash::Perceptual(0),
+            ),
+        ];
+
+        let groups = group_pages(pages, 2);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].chapter_count(), 2);
+        assert_eq!(groups[1].chapter_count(), 1);
+    }
+}
@@ -1,9 +1,11 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::Subcommand;
 
 pub(crate) mod cache;
+pub(crate) mod dedupe;
 pub(crate) mod merger;
+pub(crate) mod verify;
 
 #[derive(Subcommand)]
 pub(crate) enum ToolsCommands {
@@ -20,6 +22,22 @@ pub(crate) enum ToolsCommands {
     },
     /// Clear saved cache for some sources
     ClearCache,
+    /// Detect repeated promotional pages across chapters of a title
+    ///
+    /// The detected pages are saved to _promo_pages.json to be used with `--drop-promo` when downloading.
+    Dedupe {
+        /// Input directory to use that contains the chapters
+        input_folder: PathBuf,
+        /// Minimum amount of chapters a page need to appear in to be considered as promotional
+        #[arg(short = 'm', long, default_value_t = 3)]
+        min_chapters: usize,
+        /// Maximum difference between two pages to be considered as the same page (0-64)
+        #[arg(short = 't', long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(0..=64))]
+        threshold: u32,
+        /// Remove the detected pages and write a report to _dedupe_report.json
+        #[arg(short = 'r', long)]
+        remove: bool,
+    },
     /// Merge multiple folders of split chapters into one folder
     ///
     /// The chapter merging would depends on _info.json file generated automatically.
@@ -30,4 +48,45 @@ pub(crate) enum ToolsCommands {
         #[arg(short = 's', long = "ignore-manual")]
         ignore_manual_merge: bool,
     },
+    /// Verify the integrity of downloaded chapters
+    ///
+    /// Decode every page and check for missing pages, the chapter mapping would depends on _info.json file.
+    Verify {
+        /// Input directory to use that contains the _info.json file and chapters
+        input_folder: PathBuf,
+        /// Re-download broken chapters through the original source
+        #[arg(short = 'r', long)]
+        redownload: bool,
+        /// Account ID to use when re-downloading
+        #[arg(short = 'a', long = "account", default_value = None)]
+        account_id: Option<String>,
+        /// The source of the title, used when it can't be detected from the folder
+        #[arg(short = 's', long = "source", value_enum, default_value = None)]
+        source: Option<crate::r#impl::models::SourceDump>,
+    },
+}
+
+/// Read the chapter folders of a title.
+///
+/// A subfolder with its own `_info.json` is a title of its own, e.g. the M+ language
+/// editions in `MP_{title_id}/{language}`, those are returned separately.
+fn read_title_folders(input_folder: &Path) -> std::io::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut chapter_dirs = vec![];
+    let mut edition_dirs = vec![];
+    for entry in std::fs::read_dir(input_folder)?.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        if path.join("_info.json").is_file() {
+            edition_dirs.push(path);
+        } else {
+            chapter_dirs.push(path);
+        }
+    }
+
+    chapter_dirs.sort();
+    edition_dirs.sort();
+    Ok((chapter_dirs, edition_dirs))
 }
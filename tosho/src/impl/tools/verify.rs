@@ -0,0 +1,837 @@
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use color_print::cformat;
+
+use crate::{
+    cli::ExitCode,
+    config::ConfigImpl,
+    r#impl::{
+        Implementations, clean_filename,
+        client::select_single_account,
+        models::{DroppedPagesDump, IdDump, MangaDetailDump, SourceDump},
+    },
+};
+
+use super::read_title_folders;
+
+/// Formats that the `image` crate can't decode, we only check the header for these.
+const HEADER_ONLY_EXT: [&str; 4] = ["avif", "jxl", "heif", "heic"];
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ToolsVerifyConfig {
+    /// Re-download broken chapters through the original source
+    pub(crate) redownload: bool,
+    /// Account ID to use when re-downloading
+    pub(crate) account_id: Option<String>,
+    /// The source to use when it can't be detected from the folder
+    pub(crate) source: Option<SourceDump>,
+    /// Proxy to use when re-downloading
+    pub(crate) proxy: Option<reqwest::Proxy>,
+}
+
+/// The problem found on a single page
+#[derive(Debug, Clone, PartialEq)]
+enum PageIssue {
+    /// The file is zero-byte
+    Empty,
+    /// The file is missing the end marker of the format
+    Truncated,
+    /// The file failed to be decoded
+    Corrupt(String),
+}
+
+impl std::fmt::Display for PageIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageIssue::Empty => write!(f, "zero-byte file"),
+            PageIssue::Truncated => write!(f, "truncated file"),
+            PageIssue::Corrupt(err) => write!(f, "corrupt file ({err})"),
+        }
+    }
+}
+
+struct ChapterReport {
+    /// The chapter folder name
+    folder: String,
+    /// The chapter name from `_info.json`
+    name: Option<String>,
+    /// The chapter ID from `_info.json` or the folder name
+    id: Option<IdDump>,
+    /// Total pages found
+    pages: usize,
+    /// Pages dropped by `tools dedupe`
+    dropped: usize,
+    /// The expected page count from `_info.json`
+    expected: Option<u64>,
+    /// Pages that are broken
+    broken: Vec<(PathBuf, PageIssue)>,
+    /// Missing page numbers
+    missing: Vec<u64>,
+}
+
+impl ChapterReport {
+    fn is_healthy(&self) -> bool {
+        self.pages > 0 && self.broken.is_empty() && self.missing.is_empty() && !self.is_incomplete()
+    }
+
+    /// Check if the chapter has less pages than the expected page count.
+    fn is_incomplete(&self) -> bool {
+        self.expected
+            .is_some_and(|expected| ((self.pages + self.dropped) as u64) < expected)
+    }
+
+    fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} ({})", name, self.folder),
+            None => self.folder.clone(),
+        }
+    }
+}
+
+/// Parse the page number from the file stem.
+///
+/// We use `p000` (0-indexed) for most sources and `i_0001` (1-indexed) for NI.
+pub(super) fn parse_page_number(stem: &str) -> Option<u64> {
+    if let Some(number) = stem.strip_prefix("i_") {
+        number.parse::<u64>().ok()
+    } else if let Some(number) = stem.strip_prefix('p') {
+        number.parse::<u64>().ok()
+    } else {
+        None
+    }
+}
+
+/// Find missing page numbers between the lowest and highest page number.
+///
+/// When the expected page count is known, the range is extended to cover it,
+/// so missing leading and trailing pages are also found.
+fn find_page_gaps(numbers: &[u64], expected: Option<(u64, u64)>) -> Vec<u64> {
+    let (first, last) = match (numbers.iter().min(), numbers.iter().max(), expected) {
+        (Some(&min), Some(&max), Some((start, count))) if count > 0 => {
+            (min.min(start), max.max(start + count - 1))
+        }
+        (Some(&min), Some(&max), _) => (min, max),
+        _ => return vec![],
+    };
+
+    (first..=last)
+        .filter(|num| !numbers.contains(num))
+        .collect()
+}
+
+fn check_header_only(extension: &str, data: &[u8]) -> Option<PageIssue> {
+    let valid = match extension {
+        // ISO-BMFF: ....ftyp
+        "avif" | "heif" | "heic" => data.len() > 12 && &data[4..8] == b"ftyp",
+        // Naked codestream or ISO-BMFF container
+        "jxl" => data.starts_with(&[0xFF, 0x0A]) || data.starts_with(b"\0\0\0\x0cJXL "),
+        _ => true,
+    };
+
+    if valid {
+        None
+    } else {
+        Some(PageIssue::Corrupt("invalid file header".to_string()))
+    }
+}
+
+fn verify_page(path: &Path) -> Option<PageIssue> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(err) => return Some(PageIssue::Corrupt(err.to_string())),
+    };
+
+    if data.is_empty() {
+        return Some(PageIssue::Empty);
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if HEADER_ONLY_EXT.contains(&extension.as_str()) {
+        return check_header_only(&extension, &data);
+    }
+
+    let reader = match image::ImageReader::new(Cursor::new(&data)).with_guessed_format() {
+        Ok(reader) => reader,
+        Err(err) => return Some(PageIssue::Corrupt(err.to_string())),
+    };
+
+    // Most decoder are lenient with truncated data, so check the end marker manually
+    let trimmed = match data.iter().rposition(|&b| b != 0) {
+        Some(pos) => &data[..=pos],
+        None => return Some(PageIssue::Empty),
+    };
+    match reader.format() {
+        Some(image::ImageFormat::Jpeg) if !trimmed.ends_with(&[0xFF, 0xD9]) => {
+            return Some(PageIssue::Truncated);
+        }
+        Some(image::ImageFormat::Png) if !trimmed.ends_with(b"IEND\xAE\x42\x60\x82") => {
+            return Some(PageIssue::Truncated);
+        }
+        Some(format) if !format.reading_enabled() => return None,
+        None => return Some(PageIssue::Corrupt("unknown image format".to_string())),
+        _ => {}
+    }
+
+    match reader.decode() {
+        Ok(_) => None,
+        Err(err) => Some(PageIssue::Corrupt(err.to_string())),
+    }
+}
+
+fn verify_chapter(
+    chapter_dir: &Path,
+    folder: String,
+    expected: Option<u64>,
+) -> std::io::Result<ChapterReport> {
+    let mut page_numbers = vec![];
+    // NI pages are 1-indexed, everything else is 0-indexed
+    let mut first_page = 0;
+    let mut report = ChapterReport {
+        folder,
+        name: None,
+        id: None,
+        pages: 0,
+        dropped: 0,
+        expected,
+        broken: vec![],
+        missing: vec![],
+    };
+
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(chapter_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+
+        let Some(number) = parse_page_number(stem) else {
+            // not a page (e.g. cover, frames.json)
+            continue;
+        };
+
+        if stem.starts_with("i_") {
+            first_page = 1;
+        }
+        report.pages += 1;
+        page_numbers.push(number);
+        if let Some(issue) = verify_page(&path) {
+            report.broken.push((path, issue));
+        }
+    }
+
+    // pages dropped by `tools dedupe` are not considered missing
+    let dropped_pages = DroppedPagesDump::load(chapter_dir).pages;
+    report.dropped = dropped_pages.len();
+    page_numbers.extend(
+        dropped_pages
+            .iter()
+            .filter_map(|page| Path::new(page).file_stem()?.to_str())
+            .filter_map(parse_page_number),
+    );
+
+    report.missing = find_page_gaps(&page_numbers, expected.map(|count| (first_page, count)));
+    Ok(report)
+}
+
+async fn read_info_json(input_folder: &Path) -> Option<MangaDetailDump> {
+    let info_json = input_folder.join("_info.json");
+    let content = tokio::fs::read_to_string(info_json).await.ok()?;
+
+    serde_json::from_str(&content).ok()
+}
+
+/// Use the folder name as the title ID, KM, MU! and AM folders have no prefix.
+fn title_id_from_folder(input_folder: &Path) -> Option<IdDump> {
+    let folder_name = input_folder.file_name()?.to_str()?;
+    let title_id = folder_name
+        .split_once('_')
+        .map_or(folder_name, |(_, title_id)| title_id);
+    Some(IdDump::from(title_id.to_string()))
+}
+
+/// Guess the source and title ID from the `_info.json` or the folder name.
+fn guess_source(
+    input_folder: &Path,
+    info: Option<&MangaDetailDump>,
+) -> Option<(SourceDump, Option<IdDump>)> {
+    if let Some(info) = info
+        && let Some(source) = info.source
+    {
+        return Some((source, info.title_id.clone()));
+    }
+
+    let folder_name = input_folder.file_name()?.to_str()?;
+    let (source, title_id) = if let Some(title_id) = folder_name.strip_prefix("MP_") {
+        (SourceDump::Mplus, title_id)
+    } else if let Some(title_id) = folder_name.strip_prefix("SJV_") {
+        (SourceDump::Sjv, title_id)
+    } else if let Some(title_id) = folder_name.strip_prefix("RB_") {
+        (SourceDump::Rbean, title_id)
+    } else if let Some(title_id) = folder_name.strip_prefix("NI_") {
+        (SourceDump::Nids, title_id)
+    } else {
+        return None;
+    };
+
+    Some((source, Some(IdDump::from(title_id.to_string()))))
+}
+
+pub(crate) async fn tools_verify(
+    input_folder: &Path,
+    config: ToolsVerifyConfig,
+    console: &mut crate::term::Terminal,
+) -> ExitCode {
+    if !input_folder.is_dir() {
+        console.error(format!(
+            "The input folder is not a directory: {}",
+            input_folder.display()
+        ));
+        return 1;
+    }
+
+    let (chapter_dirs, edition_dirs) = match read_title_folders(input_folder) {
+        Ok(folders) => folders,
+        Err(err) => {
+            console.error(format!("Failed to read input folder: {err}"));
+            return 1;
+        }
+    };
+
+    if chapter_dirs.is_empty() && edition_dirs.is_empty() {
+        console.warn("No chapter folders found!");
+        return 1;
+    }
+
+    let mut exit_code = 0;
+    if !chapter_dirs.is_empty() {
+        exit_code = verify_title(input_folder, chapter_dirs, config.clone(), console).await;
+    }
+    for edition_dir in edition_dirs {
+        console.info(cformat!(
+            "Verifying edition <m,s>{}</>...",
+            edition_dir.display()
+        ));
+        let code = Box::pin(tools_verify(&edition_dir, config.clone(), console)).await;
+        exit_code = exit_code.max(code);
+    }
+
+    exit_code
+}
+
+/// Verify the chapter folders of a single title (or language edition).
+async fn verify_title(
+    input_folder: &Path,
+    chapter_dirs: Vec<PathBuf>,
+    config: ToolsVerifyConfig,
+    console: &mut crate::term::Terminal,
+) -> ExitCode {
+    let info_json = read_info_json(input_folder).await;
+    match &info_json {
+        Some(info) => console.info(cformat!(
+            "Verifying <m,s>{}</> with <s>{}</> chapters from _info.json",
+            info.title_name,
+            info.chapters.len()
+        )),
+        None => console.warn("No valid _info.json found, verifying all chapter folders"),
+    }
+
+    let progress = console.make_progress(chapter_dirs.len() as u64, Some("Verifying"));
+    let mut reports = vec![];
+    for chapter_dir in chapter_dirs {
+        let folder = chapter_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let chapter_info = info_json.as_ref().and_then(|info| {
+            info.chapters
+                .iter()
+                .find(|ch| ch.id.to_string() == folder || clean_filename(&ch.main_name) == folder)
+        });
+
+        let chapter_folder = folder.clone();
+        let expected = chapter_info.and_then(|ch| ch.pages);
+        let result = tokio::task::spawn_blocking(move || {
+            verify_chapter(&chapter_dir, chapter_folder, expected)
+        })
+        .await;
+        progress.inc(1);
+
+        let mut report = match result {
+            Ok(Ok(report)) => report,
+            Ok(Err(err)) => {
+                console.error(format!("Failed to read chapter folder {folder}: {err}"));
+                continue;
+            }
+            Err(err) => {
+                console.error(format!("Failed to verify chapter folder {folder}: {err}"));
+                continue;
+            }
+        };
+
+        report.name = chapter_info.map(|ch| ch.main_name.clone());
+        report.id = match chapter_info {
+            Some(ch) => Some(ch.id.clone()),
+            None => folder.parse::<u64>().ok().map(IdDump::from),
+        };
+        reports.push(report);
+    }
+    progress.finish_with_message("Verified");
+
+    let total_pages: usize = reports.iter().map(|r| r.pages).sum();
+    let unhealthy: Vec<&ChapterReport> = reports.iter().filter(|r| !r.is_healthy()).collect();
+
+    console.info(cformat!(
+        "Checked <s>{}</> chapters with <s>{}</> pages",
+        reports.len(),
+        total_pages
+    ));
+
+    if unhealthy.is_empty() {
+        console.info(cformat!("<g,s>All chapters are healthy!</>"));
+        return 0;
+    }
+
+    console.warn(cformat!(
+        "Found <r,s>{}</> broken chapters:",
+        unhealthy.len()
+    ));
+    for report in unhealthy.iter() {
+        console.warn(cformat!("  <m,s>{}</>", report.display_name()));
+        if report.pages == 0 {
+            console.warn("   - No pages found");
+        }
+        if let Some(expected) = report.expected
+            && report.is_incomplete()
+        {
+            console.warn(cformat!(
+                "   - Expected <s>{}</> pages, found <s>{}</>",
+                expected,
+                report.pages + report.dropped
+            ));
+        }
+        for (path, issue) in report.broken.iter() {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            console.warn(cformat!("   - <s>{}</>: {}", file_name, issue));
+        }
+        if !report.missing.is_empty() {
+            let missing = report
+                .missing
+                .iter()
+                .map(|num| num.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            console.warn(cformat!("   - Missing page number: <s>{}</>", missing));
+        }
+    }
+
+    if !config.redownload {
+        return 1;
+    }
+
+    let guessed = guess_source(input_folder, info_json.as_ref());
+    let (source, title_id) = match (guessed, config.source) {
+        (Some((_, title_id)), Some(source)) => (source, title_id),
+        (Some(guessed), None) => guessed,
+        (None, Some(source)) => (
+            source,
+            info_json
+                .as_ref()
+                .and_then(|info| info.title_id.clone())
+                .or_else(|| title_id_from_folder(input_folder)),
+        ),
+        (None, None) => {
+            console
+                .error("Unable to determine the source of this folder, use --source to specify it");
+            return 1;
+        }
+    };
+
+    let mut chapter_ids = vec![];
+    for report in unhealthy {
+        match &report.id {
+            Some(id) => {
+                // Remove the broken pages so the downloader does not skip the chapter
+                for (path, _) in report.broken.iter() {
+                    if let Err(err) = tokio::fs::remove_file(path).await {
+                        console.error(format!(
+                            "Failed to remove broken page {}: {err}",
+                            path.display()
+                        ));
+                    }
+                }
+                chapter_ids.push(id.clone());
+            }
+            None => console.warn(cformat!(
+                "Unable to determine chapter ID for <m,s>{}</>, skipping",
+                report.folder
+            )),
+        }
+    }
+
+    if chapter_ids.is_empty() {
+        console.warn("No chapters to be re-downloaded");
+        return 1;
+    }
+
+    console.info(cformat!(
+        "Re-downloading <s>{}</> chapters...",
+        chapter_ids.len()
+    ));
+
+    match redownload_chapters(
+        source,
+        title_id,
+        chapter_ids,
+        input_folder,
+        info_json.as_ref(),
+        config,
+        console,
+    )
+    .await
+    {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            console.error(format!("Failed to re-download chapters: {err}"));
+            1
+        }
+    }
+}
+
+fn select_account(
+    implementation: Implementations,
+    config: &ToolsVerifyConfig,
+    console: &crate::term::Terminal,
+) -> color_eyre::Result<ConfigImpl> {
+    select_single_account(config.account_id.as_deref(), implementation, console)
+        .ok_or_else(|| color_eyre::eyre::eyre!("No account selected"))
+}
+
+fn number_ids(chapter_ids: &[IdDump]) -> Vec<usize> {
+    chapter_ids
+        .iter()
+        .filter_map(|id| match id {
+            IdDump::Number(num) => Some(*num as usize),
+            IdDump::Uuid(_) => None,
+        })
+        .collect()
+}
+
+/// Get the numeric title ID used by most sources.
+fn parse_title_number(
+    title_id: &IdDump,
+    input_folder: &Path,
+    info: Option<&MangaDetailDump>,
+) -> color_eyre::Result<u64> {
+    match title_id {
+        IdDump::Number(num) => Ok(*num),
+        IdDump::Uuid(uuid) => uuid.parse::<u64>().map_err(|_| {
+            let origin = match info.and_then(|info| info.title_id.as_ref()) {
+                Some(info_id) if info_id == title_id => input_folder.join("_info.json"),
+                _ => input_folder.to_path_buf(),
+            };
+            color_eyre::eyre::eyre!("Invalid title ID `{uuid}` from {}", origin.display())
+        }),
+    }
+}
+
+async fn redownload_chapters(
+    source: SourceDump,
+    title_id: Option<IdDump>,
+    chapter_ids: Vec<IdDump>,
+    input_folder: &Path,
+    info: Option<&MangaDetailDump>,
+    config: ToolsVerifyConfig,
+    console: &mut crate::term::Terminal,
+) -> color_eyre::Result<ExitCode> {
+    use crate::r#impl::{amap, client, kmkc, mplus, musq, nids, rbean, sjv};
+
+    // All sources put the title folder inside the output directory
+    let output_dir = input_folder
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let Some(title_id) = title_id else {
+        color_eyre::eyre::bail!("Unable to determine the title ID");
+    };
+    let title_number = || parse_title_number(&title_id, input_folder, info);
+
+    let exit_code = match source {
+        SourceDump::Musq => {
+            let ConfigImpl::Musq(account) =
+                select_account(Implementations::Musq, &config, console)?
+            else {
+                unreachable!()
+            };
+            let client = client::make_musq_client(&account)?;
+            let client = match config.proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let dl_config = musq::download::MUDownloadCliConfig {
+                no_input: true,
+                chapter_ids: number_ids(&chapter_ids),
+                ..Default::default()
+            };
+            musq::download::musq_download(title_number()?, dl_config, output_dir, &client, console)
+                .await
+        }
+        SourceDump::Kmkc => {
+            let ConfigImpl::Kmkc(account) =
+                select_account(Implementations::Kmkc, &config, console)?
+            else {
+                unreachable!()
+            };
+            let client = client::make_kmkc_client(&account.clone().into())?;
+            let client = match config.proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let dl_config = kmkc::download::KMDownloadCliConfig {
+                no_input: true,
+                chapter_ids: number_ids(&chapter_ids),
+                ..Default::default()
+            };
+            kmkc::download::kmkc_download(
+                title_number()? as u32,
+                dl_config,
+                output_dir,
+                &client,
+                &account,
+                console,
+            )
+            .await
+        }
+        SourceDump::Amap => {
+            let ConfigImpl::Amap(account) =
+                select_account(Implementations::Amap, &config, console)?
+            else {
+                unreachable!()
+            };
+            let client = client::make_amap_client(&account.clone().into())?;
+            let client = match config.proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let dl_config = amap::download::AMDownloadCliConfig {
+                no_input: true,
+                chapter_ids: number_ids(&chapter_ids),
+                ..Default::default()
+            };
+            amap::download::amap_download(
+                title_number()?,
+                dl_config,
+                output_dir,
+                &client,
+                &account,
+                console,
+            )
+            .await
+        }
+        SourceDump::Sjv => {
+            let ConfigImpl::Sjv(account) = select_account(Implementations::Sjv, &config, console)?
+            else {
+                unreachable!()
+            };
+            let client = client::make_sjv_client(&account)?;
+            let client = match config.proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let dl_config = sjv::download::SJDownloadCliConfig {
+                no_input: true,
+                chapter_ids: number_ids(&chapter_ids),
+                ..Default::default()
+            };
+            sjv::download::sjv_download(
+                crate::r#impl::parser::NumberOrString::Number(title_number()? as usize),
+                dl_config,
+                output_dir,
+                &client,
+                console,
+            )
+            .await
+        }
+        SourceDump::Rbean => {
+            let ConfigImpl::Rbean(account) =
+                select_account(Implementations::Rbean, &config, console)?
+            else {
+                unreachable!()
+            };
+            let client = client::make_rbean_client(&account)?;
+            let mut client = match config.proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+            client.set_expiry_at(Some(account.expiry));
+
+            let dl_config = rbean::download::RBDownloadConfigCli {
+                no_input: true,
+                chapter_ids: chapter_ids.iter().map(|id| id.to_string()).collect(),
+                ..Default::default()
+            };
+            rbean::download::rbean_download(
+                &title_id.to_string(),
+                dl_config,
+                output_dir,
+                &mut client,
+                &account,
+                console,
+            )
+            .await
+        }
+        SourceDump::Mplus => {
+            let ConfigImpl::Mplus(account) =
+                select_account(Implementations::Mplus, &config, console)?
+            else {
+                unreachable!()
+            };
+            // use the language the title has been downloaded in, the language editions
+            // are saved in a folder named by the language code
+            let language = info
+                .and_then(|info| info.language.as_deref())
+                .or_else(|| input_folder.file_name().and_then(|name| name.to_str()))
+                .and_then(mplus::language_from_code)
+                .unwrap_or_else(|| mplus::MPlusLanguage::default().into());
+            let client = client::make_mplus_client(&account, language)?;
+            let client = match config.proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let dl_config = mplus::download::MPDownloadCliConfig {
+                no_input: true,
+                chapter_ids: number_ids(&chapter_ids),
+                title_dir: Some(input_folder.to_path_buf()),
+                ..Default::default()
+            };
+            mplus::download::mplus_download(
+                title_number()?,
+                dl_config,
+                output_dir,
+                &client,
+                console,
+            )
+            .await
+        }
+        SourceDump::Nids => {
+            let ConfigImpl::Nids(account) =
+                select_account(Implementations::Nids, &config, console)?
+            else {
+                unreachable!()
+            };
+            let client = client::make_nids_client(&account)?;
+            let client = match config.proxy {
+                Some(proxy) => client.with_proxy(proxy)?,
+                None => client,
+            };
+
+            let mut exit_code = 0;
+            for issue_id in number_ids(&chapter_ids) {
+                let dl_config = nids::download::NIDownloadCliConfig {
+                    output: Some(input_folder.join(issue_id.to_string())),
+                    ..Default::default()
+                };
+                let result = nids::download::nids_download(
+                    issue_id as u32,
+                    dl_config,
+                    output_dir.clone(),
+                    &client,
+                    console,
+                )
+                .await;
+                exit_code = exit_code.max(result);
+            }
+            exit_code
+        }
+    };
+
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_page_number() {
+        assert_eq!(parse_page_number("p000"), Some(0));
+        assert_eq!(parse_page_number("p012"), Some(12));
+        assert_eq!(parse_page_number("i_0001"), Some(1));
+        assert_eq!(parse_page_number("cover"), None);
+        assert_eq!(parse_page_number("frames"), None);
+    }
+
+    #[test]
+    fn test_read_title_folders() {
+        let dir = std::env::temp_dir().join("tosho-verify-title-folders-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("100")).unwrap();
+        std::fs::create_dir_all(dir.join("eng").join("101")).unwrap();
+        std::fs::write(dir.join("eng").join("_info.json"), b"{}").unwrap();
+        std::fs::write(dir.join("_languages.json"), b"{}").unwrap();
+
+        let (chapter_dirs, edition_dirs) = read_title_folders(&dir).unwrap();
+        assert_eq!(chapter_dirs, vec![dir.join("100")]);
+        assert_eq!(edition_dirs, vec![dir.join("eng")]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_title_number() {
+        let folder = Path::new("MP_abc");
+        assert_eq!(
+            parse_title_number(&IdDump::from("100".to_string()), folder, None).unwrap(),
+            100
+        );
+
+        let err = parse_title_number(&IdDump::from("abc".to_string()), folder, None).unwrap_err();
+        assert!(err.to_string().contains("MP_abc"));
+
+        let info = MangaDetailDump::new("Title".to_string(), "Author".to_string(), vec![])
+            .with_source(SourceDump::Mplus, "abc".to_string());
+        let err =
+            parse_title_number(&IdDump::from("abc".to_string()), folder, Some(&info)).unwrap_err();
+        assert!(err.to_string().contains("_info.json"));
+    }
+
+    #[test]
+    fn test_find_page_gaps() {
+        assert!(find_page_gaps(&[], None).is_empty());
+        assert!(find_page_gaps(&[0, 1, 2, 3], None).is_empty());
+        assert_eq!(find_page_gaps(&[0, 1, 4, 3], None), vec![2]);
+        assert_eq!(find_page_gaps(&[1, 5], None), vec![2, 3, 4]);
+        // missing leading and trailing pages
+        assert_eq!(find_page_gaps(&[1, 2], Some((0, 5))), vec![0, 3, 4]);
+        assert_eq!(find_page_gaps(&[2], Some((1, 3))), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_check_header_only() {
+        let avif = b"\0\0\0\x1cftypavif\0\0\0\0";
+        assert_eq!(check_header_only("avif", avif), None);
+        assert!(check_header_only("avif", b"garbage data here").is_some());
+        assert_eq!(check_header_only("jxl", &[0xFF, 0x0A, 0x00]), None);
+        assert!(check_header_only("jxl", b"not a jxl").is_some());
+    }
+}
@@ -60,6 +60,7 @@ use r#impl::Implementations;
 use r#impl::amap::AMAPCommands;
 use r#impl::amap::download::AMDownloadCliConfig;
 use r#impl::client::select_single_account;
+use r#impl::credentials::CredentialArg;
 use r#impl::mplus::MPlusCommands;
 use r#impl::mplus::download::MPDownloadCliConfig;
 use r#impl::nids::NIDSCommands;
@@ -102,7 +103,7 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
     let t = term::get_console(cli.verbose);
     let mut t_mut = term::get_console(cli.verbose);
 
-    let parsed_proxy = match cli.proxy {
+    let parsed_proxy = match &cli.proxy {
         Some(proxy) => match reqwest::Proxy::all(proxy) {
             Ok(proxy) => Some(proxy),
             Err(e) => {
@@ -127,11 +128,85 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             account_id,
             subcommand,
         } => {
+            // shared by the single account and the account pool auto download
+            let auto_config = match &subcommand {
+                MUSQCommands::AutoDownload {
+                    no_purchase,
+                    start_from,
+                    end_until,
+                    no_paid_coins,
+                    no_xp_coins,
+                    quality,
+                    drop_promo,
+                    embed_metadata,
+                    only_check_folder,
+                    ..
+                } => MUDownloadCliConfig {
+                    auto_purchase: !no_purchase,
+                    no_input: true,
+                    quality: quality.clone(),
+                    start_from: *start_from,
+                    end_at: *end_until,
+                    no_paid_point: *no_paid_coins,
+                    no_xp_point: *no_xp_coins,
+                    only_check_folder: *only_check_folder,
+                    drop_promo: *drop_promo,
+                    embed_metadata: *embed_metadata,
+                    ..Default::default()
+                },
+                _ => MUDownloadCliConfig::default(),
+            };
+
             let early_exit = match subcommand.clone() {
-                MUSQCommands::Auth { session_id, r#type } => {
-                    Some(r#impl::musq::accounts::musq_auth_session(session_id, r#type, &t).await)
-                }
+                MUSQCommands::Auth {
+                    session_id,
+                    session_id_file,
+                    session_id_stdin,
+                    r#type,
+                    no_input,
+                } => Some(
+                    r#impl::musq::accounts::musq_auth_session(
+                        CredentialArg::new(session_id, session_id_file, session_id_stdin),
+                        r#type,
+                        no_input,
+                        &t,
+                    )
+                    .await,
+                ),
                 MUSQCommands::Accounts => Some(r#impl::musq::accounts::musq_accounts(&t)),
+                MUSQCommands::AutoDownload {
+                    title_id,
+                    output,
+                    pool: Some(pool),
+                    ..
+                } => {
+                    let source = r#impl::musq::pool::MUPool {
+                        title_id,
+                        dl_config: auto_config.clone(),
+                        output_dir: output.unwrap_or_else(get_default_download_dir),
+                    };
+
+                    Some(
+                        r#impl::pool::pool_download(
+                            &source,
+                            &pool,
+                            parsed_proxy.clone(),
+                            &mut t_mut,
+                        )
+                        .await,
+                    )
+                }
+                MUSQCommands::Pool {
+                    name,
+                    account_ids,
+                    remove,
+                } => Some(r#impl::pool::account_pool_manage(
+                    Implementations::Musq,
+                    name,
+                    account_ids,
+                    remove,
+                    &t,
+                )),
                 _ => None,
             };
 
@@ -160,40 +235,18 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             };
 
             let exit_code = match subcommand {
-                MUSQCommands::Auth {
-                    session_id: _,
-                    r#type: _,
-                } => 0,
+                MUSQCommands::Auth { .. } => 0,
                 MUSQCommands::Account => {
                     r#impl::musq::accounts::musq_account_info(&client, &config, &t).await
                 }
                 MUSQCommands::Accounts => 0,
+                MUSQCommands::Pool { .. } => 0,
                 MUSQCommands::AutoDownload {
-                    title_id,
-                    no_purchase,
-                    start_from,
-                    end_until,
-                    no_paid_coins,
-                    no_xp_coins,
-                    quality,
-                    output,
-                    only_check_folder,
+                    title_id, output, ..
                 } => {
-                    let mu_config = MUDownloadCliConfig {
-                        auto_purchase: !no_purchase,
-                        no_input: true,
-                        quality,
-                        start_from,
-                        end_at: end_until,
-                        no_paid_point: no_paid_coins,
-                        no_xp_point: no_xp_coins,
-                        only_check_folder,
-                        ..Default::default()
-                    };
-
                     r#impl::musq::download::musq_download(
                         title_id,
-                        mu_config,
+                        auto_config,
                         output.unwrap_or_else(get_default_download_dir),
                         &client,
                         &mut t_mut,
@@ -209,6 +262,8 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     show_all,
                     auto_purchase,
                     quality,
+                    drop_promo,
+                    embed_metadata,
                     output,
                 } => {
                     let mu_config = MUDownloadCliConfig {
@@ -216,6 +271,8 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         show_all,
                         chapter_ids: chapters.unwrap_or_default(),
                         quality,
+                        drop_promo,
+                        embed_metadata,
                         ..Default::default()
                     };
 
@@ -234,6 +291,9 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 MUSQCommands::History => {
                     r#impl::musq::favorites::musq_my_history(&client, &config, &t).await
                 }
+                MUSQCommands::HistoryPoints { validity } => {
+                    r#impl::musq::points::musq_point_history(&client, &config, validity, &t).await
+                }
                 MUSQCommands::Info {
                     title_id,
                     show_chapters,
@@ -258,8 +318,20 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     r#impl::musq::rankings::musq_home_rankings(&client, &config, &t).await
                 }
                 MUSQCommands::Revoke => r#impl::musq::accounts::musq_account_revoke(&config, &t),
-                MUSQCommands::Search { query } => {
-                    r#impl::musq::manga::musq_search(query.as_str(), &client, &t).await
+                MUSQCommands::Search { query, tag } => match (query, tag) {
+                    (_, Some(tag)) => r#impl::musq::manga::musq_search_tag(tag, &client, &t).await,
+                    (Some(query), None) => {
+                        r#impl::musq::manga::musq_search(query.as_str(), &client, &t).await
+                    }
+                    (None, None) => 1,
+                },
+                MUSQCommands::Tags { title_ids } => {
+                    let title_ids: Vec<u64> = title_ids
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|&id| id as u64)
+                        .collect();
+                    r#impl::musq::manga::musq_tags(title_ids, &client, &t).await
                 }
                 MUSQCommands::Weekly { weekday } => {
                     let weekday: WeeklyCode = match weekday {
@@ -277,31 +349,114 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             account_id,
             subcommand,
         } => {
+            // shared by the single account and the account pool auto download
+            let auto_config = match &subcommand {
+                KMKCCommands::AutoDownload {
+                    no_purchase,
+                    start_from,
+                    end_until,
+                    no_ticket,
+                    no_point,
+                    claim_bonus,
+                    bonus_limit,
+                    embed_metadata,
+                    parallel,
+                    threads,
+                    only_check_folder,
+                    ..
+                } => KMDownloadCliConfig {
+                    auto_purchase: !no_purchase,
+                    no_input: true,
+                    start_from: *start_from,
+                    end_at: *end_until,
+                    no_point: *no_point,
+                    no_ticket: *no_ticket,
+                    parallel: *parallel,
+                    threads: max_threads(*threads),
+                    only_check_folder: *only_check_folder,
+                    embed_metadata: *embed_metadata,
+                    claim_bonus: *claim_bonus,
+                    bonus_limit: Some(*bonus_limit),
+                    ..Default::default()
+                },
+                _ => KMDownloadCliConfig::default(),
+            };
+
             let early_exit = match subcommand.clone() {
                 KMKCCommands::Auth {
                     email,
                     password,
+                    password_file,
+                    password_stdin,
                     r#type,
+                    no_input,
                 } => Some(
-                    r#impl::kmkc::accounts::kmkc_account_login(email, password, r#type, &t).await,
+                    r#impl::kmkc::accounts::kmkc_account_login(
+                        email,
+                        CredentialArg::new(password, password_file, password_stdin),
+                        r#type,
+                        no_input,
+                        &t,
+                    )
+                    .await,
                 ),
                 KMKCCommands::AuthMobile {
                     user_id,
                     hash_key,
+                    hash_key_file,
+                    hash_key_stdin,
                     r#type,
+                    no_input,
                 } => Some(
                     r#impl::kmkc::accounts::kmkc_account_login_mobile(
-                        user_id, hash_key, r#type, &t,
+                        user_id,
+                        CredentialArg::new(hash_key, hash_key_file, hash_key_stdin),
+                        r#type,
+                        no_input,
+                        &t,
                     )
                     .await,
                 ),
-                KMKCCommands::AuthWeb { cookies } => {
-                    Some(r#impl::kmkc::accounts::kmkc_account_login_web(cookies, &t).await)
-                }
+                KMKCCommands::AuthWeb { cookies, no_input } => Some(
+                    r#impl::kmkc::accounts::kmkc_account_login_web(cookies, no_input, &t).await,
+                ),
                 KMKCCommands::AuthAdapt { r#type } => {
                     Some(r#impl::kmkc::accounts::kmkc_account_login_adapt(r#type, &t).await)
                 }
                 KMKCCommands::Accounts => Some(r#impl::kmkc::accounts::kmkc_accounts(&t)),
+                KMKCCommands::AutoDownload {
+                    title_id,
+                    output,
+                    pool: Some(pool),
+                    ..
+                } => {
+                    let source = r#impl::kmkc::pool::KMPool {
+                        title_id,
+                        dl_config: auto_config.clone(),
+                        output_dir: output.unwrap_or_else(get_default_download_dir),
+                    };
+
+                    Some(
+                        r#impl::pool::pool_download(
+                            &source,
+                            &pool,
+                            parsed_proxy.clone(),
+                            &mut t_mut,
+                        )
+                        .await,
+                    )
+                }
+                KMKCCommands::Pool {
+                    name,
+                    account_ids,
+                    remove,
+                } => Some(r#impl::pool::account_pool_manage(
+                    Implementations::Kmkc,
+                    name,
+                    account_ids,
+                    remove,
+                    &t,
+                )),
                 _ => None,
             };
 
@@ -329,51 +484,24 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 client
             };
 
+            // revoking the account should not write the config back
+            let save_session = !matches!(subcommand, KMKCCommands::Revoke);
             let exit_code = match subcommand {
-                KMKCCommands::Auth {
-                    email: _,
-                    password: _,
-                    r#type: _,
-                } => 0,
-                KMKCCommands::AuthMobile {
-                    user_id: _,
-                    hash_key: _,
-                    r#type: _,
-                } => 0,
-                KMKCCommands::AuthWeb { cookies: _ } => 0,
+                KMKCCommands::Auth { .. } => 0,
+                KMKCCommands::AuthMobile { .. } => 0,
+                KMKCCommands::AuthWeb { .. } => 0,
                 KMKCCommands::AuthAdapt { r#type: _ } => 0,
                 KMKCCommands::Account => {
                     r#impl::kmkc::accounts::kmkc_account_info(&client, &config, &t).await
                 }
                 KMKCCommands::Accounts => 0,
+                KMKCCommands::Pool { .. } => 0,
                 KMKCCommands::AutoDownload {
-                    title_id,
-                    no_purchase,
-                    start_from,
-                    end_until,
-                    no_ticket,
-                    no_point,
-                    output,
-                    parallel,
-                    threads,
-                    only_check_folder,
+                    title_id, output, ..
                 } => {
-                    let main_config = KMDownloadCliConfig {
-                        auto_purchase: !no_purchase,
-                        no_input: true,
-                        start_from,
-                        end_at: end_until,
-                        no_point,
-                        no_ticket,
-                        parallel,
-                        threads: max_threads(threads),
-                        only_check_folder,
-                        ..Default::default()
-                    };
-
                     r#impl::kmkc::download::kmkc_download(
                         title_id,
-                        main_config,
+                        auto_config,
                         output.unwrap_or_else(get_default_download_dir),
                         &client,
                         &config,
@@ -384,11 +512,23 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 KMKCCommands::Balance => {
                     r#impl::kmkc::accounts::kmkc_balance(&client, &config, &t).await
                 }
+                KMKCCommands::ExportCookies { output } => {
+                    r#impl::kmkc::accounts::kmkc_account_export_cookies(
+                        &client, &config, output, &t,
+                    )
+                }
+                KMKCCommands::ClaimBonus { title_ids, limit } => {
+                    r#impl::kmkc::bonus::kmkc_claim_bonus(title_ids, limit, &client, &config, &t)
+                        .await
+                }
                 KMKCCommands::Download {
                     title_id,
                     chapters,
                     show_all,
                     auto_purchase,
+                    claim_bonus,
+                    bonus_limit,
+                    embed_metadata,
                     output,
                     parallel,
                     threads,
@@ -399,6 +539,9 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         chapter_ids: chapters.unwrap_or_default(),
                         parallel,
                         threads: max_threads(threads),
+                        embed_metadata,
+                        claim_bonus,
+                        bonus_limit: Some(bonus_limit),
                         ..Default::default()
                     };
 
@@ -455,6 +598,10 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 }
             };
 
+            if save_session {
+                r#impl::kmkc::accounts::save_session_config(&client, &config);
+            }
+
             Ok(exit_code)
         }
         ToshoCommands::Amap {
@@ -462,9 +609,21 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             subcommand,
         } => {
             let early_exit = match subcommand.clone() {
-                AMAPCommands::Auth { email, password } => {
-                    Some(r#impl::amap::accounts::amap_account_login(email, password, &t).await)
-                }
+                AMAPCommands::Auth {
+                    email,
+                    password,
+                    password_file,
+                    password_stdin,
+                    no_input,
+                } => Some(
+                    r#impl::amap::accounts::amap_account_login(
+                        email,
+                        CredentialArg::new(password, password_file, password_stdin),
+                        no_input,
+                        &t,
+                    )
+                    .await,
+                ),
                 AMAPCommands::Accounts => Some(r#impl::amap::accounts::amap_accounts(&t)),
                 _ => None,
             };
@@ -494,10 +653,7 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             };
 
             let exit_code = match subcommand {
-                AMAPCommands::Auth {
-                    email: _,
-                    password: _,
-                } => 0,
+                AMAPCommands::Auth { .. } => 0,
                 AMAPCommands::Account => {
                     r#impl::amap::accounts::amap_account_info(&client, &config, &t).await
                 }
@@ -509,6 +665,9 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     end_until,
                     no_paid_ticket,
                     no_premium_ticket,
+                    embed_metadata,
+                    write_volume_map,
+                    volumes,
                     output,
                     only_check_folder,
                 } => {
@@ -520,6 +679,9 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         no_premium: no_paid_ticket,
                         no_purchased: no_premium_ticket,
                         only_check_folder,
+                        embed_metadata,
+                        write_volume_map,
+                        volumes: volumes.unwrap_or_default(),
                         ..Default::default()
                     };
 
@@ -544,12 +706,18 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     chapters,
                     show_all,
                     auto_purchase,
+                    embed_metadata,
+                    write_volume_map,
+                    volumes,
                     output,
                 } => {
                     let dl_config = AMDownloadCliConfig {
                         auto_purchase,
                         show_all,
                         chapter_ids: chapters.unwrap_or_default(),
+                        embed_metadata,
+                        write_volume_map,
+                        volumes: volumes.unwrap_or_default(),
                         ..Default::default()
                     };
 
@@ -566,6 +734,20 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 AMAPCommands::Favorites => {
                     r#impl::amap::favorites::amap_my_favorites(&client, &config, &t).await
                 }
+                AMAPCommands::FreeDaily { titles, output } => {
+                    r#impl::amap::free_daily::amap_free_daily(
+                        titles
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|id| id as u64)
+                            .collect(),
+                        output.unwrap_or_else(get_default_download_dir),
+                        &client,
+                        &config,
+                        &mut t_mut,
+                    )
+                    .await
+                }
                 AMAPCommands::Info {
                     title_id,
                     show_chapters,
@@ -598,11 +780,21 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 SJVCommands::Auth {
                     email,
                     password,
+                    password_file,
+                    password_stdin,
                     mode,
                     platform,
+                    no_input,
                 } => Some(
-                    r#impl::sjv::accounts::sjv_account_login(email, password, mode, platform, &t)
-                        .await,
+                    r#impl::sjv::accounts::sjv_account_login(
+                        email,
+                        CredentialArg::new(password, password_file, password_stdin),
+                        mode,
+                        platform,
+                        no_input,
+                        &t,
+                    )
+                    .await,
                 ),
                 SJVCommands::Accounts => Some(r#impl::sjv::accounts::sjv_accounts(&t)),
                 _ => None,
@@ -633,18 +825,15 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             };
 
             let exit_code = match subcommand {
-                SJVCommands::Auth {
-                    email: _,
-                    password: _,
-                    mode: _,
-                    platform: _,
-                } => 0,
+                SJVCommands::Auth { .. } => 0,
                 SJVCommands::Account => r#impl::sjv::accounts::sjv_account_info(&config, &t).await,
                 SJVCommands::Accounts => 0,
                 SJVCommands::AutoDownload {
                     title_or_slug,
                     start_from,
                     end_until,
+                    embed_metadata,
+                    wait_reset,
                     output,
                     parallel,
                     threads,
@@ -657,6 +846,8 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         parallel,
                         threads: max_threads(threads),
                         only_check_folder,
+                        embed_metadata,
+                        wait_for_reset: wait_reset,
                         ..Default::default()
                     };
 
@@ -672,6 +863,8 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 SJVCommands::Download {
                     title_or_slug,
                     chapters,
+                    embed_metadata,
+                    wait_reset,
                     output,
                     parallel,
                     threads,
@@ -680,6 +873,8 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         chapter_ids: chapters.unwrap_or_default(),
                         parallel,
                         threads: max_threads(threads),
+                        embed_metadata,
+                        wait_for_reset: wait_reset,
                         ..Default::default()
                     };
 
@@ -718,10 +913,19 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 RBeanCommands::Auth {
                     email,
                     password,
+                    password_file,
+                    password_stdin,
                     platform,
+                    no_input,
                 } => Some(
-                    r#impl::rbean::accounts::rbean_account_login(email, password, platform, &t)
-                        .await,
+                    r#impl::rbean::accounts::rbean_account_login(
+                        email,
+                        CredentialArg::new(password, password_file, password_stdin),
+                        platform,
+                        no_input,
+                        &t,
+                    )
+                    .await,
                 ),
                 RBeanCommands::Accounts => Some(r#impl::rbean::accounts::rbean_accounts(&t)),
                 _ => None,
@@ -754,17 +958,14 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             client.set_expiry_at(Some(config.expiry));
 
             let exit_code = match subcommand {
-                RBeanCommands::Auth {
-                    email: _,
-                    password: _,
-                    platform: _,
-                } => 0,
+                RBeanCommands::Auth { .. } => 0,
                 RBeanCommands::Account => {
                     r#impl::rbean::accounts::rbean_account_info(&mut client, &config, &t).await
                 }
                 RBeanCommands::Accounts => 0,
                 RBeanCommands::AutoDownload {
                     uuid,
+                    embed_metadata,
                     output,
                     format,
                     quality,
@@ -779,6 +980,7 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         quality,
                         threads: max_threads(threads),
                         only_check_folder,
+                        embed_metadata,
                         ..Default::default()
                     };
                     r#impl::rbean::download::rbean_download(
@@ -794,6 +996,7 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 RBeanCommands::Download {
                     uuid,
                     chapters,
+                    embed_metadata,
                     output,
                     format,
                     quality,
@@ -806,6 +1009,7 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         parallel,
                         quality,
                         threads: max_threads(threads),
+                        embed_metadata,
                         ..Default::default()
                     };
                     r#impl::rbean::download::rbean_download(
@@ -834,6 +1038,20 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     )
                     .await
                 }
+                RBeanCommands::Publisher { slug, limit, sort } => {
+                    r#impl::rbean::publishers::rbean_publisher(
+                        &slug,
+                        limit,
+                        sort,
+                        &mut client,
+                        &config,
+                        &t,
+                    )
+                    .await
+                }
+                RBeanCommands::Publishers => {
+                    r#impl::rbean::publishers::rbean_publishers(&mut client, &config, &t).await
+                }
                 RBeanCommands::ReadList => {
                     r#impl::rbean::favorites::rbean_read_list(&mut client, &config, &t).await
                 }
@@ -860,9 +1078,21 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             app_version,
         } => {
             let early_exit = match subcommand.clone() {
-                MPlusCommands::Auth { session_id, r#type } => {
-                    Some(r#impl::mplus::accounts::mplus_auth_session(session_id, r#type, &t).await)
-                }
+                MPlusCommands::Auth {
+                    session_id,
+                    session_id_file,
+                    session_id_stdin,
+                    r#type,
+                    no_input,
+                } => Some(
+                    r#impl::mplus::accounts::mplus_auth_session(
+                        CredentialArg::new(session_id, session_id_file, session_id_stdin),
+                        r#type,
+                        no_input,
+                        &t,
+                    )
+                    .await,
+                ),
                 MPlusCommands::Accounts => Some(r#impl::mplus::accounts::mplus_accounts(&t)),
                 _ => None,
             };
@@ -894,10 +1124,7 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
             .with_app_version(app_version);
 
             let exit_code = match subcommand {
-                MPlusCommands::Auth {
-                    session_id: _,
-                    r#type: _,
-                } => 0,
+                MPlusCommands::Auth { .. } => 0,
                 MPlusCommands::Account => {
                     r#impl::mplus::accounts::mplus_account_info(&client, &config, &t).await
                 }
@@ -907,6 +1134,10 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     start_from,
                     end_until,
                     quality,
+                    drop_promo,
+                    save_comments,
+                    embed_metadata,
+                    languages,
                     output,
                     only_check_folder,
                 } => {
@@ -916,6 +1147,10 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         end_at: end_until,
                         quality,
                         only_check_folder,
+                        drop_promo,
+                        embed_metadata,
+                        save_comments,
+                        languages: languages.into_iter().map(Into::into).collect(),
                         ..Default::default()
                     };
 
@@ -928,17 +1163,39 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     )
                     .await
                 }
+                MPlusCommands::Comments {
+                    chapter_id,
+                    page,
+                    limit,
+                } => {
+                    r#impl::mplus::comments::mplus_comments(
+                        chapter_id,
+                        page as usize,
+                        limit as usize,
+                        &client,
+                        &t,
+                    )
+                    .await
+                }
                 MPlusCommands::Download {
                     title_id,
                     chapters,
                     show_all,
                     quality,
+                    drop_promo,
+                    save_comments,
+                    embed_metadata,
+                    languages,
                     output,
                 } => {
                     let mplus_config = MPDownloadCliConfig {
                         show_all,
                         chapter_ids: chapters.unwrap_or_default(),
                         quality,
+                        drop_promo,
+                        embed_metadata,
+                        save_comments,
+                        languages: languages.into_iter().map(Into::into).collect(),
                         ..Default::default()
                     };
 
@@ -954,6 +1211,9 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 MPlusCommands::Favorites => {
                     r#impl::mplus::favorites::mplus_my_favorites(&client, &config, &t).await
                 }
+                MPlusCommands::FreeTitles => {
+                    r#impl::mplus::plans::mplus_free_titles(&client, &t).await
+                }
                 MPlusCommands::Info {
                     title_id,
                     show_chapters,
@@ -994,24 +1254,82 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 NIDSCommands::Auth {
                     email,
                     password,
+                    password_file,
+                    password_stdin,
                     r#type,
+                    no_input,
                 } => Some(
                     r#impl::nids::accounts::nids_auth_email(
                         email,
-                        password,
+                        CredentialArg::new(password, password_file, password_stdin),
                         r#type,
                         parsed_proxy.as_ref(),
+                        no_input,
                         &t,
                     )
                     .await,
                 ),
                 NIDSCommands::AuthToken {
                     session_token,
+                    session_token_file,
+                    session_token_stdin,
                     r#type,
+                    no_input,
+                } => Some(
+                    r#impl::nids::accounts::nids_auth_session(
+                        CredentialArg::new(session_token, session_token_file, session_token_stdin),
+                        r#type,
+                        no_input,
+                        &t,
+                    )
+                    .await,
+                ),
+                NIDSCommands::Accounts => Some(r#impl::nids::accounts::nids_accounts(&t)),
+                NIDSCommands::Creator { creator_id, limit } => Some(
+                    r#impl::nids::creators::nids_get_creator(creator_id, limit, &clean_client, &t)
+                        .await,
+                ),
+                NIDSCommands::Creators {
+                    filters,
+                    query,
+                    limit,
+                    sort_by,
+                    direction,
                 } => {
-                    Some(r#impl::nids::accounts::nids_auth_session(session_token, r#type, &t).await)
+                    let base_filter = tosho_nids::Filter::new()
+                        .with_per_page(limit)
+                        .with_order(sort_by, direction.into());
+                    let base_filter = match query {
+                        // creators are searched by their display name
+                        Some(query) => base_filter.add_filter(
+                            tosho_nids::FilterType::Any("display_name".to_string()),
+                            query,
+                        ),
+                        None => base_filter,
+                    };
+                    let mut merged_filters = filters
+                        .unwrap_or_default()
+                        .into_iter()
+                        .fold(base_filter, |acc, (filt_type, filt_data)| {
+                            acc.add_filter(filt_type, filt_data)
+                        });
+                    Some(
+                        r#impl::nids::creators::nids_get_creators(
+                            &mut merged_filters,
+                            &clean_client,
+                            &t,
+                        )
+                        .await,
+                    )
                 }
-                NIDSCommands::Accounts => Some(r#impl::nids::accounts::nids_accounts(&t)),
+                NIDSCommands::ExportPanels {
+                    issue_dir,
+                    format,
+                    width,
+                    output,
+                } => Some(r#impl::nids::panels::nids_export_panels(
+                    &issue_dir, format, width, output, &t,
+                )),
                 NIDSCommands::Issue {
                     issue_id,
                     with_marketplace,
@@ -1154,6 +1472,15 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         .await,
                     )
                 }
+                NIDSCommands::WatchCreator { creator_id, remove } => Some(
+                    r#impl::nids::creators::nids_watch_creator(
+                        creator_id,
+                        remove,
+                        &clean_client,
+                        &t,
+                    )
+                    .await,
+                ),
                 _ => None,
             };
 
@@ -1189,12 +1516,42 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     r#impl::nids::accounts::nids_account_info(&client, &config, &t).await
                 }
                 NIDSCommands::Accounts => 0,
+                NIDSCommands::Creator { .. } => 0,
+                NIDSCommands::Creators { .. } => 0,
+                NIDSCommands::Continue {
+                    count,
+                    output,
+                    parallel,
+                    threads,
+                    report,
+                    embed_metadata,
+                    quality,
+                } => {
+                    let dl_config = NIDownloadCliConfig {
+                        parallel,
+                        threads: max_threads(threads),
+                        report_finished: report,
+                        quality,
+                        embed_metadata,
+                        ..Default::default()
+                    };
+
+                    r#impl::nids::history::nids_continue_reading(
+                        count,
+                        dl_config,
+                        output.unwrap_or_else(get_default_download_dir),
+                        &client,
+                        &mut t_mut,
+                    )
+                    .await
+                }
                 NIDSCommands::Download {
                     issue_id,
                     output,
                     parallel,
                     threads,
                     report,
+                    embed_metadata,
                     quality,
                 } => {
                     let dl_config = NIDownloadCliConfig {
@@ -1203,6 +1560,8 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                         threads: max_threads(threads),
                         report,
                         quality,
+                        embed_metadata,
+                        report_finished: false,
                     };
 
                     r#impl::nids::download::nids_download(
@@ -1214,6 +1573,10 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     )
                     .await
                 }
+                NIDSCommands::ExportPanels { .. } => 0,
+                NIDSCommands::History => {
+                    r#impl::nids::history::nids_reading_history(&client, &t).await
+                }
                 NIDSCommands::Issue { .. } => 0,
                 NIDSCommands::Issues { .. } => 0,
                 NIDSCommands::Marketplace { .. } => 0,
@@ -1273,10 +1636,57 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 NIDSCommands::Revoke => r#impl::nids::accounts::nids_account_revoke(&config, &t),
                 NIDSCommands::SeriesRun { .. } => 0,
                 NIDSCommands::SeriesRuns { .. } => 0,
+                NIDSCommands::Watch => {
+                    r#impl::nids::creators::nids_check_watched_creators(&client, &t).await
+                }
+                NIDSCommands::WatchCreator { .. } => 0,
             };
 
             Ok(exit_code)
         }
+        ToshoCommands::Calendar { days, sources, ics } => {
+            let config = r#impl::calendar::CalendarConfig {
+                days,
+                sources,
+                ics,
+                proxy: parsed_proxy,
+            };
+
+            Ok(r#impl::calendar::tosho_calendar(config, &t).await)
+        }
+        ToshoCommands::Accounts { subcommand } => match subcommand {
+            r#impl::accounts::AccountsCommands::Check => {
+                Ok(r#impl::accounts::accounts_check(parsed_proxy, &t).await)
+            }
+        },
+        ToshoCommands::Daemon { config, log_file } => {
+            let config = r#impl::daemon::DaemonConfig {
+                schedule: config,
+                log_file,
+                verbose: cli.verbose,
+                proxy_url: cli.proxy,
+                proxy: parsed_proxy,
+            };
+
+            Ok(r#impl::daemon::tosho_daemon(config, &t).await)
+        }
+        #[cfg(feature = "with-api")]
+        ToshoCommands::Serve {
+            bind,
+            token,
+            output_root,
+        } => {
+            let config = r#impl::api::ApiConfig {
+                bind,
+                token,
+                output_root: output_root.unwrap_or_else(get_default_download_dir),
+                verbose: cli.verbose,
+                proxy_url: cli.proxy,
+                proxy: parsed_proxy,
+            };
+
+            Ok(r#impl::api::tosho_api_serve(config, &t).await)
+        }
         ToshoCommands::Tools { subcommand } => {
             let exit_code = match subcommand {
                 ToolsCommands::AutoMerge {
@@ -1295,6 +1705,20 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                 ToolsCommands::ClearCache => {
                     r#impl::tools::cache::tools_clear_cache(&mut t_mut).await
                 }
+                ToolsCommands::Dedupe {
+                    input_folder,
+                    min_chapters,
+                    threshold,
+                    remove,
+                } => {
+                    let config = r#impl::tools::dedupe::ToolsDedupeConfig {
+                        min_chapters,
+                        threshold,
+                        remove,
+                    };
+
+                    r#impl::tools::dedupe::tools_dedupe(&input_folder, config, &mut t_mut).await
+                }
                 ToolsCommands::Merge {
                     input_folder,
                     ignore_manual_merge,
@@ -1307,6 +1731,21 @@ async fn entrypoint(cli: ToshoCli) -> color_eyre::Result<ExitCode> {
                     r#impl::tools::merger::tools_split_merge(&input_folder, config, &mut t_mut)
                         .await
                 }
+                ToolsCommands::Verify {
+                    input_folder,
+                    redownload,
+                    account_id,
+                    source,
+                } => {
+                    let config = r#impl::tools::verify::ToolsVerifyConfig {
+                        redownload,
+                        account_id,
+                        source,
+                        proxy: parsed_proxy,
+                    };
+
+                    r#impl::tools::verify::tools_verify(&input_folder, config, &mut t_mut).await
+                }
             };
 
             Ok(exit_code)
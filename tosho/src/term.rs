@@ -6,7 +6,7 @@ use std::{
 use anstream::println;
 use color_print::cformat;
 use indicatif::ProgressStyle;
-use inquire::{Confirm, MultiSelect, Select};
+use inquire::{Confirm, MultiSelect, Password, Select, Text};
 
 pub(crate) static IS_WIN_VT_SUPPORTED: LazyLock<bool> = LazyLock::new(|| {
     if ::supports_hyperlinks::on(::supports_hyperlinks::Stream::Stdout) {
@@ -118,6 +118,19 @@ impl Terminal {
             .unwrap_or(false)
     }
 
+    /// Do a text input prompt, the input is masked if `secret` is set
+    pub fn input(&self, prompt: &str, secret: bool) -> Option<String> {
+        let input = if secret {
+            Password::new(prompt)
+                .without_confirmation()
+                .prompt_skippable()
+        } else {
+            Text::new(prompt).prompt_skippable()
+        };
+
+        input.unwrap_or_default()
+    }
+
     /// Do a single choice prompt
     pub fn choice(&self, prompt: &str, choices: Vec<ConsoleChoice>) -> Option<ConsoleChoice> {
         let choice = Select::new(prompt, choices).prompt_skippable();
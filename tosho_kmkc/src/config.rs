@@ -174,6 +174,45 @@ impl KMConfigWeb {
     }
 }
 
+impl KMConfigWeb {
+    /// Export the config into a Netscape cookies file content.
+    ///
+    /// The result can be parsed back with [`KMConfigWeb::try_from`] or imported into a browser.
+    pub fn to_netscape(&self) -> ToshoResult<String> {
+        let mut lines = vec![
+            "# Netscape HTTP Cookie File".to_string(),
+            "# This file is generated by tosho, do not share it!".to_string(),
+        ];
+
+        let cookies = [
+            ("birthday", &self.birthday),
+            ("terms_of_service_adult", &self.tos_adult),
+            ("privacy_policy", &self.privacy),
+        ];
+        for (name, kv) in cookies {
+            let cookie = kv.try_to_cookie(name)?;
+            lines.push(format!(
+                "{}\tFALSE\t/\tTRUE\t{}\t{}\t{}",
+                BASE_HOST,
+                kv.expires,
+                name,
+                cookie.value()
+            ));
+        }
+
+        if !self.uwt.is_empty() {
+            lines.push(format!(
+                "#HttpOnly_{}\tFALSE\t/\tTRUE\t{}\tuwt\t{}",
+                BASE_HOST, self.birthday.expires, self.uwt
+            ));
+        }
+
+        let mut content = lines.join("\n");
+        content.push('\n');
+        Ok(content)
+    }
+}
+
 impl TryFrom<reqwest_cookie_store::CookieStore> for KMConfigWeb {
     type Error = ToshoError;
 
@@ -405,7 +444,9 @@ impl TryFrom<String> for KMConfigWeb {
         let mut privacy = KMConfigWebKV::default();
 
         for cookie_line in value.lines() {
-            if cookie_line.starts_with('#') && !cookie_line.starts_with("#HttpOnly_") {
+            if cookie_line.trim().is_empty()
+                || (cookie_line.starts_with('#') && !cookie_line.starts_with("#HttpOnly_"))
+            {
                 continue;
             }
 
@@ -464,6 +505,24 @@ mod tests {
         assert_eq!(decoded_cookie, "{\"value\":123,\"expires\":123}");
     }
 
+    #[test]
+    fn test_netscape_roundtrip() {
+        let config = KMConfigWeb::new(
+            "uwt-token",
+            KMConfigWebKV::new("1998-01", 1900000000),
+            KMConfigWebKV::new("1", 1900000001),
+            KMConfigWebKV::new("1", 1900000002),
+        );
+
+        let exported = config.to_netscape().unwrap();
+        let parsed = KMConfigWeb::try_from(exported).unwrap();
+        assert_eq!(parsed.uwt(), "uwt-token");
+        assert_eq!(parsed.birthday().value(), "1998-01");
+        assert_eq!(parsed.birthday().expires(), 1900000000);
+        assert_eq!(parsed.tos_adult().value(), "1");
+        assert_eq!(parsed.privacy().expires(), 1900000002);
+    }
+
     #[test]
     fn test_mobile_platform_u8() {
         assert_eq!(KMConfigMobilePlatform::Apple as u8, 1);
@@ -83,6 +83,18 @@ impl MPClient {
         new_client
     }
 
+    /// Override the language for the client.
+    ///
+    /// This will clone the client and return a new client with the language overridden.
+    ///
+    /// # Arguments
+    /// * `language` - The language to use for the client.
+    pub fn with_language(&self, language: Language) -> Self {
+        let mut new_client = self.clone();
+        new_client.language = language;
+        new_client
+    }
+
     fn make_client(
         secret: impl Into<String>,
         language: Language,
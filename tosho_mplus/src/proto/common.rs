@@ -10,7 +10,7 @@ use tosho_macros::AutoGetter;
 
 use crate::helper::SubscriptionPlan;
 
-use super::enums::{ErrorAction, Language};
+use super::enums::{ErrorAction, Language, TransitionMethod};
 
 /// A popup button action
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -137,12 +137,27 @@ impl ErrorResponse {
     }
 }
 
+/// The action of a banner.
+#[derive(Clone, AutoGetter, PartialEq, ::prost::Message)]
+pub struct TransitionAction {
+    /// How the link is opened.
+    #[prost(enumeration = "TransitionMethod", tag = "1")]
+    #[skip_field]
+    method: i32,
+    /// The link of the banner.
+    #[prost(string, tag = "2")]
+    url: ::prost::alloc::string::String,
+}
+
 /// The banner data.
 #[derive(Clone, AutoGetter, PartialEq, ::prost::Message)]
 pub struct Banner {
     /// The banner image.
     #[prost(string, tag = "1")]
     image: ::prost::alloc::string::String,
+    /// The action when the banner is clicked.
+    #[prost(message, optional, tag = "2")]
+    action: ::core::option::Option<TransitionAction>,
     /// The associated ID.
     #[prost(uint64, optional, tag = "3")]
     #[skip_field]
@@ -183,6 +183,22 @@ pub enum ChapterType {
     Unrecognized = -1,
 }
 
+/// How the link of a banner is opened
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::tosho_macros::ProstEnumUnrecognized,
+)]
+pub enum TransitionMethod {
+    /// Push into the current view
+    Push = 0,
+    /// Open as a modal
+    Modal = 1,
+    /// Open in the external browser
+    External = 2,
+    /// An error has occurred.
+    #[invalid_enum]
+    Unrecognized = -1,
+}
+
 /// Enums for update profile result
 #[derive(
     Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::tosho_macros::ProstEnumUnrecognized,
@@ -14,7 +14,7 @@ pub use config::*;
 use constants::{API_HOST, BASE_API, IMAGE_HOST, TOKEN_AUTH};
 use models::{
     ChapterDetailsResponse, ChapterListResponse, ChapterPageDetailsResponse, HomeResponse, Manga,
-    MangaListResponse, Publisher, ReadingListItem, SortOption,
+    MangaFilters, MangaListResponse, Publisher, ReadingListItem, SortOption,
 };
 use serde_json::json;
 
@@ -246,7 +246,7 @@ impl RBClient {
     }
 
     /// Get the manga filters for searching manga.
-    pub async fn get_manga_filters(&mut self) -> ToshoResult<Manga> {
+    pub async fn get_manga_filters(&mut self) -> ToshoResult<MangaFilters> {
         self.request(reqwest::Method::GET, "/manga/filters/v0", None)
             .await
     }
@@ -315,17 +315,44 @@ impl RBClient {
         offset: Option<u32>,
         count: Option<u32>,
         sort: Option<SortOption>,
+    ) -> ToshoResult<MangaListResponse> {
+        self.get_manga_list(query.as_ref(), "", offset, count, sort)
+            .await
+    }
+
+    /// Get the manga catalogue of a specific publisher.
+    ///
+    /// # Arguments
+    /// * `slug` - The slug of the publisher.
+    /// * `offset` - The offset of the result, default to `0`
+    /// * `count` - The count of the result, default to `999`
+    /// * `sort` - The sort option of the result, default to [`SortOption::Alphabetical`]
+    pub async fn get_publisher_mangas(
+        &mut self,
+        slug: impl AsRef<str>,
+        offset: Option<u32>,
+        count: Option<u32>,
+        sort: Option<SortOption>,
+    ) -> ToshoResult<MangaListResponse> {
+        self.get_manga_list("", slug.as_ref(), offset, count, sort)
+            .await
+    }
+
+    /// Get the manga list with the search string and publisher filter.
+    async fn get_manga_list(
+        &mut self,
+        query: &str,
+        publisher_slug: &str,
+        offset: Option<u32>,
+        count: Option<u32>,
+        sort: Option<SortOption>,
     ) -> ToshoResult<MangaListResponse> {
         let offset = offset.unwrap_or(0);
         let count = count.unwrap_or(999);
         let sort = sort.unwrap_or(SortOption::Alphabetical);
 
         let query_param = format!(
-            "sort={}&offset={}&count={}&tags=&search_string={}&publisher_slug=",
-            sort,
-            offset,
-            count,
-            query.as_ref()
+            "sort={sort}&offset={offset}&count={count}&tags=&search_string={query}&publisher_slug={publisher_slug}",
         );
 
         self.request(